@@ -0,0 +1,91 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Local lookup and content cache commands
+
+use antns::storage::{CheckpointCache, ContentCache};
+use anyhow::{Context, Result};
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    /// Remove all verified history checkpoints and cached proxy content
+    Clear,
+    /// Remove the verified history checkpoint of one domain
+    Invalidate {
+        /// Domain name; a subdomain invalidates its registered domain
+        domain: String,
+    },
+    /// Show checkpoint and content cache locations and sizes
+    Status,
+}
+
+pub async fn execute(command: CacheCommands) -> Result<()> {
+    match command {
+        CacheCommands::Clear => clear_command().await,
+        CacheCommands::Invalidate { domain } => invalidate_command(domain).await,
+        CacheCommands::Status => status_command().await,
+    }
+}
+
+async fn clear_command() -> Result<()> {
+    let cache = CheckpointCache::open_default().context("Failed to open checkpoint cache")?;
+
+    let removed = cache.clear().context("Failed to clear checkpoint cache")?;
+
+    println!(
+        "✓ Removed {} checkpoint(s) from {}",
+        removed,
+        cache.dir().display()
+    );
+    println!("The next lookup of each domain will verify its full history.");
+
+    let content = ContentCache::open_default().context("Failed to open content cache")?;
+    let removed = content.clear().context("Failed to clear content cache")?;
+    println!(
+        "✓ Removed {} cached file(s) from {}",
+        removed,
+        content.dir().display()
+    );
+
+    Ok(())
+}
+
+async fn invalidate_command(domain: String) -> Result<()> {
+    // Checkpoints are kept per register, i.e. per registered domain
+    let name = antns::DomainName::parse(&domain)?;
+    let cache = CheckpointCache::open_default().context("Failed to open checkpoint cache")?;
+
+    // Domains registered before names were lowercased use the as-typed register
+    for base in [name.base(), name.typed_base()] {
+        let register = antns::register::get_register_address_for_domain(base)
+            .context("Failed to derive register address")?;
+        cache
+            .remove(&register.to_hex())
+            .context("Failed to remove checkpoint")?;
+    }
+
+    println!("✓ Invalidated checkpoint for {}", name.base());
+    println!("The next lookup of the domain will verify its full history.");
+
+    Ok(())
+}
+
+async fn status_command() -> Result<()> {
+    let cache = CheckpointCache::open_default().context("Failed to open checkpoint cache")?;
+
+    let (count, bytes) = cache.usage().context("Failed to read checkpoint cache")?;
+
+    println!("Checkpoint cache: {}", cache.dir().display());
+    println!("  Checkpoints: {}", count);
+    println!("  Size: {:.1} KiB", bytes as f64 / 1024.0);
+
+    let content = ContentCache::open_default().context("Failed to open content cache")?;
+    let (count, bytes) = content.usage().context("Failed to read content cache")?;
+
+    println!("Content cache: {}", content.dir().display());
+    println!("  Files: {}", count);
+    println!("  Size: {:.1} MiB", bytes as f64 / (1024.0 * 1024.0));
+
+    Ok(())
+}
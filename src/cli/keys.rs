@@ -101,10 +101,13 @@ async fn status_command() -> Result<()> {
         Ok(_) => {
             println!("✓ Vault backup exists (not restored, just checked)");
         }
-        Err(_) => {
+        Err(antns::AntnsError::VaultNotFound) => {
             println!("✗ No vault backup found");
             println!("  Run 'antns keys backup' to create one");
         }
+        Err(e) => {
+            println!("✗ Could not check vault backup: {}", e);
+        }
     }
 
     Ok(())
@@ -3,6 +3,7 @@
 
 //! CLI command implementations
 
+pub mod cache;
 pub mod keys;
 pub mod names;
 pub mod records;
@@ -3,6 +3,9 @@
 
 //! Domain name management commands
 
+use antns::constants::DEFAULT_LOOKUP_CONCURRENCY;
+use antns::storage::CheckpointCache;
+use antns::{LookupOptions, LookupStats, VerifyMode};
 use anyhow::{Context, Result};
 use autonomi::Client;
 use clap::Subcommand;
@@ -16,13 +19,38 @@ pub enum NamesCommands {
     },
     /// Look up a domain's records
     Lookup {
-        /// Domain name to look up
+        /// Domain name to look up (e.g. mydomain.ant or www.mydomain.ant)
+        domain: String,
+        /// Number of register entries to download at the same time
+        #[arg(long, default_value_t = DEFAULT_LOOKUP_CONCURRENCY)]
+        concurrency: usize,
+        /// Print entry counts and timings for the lookup
+        #[arg(long)]
+        stats: bool,
+        /// Walk the full history instead of resuming from the local checkpoint
+        #[arg(long)]
+        no_cache: bool,
+    },
+    /// Check whether a domain is available
+    Check {
+        /// Domain name to check
+        domain: String,
+    },
+    /// Show ownership and registration details for a domain
+    Whois {
+        /// Domain name to inspect
         domain: String,
     },
     /// View domain history
     History {
         /// Domain name
         domain: String,
+        /// Number of register entries to download at the same time
+        #[arg(long, default_value_t = DEFAULT_LOOKUP_CONCURRENCY)]
+        concurrency: usize,
+        /// Print entry counts and timings for the walk
+        #[arg(long)]
+        stats: bool,
     },
     /// List locally owned domains
     List,
@@ -31,6 +59,11 @@ pub enum NamesCommands {
         /// Domain name to export
         domain: String,
     },
+    /// Generate a key for a delegated subdomain
+    Keygen {
+        /// Subdomain to generate the key for (e.g. team.company.ant)
+        domain: String,
+    },
     /// Import domain private key
     Import {
         /// Domain name
@@ -41,13 +74,25 @@ pub enum NamesCommands {
     },
 }
 
-pub async fn execute(command: NamesCommands) -> Result<()> {
+pub async fn execute(command: NamesCommands, verify_mode: VerifyMode) -> Result<()> {
     match command {
         NamesCommands::Register { domain } => register_command(domain).await,
-        NamesCommands::Lookup { domain } => lookup_command(domain).await,
-        NamesCommands::History { domain } => history_command(domain).await,
+        NamesCommands::Lookup {
+            domain,
+            concurrency,
+            stats,
+            no_cache,
+        } => lookup_command(domain, concurrency, stats, no_cache, verify_mode).await,
+        NamesCommands::Check { domain } => check_command(domain, verify_mode).await,
+        NamesCommands::Whois { domain } => whois_command(domain, verify_mode).await,
+        NamesCommands::History {
+            domain,
+            concurrency,
+            stats,
+        } => history_command(domain, concurrency, stats, verify_mode).await,
         NamesCommands::List => list_command().await,
         NamesCommands::Export { domain } => export_command(domain).await,
+        NamesCommands::Keygen { domain } => keygen_command(domain).await,
         NamesCommands::Import { domain, key } => import_command(domain, key).await,
     }
 }
@@ -70,9 +115,29 @@ async fn register_command(domain: String) -> Result<()> {
     let payment = autonomi::client::payment::PaymentOption::from(&wallet);
 
     // Register domain
-    let registration = antns::register_domain(&client, &domain, payment)
-        .await
-        .context("Failed to register domain")?;
+    let registration = match antns::register_domain(&client, &domain, payment).await {
+        Ok(registration) => registration,
+        Err(antns::AntnsError::AlreadyOwnedByOther {
+            owner_public_key, ..
+        }) => {
+            println!("\n✗ Domain '{}' is already registered.", domain);
+            println!("Owner public key: {}", owner_public_key);
+            println!("Nothing was paid for.");
+            anyhow::bail!("Domain '{}' is already registered", domain);
+        }
+        Err(antns::AntnsError::RegistrationLost {
+            owner_public_key, ..
+        }) => {
+            println!(
+                "\n✗ Domain '{}' was registered by someone else first.",
+                domain
+            );
+            println!("Owner public key: {}", owner_public_key);
+            println!("No private key was saved.");
+            anyhow::bail!("Registration of '{}' lost to another owner", domain);
+        }
+        Err(e) => return Err(e).context("Failed to register domain"),
+    };
 
     // Extract keypair components before moving
     let verifying_key = registration.owner_key.verifying_key();
@@ -80,7 +145,7 @@ async fn register_command(domain: String) -> Result<()> {
 
     // Save keypair locally
     antns::crypto::save_keypair(
-        &domain,
+        &registration.domain,
         &antns::crypto::DomainKeypair {
             signing_key,
             verifying_key,
@@ -88,7 +153,15 @@ async fn register_command(domain: String) -> Result<()> {
     )
     .context("Failed to save keypair")?;
 
-    println!("\n✓ Domain registered successfully!");
+    if registration.ownership_verified {
+        println!("\n✓ Domain registered successfully!");
+    } else {
+        println!("\n⚠ Domain register created, but ownership could not be verified yet.");
+        println!(
+            "Run 'antns names history {}' later to confirm the owner key.",
+            domain
+        );
+    }
     println!("Register address: {}", registration.register_address);
     println!("Total cost: {} AttoTokens", registration.total_cost);
     println!("\nPrivate key saved to local storage.");
@@ -100,15 +173,81 @@ async fn register_command(domain: String) -> Result<()> {
     Ok(())
 }
 
-async fn lookup_command(domain: String) -> Result<()> {
+async fn lookup_command(
+    domain: String,
+    concurrency: usize,
+    show_stats: bool,
+    no_cache: bool,
+    verify_mode: VerifyMode,
+) -> Result<()> {
     println!("Looking up domain: {}\n", domain);
 
     let client = Client::init()
         .await
         .context("Failed to initialize Autonomi client")?;
 
-    match antns::lookup_domain_records(&client, &domain).await {
-        Ok(records) => {
+    let checkpoints = if no_cache {
+        None
+    } else {
+        match CheckpointCache::open_default() {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                tracing::warn!("Checkpoint cache unavailable: {:#}", e);
+                None
+            }
+        }
+    };
+    let options = LookupOptions {
+        concurrency,
+        checkpoints,
+        verify_mode,
+        ..Default::default()
+    };
+
+    let name = antns::DomainName::parse(&domain)?;
+
+    match antns::Resolver::with_options(&client, options)
+        .resolve_name(&name)
+        .await
+    {
+        Ok(resolved) if resolved.record_name != antns::ROOT_RECORD_NAME => {
+            // Subdomain: show the records named after it in the zone holding it
+            let zone = &resolved.zone.domain;
+            println!(
+                "Records named '{}' in zone '{}':\n",
+                resolved.record_name, zone
+            );
+            for record in resolved.records() {
+                println!("  {} {} {}", record.record_type, record.name, record.value);
+            }
+
+            match resolved.target() {
+                Some(target) => println!("\n✓ {} → {}", name, target),
+                None => {
+                    println!("\n✗ {} does not resolve", name);
+                    println!(
+                        "Add an ANT record named '{}' to '{}' to point it somewhere.",
+                        resolved.record_name, zone
+                    );
+                }
+            }
+            if show_stats {
+                print_lookup_stats(&resolved.stats);
+            }
+            Ok(())
+        }
+        Ok(resolved) => {
+            let zone = &resolved.zone;
+            if let antns::ZoneAuthority::Delegated { parent, delegation } = &zone.authority {
+                println!(
+                    "Zone '{}' is delegated by '{}' to key {}\n",
+                    zone.domain,
+                    parent,
+                    delegation.public_key_hex()
+                );
+            }
+
+            let records = zone.records();
             if records.is_empty() {
                 println!("Domain '{}' is registered but has no records.", domain);
                 println!(
@@ -124,32 +263,140 @@ async fn lookup_command(domain: String) -> Result<()> {
                     );
                 }
             }
+            if show_stats {
+                print_lookup_stats(&resolved.stats);
+            }
             Ok(())
         }
-        Err(e) => {
-            let err_msg = format!("{:#}", e);
-            if err_msg.contains("Timeout")
-                || err_msg.contains("not found")
-                || err_msg.contains("Register not found")
-            {
-                println!("✗ Domain not found: {}", domain);
-                println!("The domain may not be registered, or the network may be unreachable.");
-                Ok(()) // Don't error out, just inform the user
-            } else {
-                Err(e).context("Domain lookup failed")
-            }
+        Err(e) if e.is_not_found() => {
+            println!("✗ Domain not found: {}", domain);
+            println!("The domain is not registered.");
+            Ok(()) // Don't error out, just inform the user
+        }
+        Err(e) if e.is_retryable() => {
+            Err(e).context("Domain lookup failed; the network may be unreachable, try again")
+        }
+        Err(e) => Err(e).context("Domain lookup failed"),
+    }
+}
+
+async fn check_command(domain: String, verify_mode: VerifyMode) -> Result<()> {
+    println!("Checking domain: {}\n", domain);
+
+    let client = Client::init()
+        .await
+        .context("Failed to initialize Autonomi client")?;
+
+    let options = LookupOptions {
+        verify_mode,
+        ..Default::default()
+    };
+    let info = antns::inspect_domain_with(&client, &domain, &options)
+        .await
+        .context("Failed to check domain")?;
+
+    if info.status.is_available() {
+        println!("✓ Domain '{}' is available", domain);
+        println!("\nRegister it with: antns names register {}", domain);
+    } else {
+        println!("✗ Domain '{}' is taken ({})", domain, info.status);
+        if let Some(owner) = &info.owner_public_key {
+            println!("Owner public key: {}", owner);
+        }
+    }
+
+    warn_case_sensitive_registration(&client, &domain, &info.domain).await;
+
+    Ok(())
+}
+
+async fn whois_command(domain: String, verify_mode: VerifyMode) -> Result<()> {
+    println!("Inspecting domain: {}\n", domain);
+
+    let client = Client::init()
+        .await
+        .context("Failed to initialize Autonomi client")?;
+
+    let options = LookupOptions {
+        verify_mode,
+        ..Default::default()
+    };
+    let info = antns::inspect_domain_with(&client, &domain, &options)
+        .await
+        .context("Failed to inspect domain")?;
+
+    println!("Domain:           {}", info.domain);
+    println!("Status:           {}", info.status);
+    println!("Register address: {}", info.register_address);
+
+    if let Some(owner) = &info.owner_public_key {
+        println!("Owner public key: {}", owner);
+    }
+    if let Some(chunk) = &info.owner_chunk_address {
+        println!("Owner chunk:      {}", chunk);
+    }
+
+    if let Some(stats) = &info.stats {
+        println!("\nHistory:");
+        println!("  Total entries: {}", stats.total_entries);
+        println!("  Valid entries: {}", stats.valid_entries);
+        println!("  Spam entries: {}", stats.spam_entries);
+        println!("  Corrupted entries: {}", stats.invalid_entries);
+        println!("  Spam ratio: {:.1}%", stats.spam_ratio() * 100.0);
+    }
+
+    if !info.records.is_empty() {
+        println!("\nCurrent records:");
+        for (i, record) in info.records.iter().enumerate() {
+            println!(
+                "[{}] {} {} {}",
+                i, record.record_type, record.name, record.value
+            );
+        }
+    }
+
+    warn_case_sensitive_registration(&client, &domain, &info.domain).await;
+
+    Ok(())
+}
+
+/// Warn if `domain` was registered as typed before names were lowercased
+async fn warn_case_sensitive_registration(client: &Client, domain: &str, canonical: &str) {
+    match antns::find_case_sensitive_registration(client, domain).await {
+        Ok(Some(register)) => {
+            println!(
+                "\n⚠ '{}' was registered before names became case-insensitive (register {}).",
+                domain.strip_suffix('.').unwrap_or(domain),
+                register
+            );
+            println!(
+                "  Only lookups of this exact spelling find it, and registering '{}' would take it over.",
+                canonical
+            );
         }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to check for a case-sensitive registration: {:#}", e),
     }
 }
 
-async fn history_command(domain: String) -> Result<()> {
+async fn history_command(
+    domain: String,
+    concurrency: usize,
+    show_stats: bool,
+    verify_mode: VerifyMode,
+) -> Result<()> {
     println!("Fetching history for domain: {}\n", domain);
 
     let client = Client::init()
         .await
         .context("Failed to initialize Autonomi client")?;
 
-    let history = antns::get_domain_history(&client, &domain)
+    let options = LookupOptions {
+        concurrency,
+        verify_mode,
+        ..Default::default()
+    };
+    let (history, lookup_stats) = antns::get_domain_history_with(&client, &domain, &options)
         .await
         .context("Failed to fetch domain history")?;
 
@@ -166,6 +413,7 @@ async fn history_command(domain: String) -> Result<()> {
             antns::register::HistoryEntry::Records {
                 chunk_address,
                 records,
+                sequence,
                 signature: _,
                 is_valid,
             } => {
@@ -176,6 +424,11 @@ async fn history_command(domain: String) -> Result<()> {
                 };
                 println!("Entry {} ({}):", i + 1, status);
                 println!("  Chunk: {}", chunk_address);
+                match sequence {
+                    Some(sequence) => println!("  Sequence: {}", sequence),
+                    None if records.is_some() => println!("  Sequence: none (legacy)"),
+                    None => {}
+                }
 
                 if let Some(recs) = records {
                     for rec in recs {
@@ -199,9 +452,29 @@ async fn history_command(domain: String) -> Result<()> {
     println!("  Spam entries: {}", stats.spam_entries);
     println!("  Corrupted entries: {}", stats.invalid_entries);
 
+    if show_stats {
+        print_lookup_stats(&lookup_stats);
+    }
+
     Ok(())
 }
 
+fn print_lookup_stats(stats: &LookupStats) {
+    println!("\nLookup statistics:");
+    println!(
+        "  Entries: {} ({} valid, {} invalid)",
+        stats.entries, stats.valid_entries, stats.invalid_entries
+    );
+    if stats.cached_entries > 0 {
+        println!("  From checkpoint: {}", stats.cached_entries);
+    }
+    println!("  Concurrency: {}", stats.concurrency);
+    println!("  Register history: {:.2?}", stats.history_time);
+    println!("  Entry downloads: {:.2?}", stats.entries_time);
+    println!("  Signature checks: {:.2?}", stats.verify_time);
+    println!("  Total: {:.2?}", stats.total_time);
+}
+
 async fn list_command() -> Result<()> {
     println!("Locally owned domains:\n");
 
@@ -235,7 +508,52 @@ async fn export_command(domain: String) -> Result<()> {
     Ok(())
 }
 
+async fn keygen_command(domain: String) -> Result<()> {
+    let name = antns::DomainName::parse(&domain)?;
+    if name.is_root() {
+        anyhow::bail!(
+            "'{}' is a registered domain; use 'antns names register {}' instead",
+            name,
+            name
+        );
+    }
+    let domain = name.to_string();
+
+    if antns::crypto::load_keypair(&domain).is_ok() {
+        anyhow::bail!(
+            "A key for '{}' already exists; export it with 'antns names export {}'",
+            domain,
+            domain
+        );
+    }
+
+    let keypair = antns::crypto::DomainKeypair::generate();
+    antns::crypto::save_keypair(&domain, &keypair).context("Failed to save keypair")?;
+
+    let (label, parent) = domain.split_once('.').context("Subdomain has no parent")?;
+
+    println!("✓ Key generated for '{}'", domain);
+    println!("Public Key: {}", keypair.public_key_hex());
+    println!(
+        "\nAsk the owner of '{}' to delegate the name to this key:",
+        parent
+    );
+    println!(
+        "  antns records add --name {} DELEGATE {} {}",
+        parent,
+        label,
+        keypair.public_key_hex()
+    );
+    println!(
+        "\nThen publish records with: antns records add --name {} ANT . <address>",
+        domain
+    );
+
+    Ok(())
+}
+
 async fn import_command(domain: String, key: String) -> Result<()> {
+    let domain = antns::DomainName::parse(&domain)?.to_string();
     println!("Importing private key for domain: {}", domain);
 
     let key_bytes = hex::decode(&key).context("Invalid hex in private key")?;
@@ -3,6 +3,7 @@
 
 //! Domain records management commands
 
+use antns::{LookupOptions, VerifyMode};
 use anyhow::{Context, Result};
 use autonomi::Client;
 use clap::Subcommand;
@@ -20,12 +21,15 @@ pub enum RecordsCommands {
         /// Domain name
         #[arg(long)]
         name: String,
-        /// Record type (TEXT or ANT)
+        /// Record type (A, AAAA, CNAME, TXT, MX, SRV, CAA, ANT or DELEGATE)
         record_type: String,
         /// Record name (use . for root)
         record_name: String,
-        /// Record value
+        /// Record value (e.g. "10 mail.example.com" for MX)
         value: String,
+        /// Time to live in seconds
+        #[arg(long)]
+        ttl: Option<u32>,
     },
     /// Delete a record by index
     Delete {
@@ -42,24 +46,34 @@ pub enum RecordsCommands {
         name: String,
         /// Record index to update
         index: usize,
-        /// New record type (TEXT or ANT)
+        /// New record type (A, AAAA, CNAME, TXT, MX, SRV, CAA, ANT or DELEGATE)
         record_type: String,
         /// New record name (use . for root)
         record_name: String,
         /// New record value
         value: String,
+        /// Time to live in seconds
+        #[arg(long)]
+        ttl: Option<u32>,
+    },
+    /// Re-sign legacy records in the current signed format
+    Migrate {
+        /// Domain name
+        #[arg(long)]
+        name: String,
     },
 }
 
-pub async fn execute(command: RecordsCommands) -> Result<()> {
+pub async fn execute(command: RecordsCommands, verify_mode: VerifyMode) -> Result<()> {
     match command {
-        RecordsCommands::List { name } => list_command(name).await,
+        RecordsCommands::List { name } => list_command(name, verify_mode).await,
         RecordsCommands::Add {
             name,
             record_type,
             record_name,
             value,
-        } => add_command(name, record_type, record_name, value).await,
+            ttl,
+        } => add_command(name, record_type, record_name, value, ttl).await,
         RecordsCommands::Delete { name, index } => delete_command(name, index).await,
         RecordsCommands::Update {
             name,
@@ -67,11 +81,38 @@ pub async fn execute(command: RecordsCommands) -> Result<()> {
             record_type,
             record_name,
             value,
-        } => update_command(name, index, record_type, record_name, value).await,
+            ttl,
+        } => update_command(name, index, record_type, record_name, value, ttl).await,
+        RecordsCommands::Migrate { name } => migrate_command(name).await,
     }
 }
 
-async fn list_command(domain: String) -> Result<()> {
+/// Validate a record before anything is paid for
+///
+/// Returns the record with its type and value in canonical form.
+fn build_record(
+    domain: &str,
+    record_type: &str,
+    record_name: &str,
+    value: &str,
+    ttl: Option<u32>,
+) -> Result<antns::register::DnsRecord> {
+    let data = antns::RecordData::parse(record_type, value)?;
+
+    if record_name != antns::ROOT_RECORD_NAME {
+        antns::DomainName::parse(&format!("{}.{}", record_name, domain))?;
+    } else if data.record_type() == antns::RecordType::Delegate {
+        anyhow::bail!("DELEGATE records need a label, the domain itself cannot be delegated");
+    }
+
+    let record = antns::register::DnsRecord::new(record_name, data);
+    Ok(match ttl {
+        Some(ttl) => record.with_ttl(ttl),
+        None => record,
+    })
+}
+
+async fn list_command(domain: String, verify_mode: VerifyMode) -> Result<()> {
     println!("Listing records for domain: {}\n", domain);
 
     let client = Client::init()
@@ -79,8 +120,13 @@ async fn list_command(domain: String) -> Result<()> {
         .context("Failed to initialize Autonomi client")?;
 
     // Fetch current records
-    match antns::lookup_domain_records(&client, &domain).await {
-        Ok(records) => {
+    let options = LookupOptions {
+        verify_mode,
+        ..Default::default()
+    };
+    match antns::lookup_domain_document_with(&client, &domain, &options).await {
+        Ok((document, _)) => {
+            let records = document.map(|doc| doc.records).unwrap_or_default();
             if records.is_empty() {
                 println!("No records found for domain: {}", domain);
                 println!(
@@ -97,19 +143,14 @@ async fn list_command(domain: String) -> Result<()> {
             }
             Ok(())
         }
-        Err(e) => {
-            let err_msg = format!("{:#}", e);
-            if err_msg.contains("Timeout")
-                || err_msg.contains("not found")
-                || err_msg.contains("Register not found")
-            {
-                println!("\n✗ Domain not found: {}", domain);
-                println!("The domain may not be registered, or the network may be unreachable.");
-                Ok(())
-            } else {
-                Err(e).context("Failed to list domain records")
-            }
+        Err(e) if e.is_not_found() => {
+            println!("\n✗ Domain not found: {}", domain);
+            println!("The domain is not registered.");
+            Ok(())
         }
+        Err(e) if e.is_retryable() => Err(e)
+            .context("Failed to list domain records; the network may be unreachable, try again"),
+        Err(e) => Err(e).context("Failed to list domain records"),
     }
 }
 
@@ -118,6 +159,7 @@ async fn add_command(
     record_type: String,
     record_name: String,
     value: String,
+    ttl: Option<u32>,
 ) -> Result<()> {
     println!("Adding record to domain: {}", domain);
     println!(
@@ -125,10 +167,7 @@ async fn add_command(
         record_type, record_name, value
     );
 
-    // Validate record type
-    if record_type != "TEXT" && record_type != "ANT" {
-        anyhow::bail!("Invalid record type. Must be TEXT or ANT");
-    }
+    let record = build_record(&domain, &record_type, &record_name, &value, ttl)?;
 
     // Load keypair
     let keypair = antns::crypto::load_keypair(&domain)
@@ -147,13 +186,6 @@ async fn add_command(
     // Create payment option
     let payment = autonomi::client::payment::PaymentOption::from(&wallet);
 
-    // Create record
-    let record = antns::register::DnsRecord {
-        record_type,
-        name: record_name,
-        value,
-    };
-
     // Add record
     let cost = antns::add_domain_record(&client, &domain, record, &keypair.signing_key, payment)
         .await
@@ -202,6 +234,7 @@ async fn update_command(
     record_type: String,
     record_name: String,
     value: String,
+    ttl: Option<u32>,
 ) -> Result<()> {
     println!("Updating record {} for domain: {}", index, domain);
     println!(
@@ -209,10 +242,7 @@ async fn update_command(
         record_type, record_name, value
     );
 
-    // Validate record type
-    if record_type != "TEXT" && record_type != "ANT" {
-        anyhow::bail!("Invalid record type. Must be TEXT or ANT");
-    }
+    let record = build_record(&domain, &record_type, &record_name, &value, ttl)?;
 
     // Load keypair
     let keypair = antns::crypto::load_keypair(&domain)
@@ -231,13 +261,6 @@ async fn update_command(
     // Create payment option
     let payment = autonomi::client::payment::PaymentOption::from(&wallet);
 
-    // Create record
-    let record = antns::register::DnsRecord {
-        record_type,
-        name: record_name,
-        value,
-    };
-
     // Update record
     let cost = antns::update_domain_record(
         &client,
@@ -255,3 +278,40 @@ async fn update_command(
 
     Ok(())
 }
+
+async fn migrate_command(domain: String) -> Result<()> {
+    println!("Migrating records for domain: {}", domain);
+
+    // Load keypair
+    let keypair = antns::crypto::load_keypair(&domain)
+        .context("Failed to load domain keypair. Do you own this domain?")?;
+
+    let client = Client::init()
+        .await
+        .context("Failed to initialize Autonomi client")?;
+
+    // Load wallet using the client's network
+    let wallet =
+        antns::wallet::load_wallet_from_client(&client).context("Failed to load wallet")?;
+
+    println!("Using wallet: {}", wallet.address());
+
+    // Create payment option
+    let payment = autonomi::client::payment::PaymentOption::from(&wallet);
+
+    // Re-publish current records in the current signed format
+    match antns::migrate_domain_records(&client, &domain, &keypair.signing_key, payment)
+        .await
+        .context("Failed to migrate records")?
+    {
+        Some(cost) => {
+            println!("\n✓ Records re-signed in the current format!");
+            println!("Cost: {} AttoTokens", cost);
+        }
+        None => {
+            println!("\n✓ Nothing to migrate: records are already in the current format.");
+        }
+    }
+
+    Ok(())
+}
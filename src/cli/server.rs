@@ -3,69 +3,195 @@
 
 //! DNS resolver and HTTP proxy server commands
 
+use antns::server::acl::{AccessList, Network};
 use anyhow::Result;
-use clap::Subcommand;
+use clap::{Args, Subcommand};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Subcommand)]
 pub enum ServerCommands {
     /// Start DNS resolver and HTTP proxy
-    Start {
-        /// DNS port
-        #[arg(long, default_value = "5354")]
-        dns_port: u16,
-        /// HTTP proxy port
-        #[arg(long, default_value = "80")]
-        proxy_port: u16,
-        /// Upstream URL template for HTTP proxy (use $ADDRESS for target)
-        #[arg(long, default_value = "http://localhost:18888/$ADDRESS")]
-        upstream: String,
-        /// Cache TTL in minutes (0 to disable caching)
-        #[arg(long, default_value = "60")]
-        ttl: u64,
-    },
+    Start(Box<StartArgs>),
     /// Set up DNS resolver configuration
     Setup {
         /// DNS port
         #[arg(long, default_value = "5354")]
         dns_port: u16,
     },
+    /// Show how to trust the local CA used by the HTTPS proxy
+    Trust,
     /// Stop running servers
     Stop,
     /// Show server status
     Status,
 }
 
+#[derive(Args)]
+pub struct StartArgs {
+    /// DNS port
+    #[arg(long, default_value = "5354")]
+    dns_port: u16,
+    /// HTTP proxy port
+    #[arg(long, default_value = "80")]
+    proxy_port: u16,
+    /// HTTPS proxy port, with certificates from a local CA
+    #[arg(long)]
+    https_port: Option<u16>,
+    /// Upstream URL template for HTTP proxy (use $ADDRESS for target)
+    #[arg(long, default_value = "http://localhost:18888/$ADDRESS")]
+    upstream: String,
+    /// Serve content straight from the Autonomi network instead of the upstream
+    #[arg(long, conflicts_with = "upstream")]
+    native: bool,
+    /// Idle upstream connections the proxy keeps per host
+    #[arg(long, default_value_t = antns::DEFAULT_PROXY_MAX_IDLE_PER_HOST)]
+    proxy_max_idle: usize,
+    /// Seconds an idle upstream connection is kept open
+    #[arg(long, default_value_t = antns::DEFAULT_PROXY_IDLE_TIMEOUT_SECS)]
+    proxy_idle_timeout: u64,
+    /// Timeout in seconds for connecting to the upstream
+    #[arg(long, default_value_t = antns::DEFAULT_PROXY_CONNECT_TIMEOUT_SECS)]
+    proxy_connect_timeout: u64,
+    /// Timeout in seconds for the upstream's response headers
+    #[arg(long, default_value_t = antns::DEFAULT_PROXY_RESPONSE_TIMEOUT_SECS)]
+    proxy_timeout: u64,
+    /// Cache TTL in minutes (0 to disable caching)
+    #[arg(long, default_value = "60")]
+    ttl: u64,
+    /// Maximum number of names kept in the resolution cache
+    #[arg(long, default_value_t = antns::RESOLUTION_CACHE_MAX_ENTRIES)]
+    cache_size: usize,
+    /// Seconds an unregistered name is cached
+    #[arg(long, default_value_t = antns::DEFAULT_NEGATIVE_TTL_SECS)]
+    negative_ttl: u64,
+    /// Size cap of the proxy's on-disk content cache in MiB (0 to disable)
+    #[arg(long, default_value_t = antns::CONTENT_CACHE_MAX_BYTES / (1024 * 1024))]
+    content_cache_size: u64,
+    /// Seconds between checks of cached names' registers for updates
+    /// (0 to disable)
+    #[arg(long, default_value_t = antns::DEFAULT_WATCH_INTERVAL_SECS)]
+    watch_interval: u64,
+    /// Number of recently used names whose registers are checked
+    #[arg(long, default_value_t = antns::DEFAULT_WATCH_MAX_HOT)]
+    watch_hot: usize,
+    /// Always watch this name and keep it resolved (repeatable)
+    #[arg(long = "pin", value_name = "NAME")]
+    pin: Vec<antns::DomainName>,
+    /// Address to listen on, IPv4 or IPv6 (repeatable)
+    #[arg(long = "bind", value_name = "IP", default_value = "127.0.0.1")]
+    bind: Vec<IpAddr>,
+    /// Client network allowed to use the servers, in CIDR notation
+    /// (repeatable, default: loopback only)
+    #[arg(long = "allow", value_name = "CIDR")]
+    allow: Vec<Network>,
+    /// Forward non-AntNS queries to this resolver (IP or IP:port, repeatable)
+    #[arg(long = "forward", value_name = "ADDR")]
+    forward: Vec<String>,
+    /// Timeout in seconds for each upstream resolver
+    #[arg(long, default_value_t = antns::DEFAULT_FORWARD_TIMEOUT_SECS)]
+    forward_timeout: u64,
+    /// DNS-over-HTTPS port (requires --tls-cert and --tls-key)
+    #[arg(long)]
+    doh_port: Option<u16>,
+    /// DNS-over-TLS port (requires --tls-cert and --tls-key)
+    #[arg(long)]
+    dot_port: Option<u16>,
+    /// PEM certificate chain for DNS-over-HTTPS/TLS
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key for DNS-over-HTTPS/TLS
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
+}
+
 pub async fn execute(command: ServerCommands) -> Result<()> {
     match command {
-        ServerCommands::Start {
-            dns_port,
-            proxy_port,
-            upstream,
-            ttl,
-        } => start_command(dns_port, proxy_port, upstream, ttl).await,
+        ServerCommands::Start(args) => start_command(*args).await,
         ServerCommands::Setup { dns_port } => setup_command(dns_port).await,
+        ServerCommands::Trust => trust_command().await,
         ServerCommands::Stop => stop_command().await,
         ServerCommands::Status => status_command().await,
     }
 }
 
-async fn start_command(
-    dns_port: u16,
-    proxy_port: u16,
-    upstream: String,
-    ttl_minutes: u64,
-) -> Result<()> {
+async fn start_command(args: StartArgs) -> Result<()> {
     use anyhow::Context;
 
+    let upstreams = args
+        .forward
+        .iter()
+        .map(|upstream| antns::server::forward::parse_upstream(upstream))
+        .collect::<Result<Vec<_>>>()?;
+    let tls = tls_listeners(&args)?;
+    let only_loopback = args.allow.is_empty();
+    let access_list = Arc::new(if only_loopback {
+        AccessList::loopback()
+    } else {
+        AccessList::new(args.allow)
+    });
+    let dns_port = args.dns_port;
+    let ttl_minutes = args.ttl;
+    let source = if args.native {
+        antns::server::ContentSource::Network
+    } else {
+        antns::server::ContentSource::Upstream(args.upstream.clone())
+    };
+    let upstream_options = antns::server::UpstreamOptions {
+        max_idle_per_host: args.proxy_max_idle,
+        idle_timeout: Duration::from_secs(args.proxy_idle_timeout),
+        connect_timeout: Duration::from_secs(args.proxy_connect_timeout),
+        response_timeout: Duration::from_secs(args.proxy_timeout),
+    };
+
     println!("Starting AntNS servers...");
     println!("DNS Resolver: port {}", dns_port);
-    println!("HTTP Proxy: port {}", proxy_port);
-    println!("Upstream: {}", upstream);
+    println!("HTTP Proxy: port {}", args.proxy_port);
+    if let Some(port) = args.https_port {
+        println!("HTTPS Proxy: port {}", port);
+    }
+    if args.native {
+        println!("Content: served from the Autonomi network");
+    } else {
+        println!("Upstream: {}", args.upstream);
+    }
     if ttl_minutes > 0 {
-        println!("Cache TTL: {} minutes", ttl_minutes);
+        println!(
+            "Cache TTL: {} minutes ({}s for unregistered names, up to {} names)",
+            ttl_minutes, args.negative_ttl, args.cache_size
+        );
     } else {
         println!("Cache: disabled");
     }
+    let watch = ttl_minutes > 0 && args.watch_interval > 0;
+    if watch {
+        println!(
+            "Register watcher: every {}s ({} recent names, {} pinned)",
+            args.watch_interval,
+            args.watch_hot,
+            args.pin.len()
+        );
+    }
+    let bind: Vec<String> = args.bind.iter().map(ToString::to_string).collect();
+    println!("Listening on: {}", bind.join(", "));
+    println!("Allowed clients: {}", access_list);
+    if !upstreams.is_empty() {
+        println!("Forwarding: {} upstream resolver(s)", upstreams.len());
+    }
+    if let Some(tls) = &tls {
+        if let Some(port) = tls.doh_port {
+            println!("DNS-over-HTTPS: port {}", port);
+        }
+        if let Some(port) = tls.dot_port {
+            println!("DNS-over-TLS: port {}", port);
+        }
+    }
+    if only_loopback && args.bind.iter().any(|ip| !ip.is_loopback()) {
+        println!("⚠️  Listening beyond loopback, but only loopback clients are served.");
+        println!("   Use --allow <CIDR> to admit clients on your network.");
+    }
 
     // Check resolver configuration
     println!("\nChecking DNS resolver configuration...");
@@ -92,14 +218,98 @@ async fn start_command(
         println!("✓ DNS resolver configuration OK");
     }
 
+    let https = match args.https_port {
+        Some(port) => {
+            let ca = antns::server::LocalCa::open_default().context("Failed to open local CA")?;
+            if ca.is_new() {
+                println!("\n✓ Created local CA: {}", ca.cert_path().display());
+                println!("\n{}", ca.trust_instructions());
+            } else {
+                println!("\n✓ Local CA: {}", ca.cert_path().display());
+                println!("  Run 'antns server trust' if browsers do not trust it yet");
+            }
+            Some(antns::server::HttpsListener {
+                port,
+                ca: Arc::new(ca),
+            })
+        }
+        None => None,
+    };
+
+    let content_cache = match args.content_cache_size {
+        0 => None,
+        size => {
+            let dir = antns::storage::content::get_content_cache_dir()?;
+            Some(antns::storage::ContentCache::new(dir, size * 1024 * 1024))
+        }
+    };
+
+    println!("\nInitializing Autonomi client...");
+    let client = autonomi::Client::init()
+        .await
+        .context("Failed to initialize Autonomi client")?;
+    println!("✓ Autonomi client initialized");
+
+    // Resume lookups from verified checkpoints so only new entries are fetched
+    let checkpoints = match antns::storage::CheckpointCache::open_default() {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            tracing::warn!("Checkpoint cache unavailable: {:#}", e);
+            None
+        }
+    };
+
+    // Both servers answer from the same verified resolutions
+    let cache = Arc::new(
+        antns::server::ResolutionCache::new(
+            client,
+            antns::LookupOptions {
+                checkpoints,
+                ..Default::default()
+            },
+            Duration::from_secs(ttl_minutes * 60),
+        )
+        .with_capacity(args.cache_size)
+        .with_negative_ttl(Duration::from_secs(args.negative_ttl)),
+    );
+
+    // Pick up owners' updates before cached resolutions expire
+    if watch {
+        let watcher = antns::server::RegisterWatcher::new(
+            cache.clone(),
+            Duration::from_secs(args.watch_interval),
+        )
+        .with_pins(args.pin.clone())
+        .with_max_hot(args.watch_hot);
+        tokio::spawn(watcher.run());
+    }
+
+    let mut dns_handler =
+        antns::server::AntDnsHandler::new(cache.clone()).with_access_list(access_list.clone());
+    if !upstreams.is_empty() {
+        dns_handler = dns_handler.with_forwarder(antns::server::Forwarder::new(
+            upstreams,
+            Duration::from_secs(args.forward_timeout),
+        ));
+    }
+
     println!("\nStarting servers...\n");
 
     // Start both servers concurrently
     tokio::select! {
-        result = antns::server::run_dns(dns_port) => {
+        result = antns::server::run_dns(&args.bind, dns_port, dns_handler, tls) => {
             eprintln!("DNS server exited: {:?}", result);
         }
-        result = antns::server::run_http(proxy_port, upstream, ttl_minutes) => {
+        result = antns::server::run_http(
+            &args.bind,
+            args.proxy_port,
+            https,
+            source,
+            upstream_options,
+            content_cache,
+            cache.clone(),
+            access_list,
+        ) => {
             eprintln!("HTTP proxy exited: {:?}", result);
         }
         _ = tokio::signal::ctrl_c() => {
@@ -107,11 +317,30 @@ async fn start_command(
         }
     }
 
+    if ttl_minutes > 0 {
+        println!("Resolution cache: {}", cache.stats());
+    }
     println!("Servers stopped.");
 
     Ok(())
 }
 
+/// Encrypted DNS listeners from the command line flags
+fn tls_listeners(args: &StartArgs) -> Result<Option<antns::server::TlsListeners>> {
+    if args.doh_port.is_none() && args.dot_port.is_none() {
+        return Ok(None);
+    }
+    match (&args.tls_cert, &args.tls_key) {
+        (Some(cert_path), Some(key_path)) => Ok(Some(antns::server::TlsListeners {
+            cert_path: cert_path.clone(),
+            key_path: key_path.clone(),
+            doh_port: args.doh_port,
+            dot_port: args.dot_port,
+        })),
+        _ => anyhow::bail!("--doh-port and --dot-port require --tls-cert and --tls-key"),
+    }
+}
+
 async fn setup_command(dns_port: u16) -> Result<()> {
     use anyhow::Context;
 
@@ -126,6 +355,22 @@ async fn setup_command(dns_port: u16) -> Result<()> {
     Ok(())
 }
 
+async fn trust_command() -> Result<()> {
+    use anyhow::Context;
+
+    let ca = antns::server::LocalCa::open_default().context("Failed to open local CA")?;
+    if ca.is_new() {
+        println!("✓ Created local CA: {}\n", ca.cert_path().display());
+    } else {
+        println!("Local CA: {}\n", ca.cert_path().display());
+    }
+    println!("{}", ca.trust_instructions());
+    println!("\nThen start the HTTPS proxy with:");
+    println!("  antns server start --https-port 443");
+
+    Ok(())
+}
+
 async fn stop_command() -> Result<()> {
     println!("Stopping AntNS servers...");
     println!("\n⚠️  Server management not yet implemented.");
@@ -17,3 +17,80 @@ pub const DOMAIN_SUFFIX: &str = ".ant";
 
 /// Alternative domain suffix
 pub const DOMAIN_SUFFIX_ALT: &str = ".autonomi";
+
+/// Public gateway suffix; `name.antns.net` maps to `name.ant`
+pub const GATEWAY_SUFFIX: &str = ".antns.net";
+
+/// Record name that refers to the registered domain itself
+pub const ROOT_RECORD_NAME: &str = ".";
+
+/// Version of the signed records payload produced by this implementation
+pub const RECORDS_PROTOCOL_VERSION: u32 = 1;
+
+/// Domain-separation tag included in every signed records payload
+pub const RECORDS_SIGNING_TAG: &str = "antns-records";
+
+/// Default number of register entries downloaded concurrently during a lookup
+pub const DEFAULT_LOOKUP_CONCURRENCY: usize = 8;
+
+/// Format version of on-disk history checkpoints
+pub const CHECKPOINT_FORMAT_VERSION: u32 = 2;
+
+/// Default size cap of the history checkpoint cache (bytes)
+pub const CHECKPOINT_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Default size cap of the proxy's content cache (bytes)
+pub const CONTENT_CACHE_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Largest response body the proxy's content cache stores (bytes)
+pub const CONTENT_CACHE_MAX_ENTRY_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Largest file the proxy serves from the network itself (bytes)
+///
+/// Files are downloaded whole and held in memory while they are sent.
+pub const NATIVE_CONTENT_MAX_FILE_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Maximum number of targets whose archive listing the proxy keeps parsed
+pub const ARCHIVE_CACHE_MAX_ENTRIES: usize = 256;
+
+/// Maximum number of nested delegations followed when resolving a name
+pub const MAX_DELEGATION_DEPTH: usize = 4;
+
+/// TTL (seconds) of DNS answers from records without an explicit TTL
+pub const DEFAULT_RECORD_TTL: u32 = 300;
+
+/// Default number of names kept in the resolution cache
+pub const RESOLUTION_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// Default time (seconds) an unregistered name is remembered
+pub const DEFAULT_NEGATIVE_TTL_SECS: u64 = 30;
+
+/// Default interval (seconds) between register watcher polls
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 30;
+
+/// Default number of recently used names the register watcher polls
+pub const DEFAULT_WATCH_MAX_HOT: usize = 64;
+
+/// Default timeout (seconds) for each upstream resolver when forwarding
+pub const DEFAULT_FORWARD_TIMEOUT_SECS: u64 = 2;
+
+/// Maximum number of forwarded responses kept in memory
+pub const FORWARD_CACHE_MAX_ENTRIES: usize = 4096;
+
+/// Upper bound (seconds) on how long a forwarded response is cached
+pub const FORWARD_CACHE_MAX_TTL: u32 = 3600;
+
+/// Cache time (seconds) of forwarded responses without any records
+pub const FORWARD_NEGATIVE_TTL: u32 = 60;
+
+/// Default number of idle upstream connections the HTTP proxy keeps per host
+pub const DEFAULT_PROXY_MAX_IDLE_PER_HOST: usize = 32;
+
+/// Default time (seconds) an idle upstream connection is kept open
+pub const DEFAULT_PROXY_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Default timeout (seconds) for connecting to the proxy upstream
+pub const DEFAULT_PROXY_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Default timeout (seconds) for the proxy upstream's response headers
+pub const DEFAULT_PROXY_RESPONSE_TIMEOUT_SECS: u64 = 60;
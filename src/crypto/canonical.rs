@@ -0,0 +1,225 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Canonical JSON encoding for signed payloads
+//!
+//! Implements the JSON Canonicalization Scheme (RFC 8785) so that signatures
+//! do not depend on how a particular serializer orders fields or formats
+//! values. Any implementation that produces the same canonical bytes for the
+//! same payload will verify the same signatures.
+//!
+//! Rules:
+//! - No whitespace between tokens
+//! - Object members sorted by the UTF-16 code units of their keys
+//! - Strings escape only `"`, `\` and control characters (short escapes for
+//!   `\b \t \n \f \r`, lowercase `\u00xx` for the rest); everything else is
+//!   emitted as UTF-8
+//! - Numbers use the ECMAScript `Number.prototype.toString` format; integers
+//!   outside the IEEE 754 safe range (±2^53 - 1) are rejected because other
+//!   implementations cannot represent them exactly
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::fmt::Write;
+
+/// Largest integer that every JSON implementation can represent exactly
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Serialize a value to its canonical JSON representation
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value).context("Failed to serialize value")?;
+    canonicalize(&value)
+}
+
+/// Encode a JSON value in canonical form
+pub fn canonicalize(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    write_value(&mut out, value)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n)?,
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Sort by UTF-16 code units, not by UTF-8 bytes
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, item)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, item)?;
+            }
+            out.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_number(out: &mut String, n: &Number) -> Result<()> {
+    if let Some(u) = n.as_u64() {
+        if u > MAX_SAFE_INTEGER {
+            anyhow::bail!("Integer {} exceeds the canonical JSON safe range", u);
+        }
+        let _ = write!(out, "{}", u);
+    } else if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() > MAX_SAFE_INTEGER {
+            anyhow::bail!("Integer {} exceeds the canonical JSON safe range", i);
+        }
+        let _ = write!(out, "{}", i);
+    } else {
+        let f = n.as_f64().context("Unsupported JSON number")?;
+        write_float(out, f);
+    }
+
+    Ok(())
+}
+
+/// Format a finite double the way ECMAScript `Number.prototype.toString` does
+fn write_float(out: &mut String, f: f64) {
+    if f == 0.0 {
+        // Covers -0 as well
+        out.push('0');
+        return;
+    }
+
+    if f < 0.0 {
+        out.push('-');
+    }
+
+    // Rust's exponent formatting yields the shortest round-tripping digits
+    let formatted = format!("{:e}", f.abs());
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .unwrap_or((formatted.as_str(), "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    // ECMAScript: value = 0.d1d2...dk × 10^n, with k digits
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        // Integer: digits followed by zeros
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        // Fixed notation with the decimal point inside the digits
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        // Small fraction with leading zeros
+        out.push_str("0.");
+        out.push_str(&"0".repeat((-n) as usize));
+        out.push_str(&digits);
+    } else {
+        // Exponential notation
+        let e = n - 1;
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if e < 0 { '-' } else { '+' });
+        let _ = write!(out, "{}", e.abs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Vectors {
+        canonicalization: Vec<CanonicalizationVector>,
+    }
+
+    #[derive(Deserialize)]
+    struct CanonicalizationVector {
+        description: String,
+        input: String,
+        canonical: Option<String>,
+    }
+
+    #[test]
+    fn test_canonicalization_vectors() {
+        let vectors: Vectors =
+            serde_json::from_str(include_str!("../../test-vectors/records-v1.json")).unwrap();
+
+        for vector in vectors.canonicalization {
+            let value: Value = serde_json::from_str(&vector.input).unwrap();
+            let result = canonicalize(&value);
+
+            match vector.canonical {
+                Some(expected) => {
+                    assert_eq!(result.unwrap(), expected, "{}", vector.description)
+                }
+                None => assert!(result.is_err(), "{}", vector.description),
+            }
+        }
+    }
+
+    #[test]
+    fn test_float_formatting() {
+        let cases = [
+            (1.0, "1"),
+            (-1.5, "-1.5"),
+            (0.000001, "0.000001"),
+            (0.0000001, "1e-7"),
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (123.456, "123.456"),
+            (5e-324, "5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+        ];
+
+        for (value, expected) in cases {
+            let mut out = String::new();
+            write_float(&mut out, value);
+            assert_eq!(out, expected);
+        }
+    }
+}
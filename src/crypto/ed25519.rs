@@ -3,15 +3,77 @@
 
 //! Ed25519 signature operations for domain ownership verification
 
+use crate::constants::{RECORDS_PROTOCOL_VERSION, RECORDS_SIGNING_TAG};
+use crate::crypto::canonical::to_canonical_json;
 use crate::register::DnsRecord;
 use anyhow::{Context, Result};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::Serialize;
 
-/// Sign a list of DNS records with an Ed25519 private key
+/// Which records signature formats a verification accepts
+///
+/// Legacy signatures are not bound to a domain name, so a document signed for
+/// one domain verifies for any other domain with the same owner key. They are
+/// rejected unless a caller explicitly asks for compatibility.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerifyMode {
+    /// Only versioned, domain-bound signatures
+    #[default]
+    Strict,
+    /// Also unversioned legacy signatures
+    AllowLegacy,
+}
+
+/// Payload covered by the signature of a versioned records document
+///
+/// Encoded with [`to_canonical_json`] before signing.
+#[derive(Serialize)]
+struct SignedRecordsPayload<'a> {
+    domain: String,
+    records: &'a [DnsRecord],
+    sequence: u64,
+    tag: &'static str,
+    timestamp: u64,
+    version: u32,
+}
+
+/// Normalize a domain name for inclusion in a signed payload
+pub fn normalize_domain(domain: &str) -> String {
+    domain.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Canonical encoding of the signed payload for a versioned records document
+pub fn records_payload(
+    domain: &str,
+    records: &[DnsRecord],
+    sequence: u64,
+    timestamp: u64,
+) -> Result<String> {
+    to_canonical_json(&SignedRecordsPayload {
+        domain: normalize_domain(domain),
+        records,
+        sequence,
+        tag: RECORDS_SIGNING_TAG,
+        timestamp,
+        version: RECORDS_PROTOCOL_VERSION,
+    })
+    .context("Failed to serialize records")
+}
+
+/// Sign a list of DNS records for a domain
+///
+/// The signature covers a domain-separation tag, the protocol version, the
+/// domain name, the sequence number, the timestamp and the records.
 /// Returns the signature as a hex string
-pub fn sign_records(records: &[DnsRecord], signing_key: &SigningKey) -> Result<String> {
-    // Serialize records to canonical JSON (deterministic ordering)
-    let json = serde_json::to_string(records).context("Failed to serialize records")?;
+pub fn sign_records(
+    domain: &str,
+    records: &[DnsRecord],
+    sequence: u64,
+    timestamp: u64,
+    signing_key: &SigningKey,
+) -> Result<String> {
+    // Serialize payload to canonical JSON (RFC 8785)
+    let json = records_payload(domain, records, sequence, timestamp)?;
 
     // Sign the JSON bytes
     let signature = signing_key.sign(json.as_bytes());
@@ -20,13 +82,45 @@ pub fn sign_records(records: &[DnsRecord], signing_key: &SigningKey) -> Result<S
     Ok(hex::encode(signature.to_bytes()))
 }
 
-/// Verify a signature on DNS records using an Ed25519 public key
+/// Verify a signature on DNS records for a domain using an Ed25519 public key
 /// Returns true if the signature is valid
 pub fn verify_records(
+    domain: &str,
     records: &[DnsRecord],
+    sequence: u64,
+    timestamp: u64,
     signature_hex: &str,
     verifying_key: &VerifyingKey,
 ) -> bool {
+    // Serialize records to same canonical JSON format
+    let json = match records_payload(domain, records, sequence, timestamp) {
+        Ok(j) => j,
+        Err(_) => return false,
+    };
+
+    verify_payload(json.as_bytes(), signature_hex, verifying_key)
+}
+
+/// Verify a signature in the unversioned legacy format
+///
+/// Legacy documents sign only the records array and are not bound to a domain
+/// name, so callers must only use this with [`VerifyMode::AllowLegacy`].
+///
+/// The legacy payload is plain `serde_json` output in struct field order, not
+/// canonical JSON, because that is what the old implementation signed.
+pub fn verify_legacy_records(
+    records: &[DnsRecord],
+    signature_hex: &str,
+    verifying_key: &VerifyingKey,
+) -> bool {
+    match serde_json::to_string(records) {
+        Ok(json) => verify_payload(json.as_bytes(), signature_hex, verifying_key),
+        Err(_) => false,
+    }
+}
+
+/// Verify a hex-encoded signature over raw payload bytes
+fn verify_payload(payload: &[u8], signature_hex: &str, verifying_key: &VerifyingKey) -> bool {
     // Decode signature from hex
     let sig_bytes = match hex::decode(signature_hex) {
         Ok(bytes) => bytes,
@@ -39,14 +133,8 @@ pub fn verify_records(
         Err(_) => return false,
     };
 
-    // Serialize records to same canonical JSON format
-    let json = match serde_json::to_string(records) {
-        Ok(j) => j,
-        Err(_) => return false,
-    };
-
     // Verify signature
-    verifying_key.verify(json.as_bytes(), &signature).is_ok()
+    verifying_key.verify(payload, &signature).is_ok()
 }
 
 #[cfg(test)]
@@ -55,20 +143,32 @@ mod tests {
     use ed25519_dalek::SigningKey;
     use rand::rngs::OsRng;
 
+    fn test_records(value: &str) -> Vec<DnsRecord> {
+        vec![DnsRecord {
+            record_type: "ant".to_string(),
+            name: ".".to_string(),
+            value: value.to_string(),
+            ttl: None,
+        }]
+    }
+
     #[test]
     fn test_sign_and_verify() {
         let mut rng = OsRng;
         let signing_key = SigningKey::generate(&mut rng);
         let verifying_key = signing_key.verifying_key();
 
-        let records = vec![DnsRecord {
-            record_type: "ant".to_string(),
-            name: ".".to_string(),
-            value: "abc123".to_string(),
-        }];
+        let records = test_records("abc123");
 
-        let signature = sign_records(&records, &signing_key).unwrap();
-        assert!(verify_records(&records, &signature, &verifying_key));
+        let signature = sign_records("test.ant", &records, 1, 1_700_000_000, &signing_key).unwrap();
+        assert!(verify_records(
+            "test.ant",
+            &records,
+            1,
+            1_700_000_000,
+            &signature,
+            &verifying_key
+        ));
     }
 
     #[test]
@@ -77,21 +177,177 @@ mod tests {
         let signing_key = SigningKey::generate(&mut rng);
         let verifying_key = signing_key.verifying_key();
 
-        let records = vec![DnsRecord {
-            record_type: "ant".to_string(),
-            name: ".".to_string(),
-            value: "abc123".to_string(),
-        }];
+        let records = test_records("abc123");
 
-        let signature = sign_records(&records, &signing_key).unwrap();
+        let signature = sign_records("test.ant", &records, 1, 1_700_000_000, &signing_key).unwrap();
 
         // Tamper with records
-        let tampered = vec![DnsRecord {
-            record_type: "ant".to_string(),
-            name: ".".to_string(),
-            value: "xyz789".to_string(), // Changed!
-        }];
+        let tampered = test_records("xyz789"); // Changed!
+
+        assert!(!verify_records(
+            "test.ant",
+            &tampered,
+            1,
+            1_700_000_000,
+            &signature,
+            &verifying_key
+        ));
+    }
+
+    #[test]
+    fn test_sequence_is_signed() {
+        let mut rng = OsRng;
+        let signing_key = SigningKey::generate(&mut rng);
+        let verifying_key = signing_key.verifying_key();
+
+        let records = test_records("abc123");
+
+        let signature = sign_records("test.ant", &records, 7, 1_700_000_000, &signing_key).unwrap();
+
+        // Bumping the sequence or timestamp must invalidate the signature
+        assert!(!verify_records(
+            "test.ant",
+            &records,
+            8,
+            1_700_000_000,
+            &signature,
+            &verifying_key
+        ));
+        assert!(!verify_records(
+            "test.ant",
+            &records,
+            7,
+            1_700_000_001,
+            &signature,
+            &verifying_key
+        ));
+    }
+
+    #[test]
+    fn test_cross_domain_reuse_rejected() {
+        let mut rng = OsRng;
+        let signing_key = SigningKey::generate(&mut rng);
+        let verifying_key = signing_key.verifying_key();
+
+        let records = test_records("abc123");
+
+        let signature =
+            sign_records("first.ant", &records, 1, 1_700_000_000, &signing_key).unwrap();
+
+        // Same key, same records, different domain
+        assert!(!verify_records(
+            "second.ant",
+            &records,
+            1,
+            1_700_000_000,
+            &signature,
+            &verifying_key
+        ));
+
+        // Domain names are compared case-insensitively
+        assert!(verify_records(
+            "First.ANT.",
+            &records,
+            1,
+            1_700_000_000,
+            &signature,
+            &verifying_key
+        ));
+
+        // A versioned signature never verifies as a legacy one
+        assert!(!verify_legacy_records(&records, &signature, &verifying_key));
+    }
+
+    #[test]
+    fn test_legacy_signatures() {
+        let mut rng = OsRng;
+        let signing_key = SigningKey::generate(&mut rng);
+        let verifying_key = signing_key.verifying_key();
+
+        let records = test_records("abc123");
+
+        // Legacy documents covered only the records
+        let json = serde_json::to_string(&records).unwrap();
+        let signature = hex::encode(signing_key.sign(json.as_bytes()).to_bytes());
+        assert!(verify_legacy_records(&records, &signature, &verifying_key));
+        assert!(!verify_legacy_records(
+            &test_records("other"),
+            &signature,
+            &verifying_key
+        ));
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct SignatureVector {
+        description: String,
+        secret_key: String,
+        public_key: String,
+        domain: String,
+        document: crate::register::DomainRecordsDocument,
+        canonical_payload: Option<String>,
+        valid: bool,
+        valid_with_legacy: bool,
+    }
+
+    #[test]
+    fn test_signature_vectors() {
+        #[derive(serde::Deserialize)]
+        struct Vectors {
+            signatures: Vec<SignatureVector>,
+        }
+
+        let vectors: Vectors =
+            serde_json::from_str(include_str!("../../test-vectors/records-v1.json")).unwrap();
+
+        for vector in vectors.signatures {
+            let desc = &vector.description;
+            let secret: [u8; 32] = hex::decode(&vector.secret_key).unwrap().try_into().unwrap();
+            let signing_key = SigningKey::from_bytes(&secret);
+            let verifying_key = signing_key.verifying_key();
+            assert_eq!(
+                hex::encode(verifying_key.to_bytes()),
+                vector.public_key,
+                "{}",
+                desc
+            );
+
+            let doc = &vector.document;
+
+            if let Some(expected) = &vector.canonical_payload {
+                let payload = records_payload(
+                    &vector.domain,
+                    &doc.records,
+                    doc.sequence.unwrap(),
+                    doc.timestamp.unwrap(),
+                )
+                .unwrap();
+                assert_eq!(&payload, expected, "{}", desc);
+
+                // Ed25519 is deterministic, so re-signing must reproduce the vector
+                let signature = sign_records(
+                    &vector.domain,
+                    &doc.records,
+                    doc.sequence.unwrap(),
+                    doc.timestamp.unwrap(),
+                    &signing_key,
+                )
+                .unwrap();
+                assert_eq!(signature, doc.signature, "{}", desc);
+            }
 
-        assert!(!verify_records(&tampered, &signature, &verifying_key));
+            assert_eq!(
+                doc.verify_with(&vector.domain, &verifying_key, VerifyMode::Strict),
+                vector.valid,
+                "{}",
+                desc
+            );
+            assert_eq!(
+                doc.verify_with(&vector.domain, &verifying_key, VerifyMode::AllowLegacy),
+                vector.valid_with_legacy,
+                "{}",
+                desc
+            );
+        }
     }
 }
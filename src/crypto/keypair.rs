@@ -3,9 +3,11 @@
 
 //! Domain keypair management and storage
 
+use crate::crypto::ed25519::normalize_domain;
+use crate::error::AntnsError;
 use anyhow::{Context, Result};
 use ed25519_dalek::{SigningKey, VerifyingKey};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Domain keypair structure
 #[derive(Debug)]
@@ -55,7 +57,15 @@ impl DomainKeypair {
 /// Save a domain keypair to local storage
 pub fn save_keypair(domain: &str, keypair: &DomainKeypair) -> Result<PathBuf> {
     let keys_dir = crate::storage::local::get_domain_keys_dir()?;
-    std::fs::create_dir_all(&keys_dir).context("Failed to create domain keys directory")?;
+    save_keypair_in(&keys_dir, domain, keypair)
+}
+
+/// Save a domain keypair to the given keys directory
+///
+/// Keys are stored under the canonical (lowercase) domain name.
+pub fn save_keypair_in(keys_dir: &Path, domain: &str, keypair: &DomainKeypair) -> Result<PathBuf> {
+    std::fs::create_dir_all(keys_dir).context("Failed to create domain keys directory")?;
+    let domain = normalize_domain(domain);
 
     // Save private key
     let key_file = keys_dir.join(format!("domain-key-{}.txt", domain));
@@ -76,13 +86,20 @@ pub fn save_keypair(domain: &str, keypair: &DomainKeypair) -> Result<PathBuf> {
 }
 
 /// Load a domain keypair from local storage
-pub fn load_keypair(domain: &str) -> Result<DomainKeypair> {
+///
+/// Returns [`AntnsError::KeyNotFound`] if no key is stored for the domain.
+/// Keys saved before names were lowercased are found under the name as typed.
+pub fn load_keypair(domain: &str) -> crate::Result<DomainKeypair> {
     let keys_dir = crate::storage::local::get_domain_keys_dir()?;
-    let key_file = keys_dir.join(format!("domain-key-{}.txt", domain));
+    let key_file = [normalize_domain(domain), domain.to_string()]
+        .iter()
+        .map(|name| keys_dir.join(format!("domain-key-{}.txt", name)))
+        .find(|file| file.exists())
+        .ok_or_else(|| AntnsError::KeyNotFound(domain.to_string()))?;
 
     let key_hex = std::fs::read_to_string(&key_file).context("Failed to read private key file")?;
 
     let key_bytes = hex::decode(key_hex.trim()).context("Invalid hex in private key file")?;
 
-    DomainKeypair::from_bytes(&key_bytes)
+    Ok(DomainKeypair::from_bytes(&key_bytes)?)
 }
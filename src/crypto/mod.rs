@@ -3,8 +3,9 @@
 
 //! Cryptographic operations for domain ownership
 
+pub mod canonical;
 pub mod ed25519;
 pub mod keypair;
 
-pub use ed25519::{sign_records, verify_records};
-pub use keypair::{load_keypair, save_keypair, DomainKeypair};
+pub use ed25519::{sign_records, verify_records, VerifyMode};
+pub use keypair::{load_keypair, save_keypair, save_keypair_in, DomainKeypair};
@@ -0,0 +1,138 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Error type for AntNS library operations
+//!
+//! Callers can branch on the variants instead of matching error text. Errors
+//! that do not fit a specific case are carried in [`AntnsError::Other`] with
+//! their full context chain.
+
+/// Errors returned by AntNS operations
+#[derive(Debug, thiserror::Error)]
+pub enum AntnsError {
+    /// The domain's register has no entries, i.e. the domain is not registered
+    ///
+    /// Carries the domain name, or the client's error text when the failing
+    /// operation only knew the register address.
+    #[error("Register not found for domain: {0}")]
+    RegisterNotFound(String),
+
+    /// The owner document referenced by the first register entry could not be fetched
+    #[error("Owner document missing for domain '{domain}': {reason}")]
+    OwnerDocumentMissing { domain: String, reason: String },
+
+    /// The owner document exists but is not valid JSON of the expected shape
+    #[error("Owner document for domain '{domain}' is corrupt: {reason}")]
+    OwnerDocumentCorrupt { domain: String, reason: String },
+
+    /// The owner document does not contain a valid Ed25519 public key
+    #[error("Invalid owner public key for domain '{domain}': {reason}")]
+    InvalidOwnerKey { domain: String, reason: String },
+
+    /// A network operation timed out
+    #[error("Network timeout during {operation}: {reason}")]
+    NetworkTimeout { operation: String, reason: String },
+
+    /// A network operation failed for a reason other than a timeout
+    #[error("Network error during {operation}: {reason}")]
+    Network { operation: String, reason: String },
+
+    /// Paying for network storage failed
+    #[error("Payment failed during {operation}: {reason}")]
+    PaymentFailed { operation: String, reason: String },
+
+    /// The name is not a valid `.ant` / `.autonomi` domain name
+    #[error("Invalid domain name '{name}': {reason}")]
+    InvalidDomainName { name: String, reason: String },
+
+    /// A delegated subdomain is malformed, nested too deeply or not writable with the given key
+    #[error("Invalid delegation for '{name}': {reason}")]
+    Delegation { name: String, reason: String },
+
+    /// A record value does not match its record type
+    #[error("Invalid {record_type} record '{value}': {reason}")]
+    InvalidRecord {
+        record_type: String,
+        value: String,
+        reason: String,
+    },
+
+    /// The domain is registered but has no validly signed records
+    #[error("No valid DNS records found for domain: {0}")]
+    NoValidRecords(String),
+
+    /// No private key for the domain is stored locally
+    #[error("No local key found for domain: {0}")]
+    KeyNotFound(String),
+
+    /// No key backup exists in the wallet's vault
+    #[error("No vault backup found")]
+    VaultNotFound,
+
+    /// The domain already had an owner before anything was paid for
+    #[error("Domain '{domain}' is already registered (owner key: {owner_public_key})")]
+    AlreadyOwnedByOther {
+        domain: String,
+        owner_public_key: String,
+    },
+
+    /// Another owner's document ended up as the first register entry
+    #[error("Registration of '{domain}' lost to another owner (owner key: {owner_public_key})")]
+    RegistrationLost {
+        domain: String,
+        owner_public_key: String,
+    },
+
+    /// A record index passed to an update operation does not exist
+    #[error("Record index {index} out of bounds (total records: {total})")]
+    RecordIndexOutOfBounds { index: usize, total: usize },
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Result type for AntNS operations
+pub type Result<T> = std::result::Result<T, AntnsError>;
+
+impl AntnsError {
+    /// Whether this error means the domain is not registered
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, AntnsError::RegisterNotFound(_))
+    }
+
+    /// Whether a network operation timed out
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, AntnsError::NetworkTimeout { .. })
+    }
+
+    /// Whether the same operation may succeed if tried again later
+    ///
+    /// Timeouts and other network failures are usually transient; a
+    /// missing domain or an invalid document is not.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AntnsError::NetworkTimeout { .. } | AntnsError::Network { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_found() {
+        assert!(AntnsError::RegisterNotFound("a.ant".to_string()).is_not_found());
+        assert!(!AntnsError::NoValidRecords("a.ant".to_string()).is_not_found());
+
+        // An unreachable network says nothing about the domain
+        let timeout = AntnsError::NetworkTimeout {
+            operation: "register history".to_string(),
+            reason: "no response".to_string(),
+        };
+        assert!(!timeout.is_not_found());
+        assert!(timeout.is_timeout() && timeout.is_retryable());
+        assert!(!AntnsError::RegisterNotFound("a.ant".to_string()).is_retryable());
+    }
+}
@@ -8,26 +8,40 @@
 
 pub mod constants;
 pub mod crypto;
+pub mod error;
+pub mod network;
 pub mod register;
 pub mod server;
 pub mod storage;
+#[cfg(test)]
+mod testing;
 pub mod vault;
 pub mod wallet;
 
 pub use constants::*;
 
 // Re-export commonly used types
-pub use crypto::ed25519::{sign_records, verify_records};
+pub use crypto::ed25519::{sign_records, verify_records, VerifyMode};
+pub use error::{AntnsError, Result};
+pub use network::NameNetwork;
 pub use register::{
     create::register_domain,
-    history::get_domain_history,
-    lookup::{lookup_domain, lookup_domain_records},
+    delegation::Delegation,
+    history::{get_domain_history, get_domain_history_with},
+    inspect::{
+        find_case_sensitive_registration, inspect_domain, inspect_domain_with, DomainInfo,
+        DomainStatus,
+    },
+    lookup::{
+        lookup_domain, lookup_domain_document, lookup_domain_document_with, lookup_domain_owner,
+        lookup_domain_records, lookup_domain_with, LookupOptions, LookupStats,
+    },
+    name::DomainName,
+    record::{RecordData, RecordType},
+    resolver::{ResolvedDomain, ResolvedName, Resolver, ZoneAuthority},
     update::{
-        add_domain_record, delete_domain_record, update_domain, update_domain_record,
-        update_domain_records,
+        add_domain_record, delete_domain_record, migrate_domain_records, update_domain,
+        update_domain_record, update_domain_records,
     },
 };
 pub use storage::list_local_domains;
-
-/// Common error type for AntNS operations
-pub type Result<T> = std::result::Result<T, anyhow::Error>;
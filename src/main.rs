@@ -19,6 +19,12 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Accept legacy record signatures that are not bound to a domain name
+    ///
+    /// Applies to `names` and `records` lookups; the server is always strict.
+    #[arg(long, global = true)]
+    legacy_signatures: bool,
 }
 
 #[derive(Subcommand)]
@@ -43,6 +49,11 @@ enum Commands {
         #[command(subcommand)]
         command: cli::keys::KeysCommands,
     },
+    /// Local lookup cache management
+    Cache {
+        #[command(subcommand)]
+        command: cli::cache::CacheCommands,
+    },
 }
 
 #[tokio::main]
@@ -77,13 +88,20 @@ async fn main() -> Result<()> {
             .init();
     }
 
+    let verify_mode = if cli.legacy_signatures {
+        tracing::warn!("Legacy signature compatibility mode enabled");
+        antns::VerifyMode::AllowLegacy
+    } else {
+        antns::VerifyMode::Strict
+    };
+
     // Execute command
     match cli.command {
         Commands::Names { command } => {
-            cli::names::execute(command).await?;
+            cli::names::execute(command, verify_mode).await?;
         }
         Commands::Records { command } => {
-            cli::records::execute(command).await?;
+            cli::records::execute(command, verify_mode).await?;
         }
         Commands::Server { command } => {
             cli::server::execute(command).await?;
@@ -91,6 +109,9 @@ async fn main() -> Result<()> {
         Commands::Keys { command } => {
             cli::keys::execute(command).await?;
         }
+        Commands::Cache { command } => {
+            cli::cache::execute(command).await?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,235 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! [`NameNetwork`] backed by the live Autonomi network
+
+use crate::error::{AntnsError, Result};
+use crate::network::{ArchiveEntry, NameNetwork, PublicData};
+use async_trait::async_trait;
+use autonomi::chunk::ChunkAddress;
+use autonomi::client::files::PublicArchive;
+use autonomi::client::payment::{PayError, PaymentOption};
+use autonomi::client::vault::{VaultContentType, VaultSecretKey};
+use autonomi::client::GetError;
+use autonomi::data::DataAddress;
+use autonomi::networking::NetworkError;
+use autonomi::register::{RegisterAddress, RegisterError, RegisterValue};
+use autonomi::{AttoTokens, Client, SecretKey};
+use bytes::Bytes;
+use std::error::Error;
+use xor_name::XorName;
+
+/// An error followed by the errors it wraps, outermost first
+fn error_chain<'a>(
+    error: &'a (dyn Error + 'static),
+) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    std::iter::successors(Some(error), |error| error.source())
+}
+
+/// Whether the client could not find the record an operation needed
+///
+/// Reading a register that was never created fails to find its head
+/// pointer; updating one is refused outright.
+fn is_missing(error: &(dyn Error + 'static)) -> bool {
+    error_chain(error).any(|error| {
+        matches!(error.downcast_ref(), Some(GetError::RecordNotFound))
+            || matches!(
+                error.downcast_ref(),
+                Some(RegisterError::CannotUpdateNewRegister)
+            )
+    })
+}
+
+fn is_timeout(error: &(dyn Error + 'static)) -> bool {
+    error_chain(error).any(|error| {
+        matches!(
+            error.downcast_ref(),
+            Some(NetworkError::GetRecordTimeout(_) | NetworkError::PutRecordTimeout(_))
+        )
+    })
+}
+
+fn is_payment_failure(error: &(dyn Error + 'static)) -> bool {
+    error_chain(error).any(|error| error.is::<PayError>())
+}
+
+/// Classify an error returned by the Autonomi client
+fn client_error(operation: &str, error: impl Error + 'static) -> AntnsError {
+    let (operation, reason) = (operation.to_string(), error.to_string());
+    if is_timeout(&error) {
+        AntnsError::NetworkTimeout { operation, reason }
+    } else if is_payment_failure(&error) {
+        AntnsError::PaymentFailed { operation, reason }
+    } else {
+        AntnsError::Network { operation, reason }
+    }
+}
+
+/// Classify an error returned by the Autonomi client for a register operation
+fn register_error(operation: &str, error: RegisterError) -> AntnsError {
+    if is_missing(&error) {
+        AntnsError::RegisterNotFound(error.to_string())
+    } else {
+        client_error(operation, error)
+    }
+}
+
+/// Map the client's register history result onto the trait's contract
+///
+/// The client reports an unregistered name as a missing head pointer; the
+/// trait promises an empty history for it.
+fn history_or_empty(
+    result: std::result::Result<Vec<RegisterValue>, RegisterError>,
+) -> Result<Vec<RegisterValue>> {
+    match result {
+        Ok(history) => Ok(history),
+        Err(e) => match register_error("register history", e) {
+            AntnsError::RegisterNotFound(_) => Ok(Vec::new()),
+            error => Err(error),
+        },
+    }
+}
+
+#[async_trait]
+impl NameNetwork for Client {
+    type Payment = PaymentOption;
+
+    async fn register_create(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: PaymentOption,
+    ) -> Result<(AttoTokens, RegisterAddress)> {
+        Client::register_create(self, key, value, payment)
+            .await
+            .map_err(|e| register_error("register create", e))
+    }
+
+    async fn register_update(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: PaymentOption,
+    ) -> Result<AttoTokens> {
+        Client::register_update(self, key, value, payment)
+            .await
+            .map_err(|e| register_error("register update", e))
+    }
+
+    async fn register_get(&self, address: &RegisterAddress) -> Result<RegisterValue> {
+        Client::register_get(self, address)
+            .await
+            .map_err(|e| register_error("register get", e))
+    }
+
+    async fn register_history(&self, address: &RegisterAddress) -> Result<Vec<RegisterValue>> {
+        history_or_empty(Client::register_history(self, address).collect().await)
+    }
+
+    async fn data_put_public(
+        &self,
+        data: Bytes,
+        payment: PaymentOption,
+    ) -> Result<(AttoTokens, [u8; 32])> {
+        let (cost, address) = Client::data_put_public(self, data, payment)
+            .await
+            .map_err(|e| client_error("data upload", e))?;
+
+        Ok((cost, address.xorname().0))
+    }
+
+    async fn data_get_public(&self, address: [u8; 32]) -> Result<Bytes> {
+        Client::data_get_public(self, &DataAddress::new(XorName(address)))
+            .await
+            .map_err(|e| client_error("data download", e))
+    }
+
+    async fn archive_get_public(&self, address: [u8; 32]) -> Result<PublicData> {
+        let data = Client::data_get_public(self, &DataAddress::new(XorName(address)))
+            .await
+            .map_err(|e| client_error("archive download", e))?;
+
+        // Archives are ordinary public data; anything that does not decode as
+        // one is a plain file
+        let Ok(archive) = PublicArchive::from_bytes(data.clone()) else {
+            return Ok(PublicData::File(data));
+        };
+
+        Ok(PublicData::Archive(
+            archive
+                .iter()
+                .map(|(path, address, metadata)| ArchiveEntry {
+                    path: path.to_string_lossy().replace('\\', "/"),
+                    address: address.xorname().0,
+                    size: metadata.size,
+                })
+                .collect(),
+        ))
+    }
+
+    async fn chunk_get(&self, address: [u8; 32]) -> Result<Bytes> {
+        let chunk = Client::chunk_get(self, &ChunkAddress::new(XorName(address)))
+            .await
+            .map_err(|e| client_error("chunk download", e))?;
+
+        Ok(chunk.value)
+    }
+
+    async fn vault_put(
+        &self,
+        data: Bytes,
+        payment: PaymentOption,
+        key: &VaultSecretKey,
+        content_type: VaultContentType,
+    ) -> Result<AttoTokens> {
+        Client::vault_put(self, data, payment, key, content_type)
+            .await
+            .map_err(|e| client_error("vault put", e))
+    }
+
+    async fn vault_get(&self, key: &VaultSecretKey) -> Result<(Bytes, VaultContentType)> {
+        Client::vault_get(self, key).await.map_err(|e| {
+            if is_missing(&e) {
+                AntnsError::VaultNotFound
+            } else {
+                client_error("vault get", e)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_register_has_empty_history() {
+        let missing = Err(RegisterError::CannotUpdateNewRegister);
+        assert!(history_or_empty(missing).unwrap().is_empty());
+
+        let history = Ok(vec![[7u8; 32]]);
+        assert_eq!(history_or_empty(history).unwrap(), vec![[7u8; 32]]);
+
+        let corrupt = Err(RegisterError::Corrupt("bad entry".to_string()));
+        assert!(matches!(
+            history_or_empty(corrupt),
+            Err(AntnsError::Network { .. })
+        ));
+    }
+
+    #[test]
+    fn test_client_error_classification() {
+        assert!(matches!(
+            client_error("data download", GetError::RecordNotFound),
+            AntnsError::Network { .. }
+        ));
+        assert!(matches!(
+            client_error("data download", NetworkError::GetRecordTimeout(Vec::new())),
+            AntnsError::NetworkTimeout { .. }
+        ));
+        assert!(matches!(
+            register_error("register get", RegisterError::CannotUpdateNewRegister),
+            AntnsError::RegisterNotFound(_)
+        ));
+    }
+}
@@ -0,0 +1,970 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Deterministic in-memory [`NameNetwork`] for offline testing
+//!
+//! Registers, public data and vaults are kept in process memory. Data is
+//! addressed by the SHA-256 of its content and every paid operation charges a
+//! fixed fake cost against a [`MemoryPayment`], so registration, lookup,
+//! history and backup flows behave like they do on the live network without
+//! any peers.
+
+use crate::error::{AntnsError, Result};
+use crate::network::{ArchiveEntry, NameNetwork, PublicData};
+use async_trait::async_trait;
+use autonomi::client::vault::{VaultContentType, VaultSecretKey};
+use autonomi::register::{RegisterAddress, RegisterValue};
+use autonomi::{AttoTokens, SecretKey};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Fake cost of storing one piece of public data
+pub const MEMORY_DATA_COST: u64 = 10;
+
+/// Fake cost of creating or updating a register
+pub const MEMORY_REGISTER_COST: u64 = 100;
+
+/// Fake cost of writing a vault
+pub const MEMORY_VAULT_COST: u64 = 50;
+
+/// Fake payment for [`MemoryNetwork`]
+///
+/// Clones share the same balance, like clones of a wallet payment option.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPayment {
+    balance: Option<Arc<Mutex<u64>>>,
+}
+
+impl MemoryPayment {
+    /// Payment that never runs out
+    pub fn unlimited() -> Self {
+        Self { balance: None }
+    }
+
+    /// Payment with a limited balance; operations fail once it is spent
+    pub fn with_balance(balance: u64) -> Self {
+        Self {
+            balance: Some(Arc::new(Mutex::new(balance))),
+        }
+    }
+
+    /// Remaining balance, or `None` if unlimited
+    pub fn remaining(&self) -> Option<u64> {
+        self.balance
+            .as_ref()
+            .map(|balance| *balance.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn charge(&self, operation: &str, cost: u64) -> Result<AttoTokens> {
+        if let Some(balance) = &self.balance {
+            let mut balance = balance.lock().unwrap_or_else(|e| e.into_inner());
+            if *balance < cost {
+                return Err(AntnsError::PaymentFailed {
+                    operation: operation.to_string(),
+                    reason: format!("insufficient balance: have {}, need {}", *balance, cost),
+                });
+            }
+            *balance -= cost;
+        }
+
+        Ok(AttoTokens::from_u64(cost))
+    }
+}
+
+#[derive(Default)]
+struct MemoryState {
+    registers: HashMap<String, Vec<RegisterValue>>,
+    data: HashMap<[u8; 32], Bytes>,
+    archives: HashMap<[u8; 32], Vec<ArchiveEntry>>,
+    vaults: HashMap<String, (Bytes, VaultContentType)>,
+}
+
+/// In-memory network backend
+///
+/// Clones share the same storage, so several "clients" can race against
+/// each other in a test.
+#[derive(Clone, Default)]
+pub struct MemoryNetwork {
+    state: Arc<Mutex<MemoryState>>,
+    latency: Duration,
+    lose_create_replies: bool,
+}
+
+impl MemoryNetwork {
+    /// Create an empty network
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every read by `latency`, so concurrent callers overlap like
+    /// they do on the live network
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Report every register creation as timed out after it was stored,
+    /// like a client that never hears back from the network
+    pub fn with_lost_create_replies(mut self) -> Self {
+        self.lose_create_replies = true;
+        self
+    }
+
+    /// Store raw bytes without payment, e.g. to plant corrupt data in tests
+    pub fn insert_data(&self, data: Bytes) -> [u8; 32] {
+        let address = content_address(&data);
+        self.state().data.insert(address, data);
+        address
+    }
+
+    /// Store files and a public archive listing them, without payment
+    ///
+    /// Returns the archive's address.
+    pub fn insert_archive(&self, files: &[(&str, &[u8])]) -> [u8; 32] {
+        let entries: Vec<ArchiveEntry> = files
+            .iter()
+            .map(|(path, data)| ArchiveEntry {
+                path: path.to_string(),
+                address: self.insert_data(Bytes::copy_from_slice(data)),
+                size: data.len() as u64,
+            })
+            .collect();
+
+        let listing: String = entries
+            .iter()
+            .map(|entry| format!("{} {}\n", hex::encode(entry.address), entry.path))
+            .collect();
+        let address = self.insert_data(Bytes::from(listing));
+        self.state().archives.insert(address, entries);
+        address
+    }
+
+    async fn delay(&self) {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Content address of a piece of data
+fn content_address(data: &[u8]) -> [u8; 32] {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    let mut address = [0u8; 32];
+    address.copy_from_slice(digest.as_ref());
+    address
+}
+
+fn register_key(address: &RegisterAddress) -> String {
+    address.to_string()
+}
+
+fn vault_id(key: &VaultSecretKey) -> String {
+    key.public_key().to_hex()
+}
+
+fn missing(operation: &str, what: &str) -> AntnsError {
+    AntnsError::Network {
+        operation: operation.to_string(),
+        reason: format!("{} not found", what),
+    }
+}
+
+#[async_trait]
+impl NameNetwork for MemoryNetwork {
+    type Payment = MemoryPayment;
+
+    async fn register_create(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: MemoryPayment,
+    ) -> Result<(AttoTokens, RegisterAddress)> {
+        let address = RegisterAddress::new(key.public_key());
+        let mut state = self.state();
+
+        if state.registers.contains_key(&register_key(&address)) {
+            return Err(AntnsError::Network {
+                operation: "register create".to_string(),
+                reason: "register already exists".to_string(),
+            });
+        }
+
+        let cost = payment.charge("register create", MEMORY_REGISTER_COST)?;
+        state.registers.insert(register_key(&address), vec![value]);
+
+        if self.lose_create_replies {
+            return Err(AntnsError::NetworkTimeout {
+                operation: "register create".to_string(),
+                reason: "no reply from the network".to_string(),
+            });
+        }
+
+        Ok((cost, address))
+    }
+
+    async fn register_update(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: MemoryPayment,
+    ) -> Result<AttoTokens> {
+        let address = RegisterAddress::new(key.public_key());
+        let mut state = self.state();
+
+        let history = state
+            .registers
+            .get_mut(&register_key(&address))
+            .ok_or_else(|| missing("register update", "register"))?;
+
+        let cost = payment.charge("register update", MEMORY_REGISTER_COST)?;
+        history.push(value);
+
+        Ok(cost)
+    }
+
+    async fn register_get(&self, address: &RegisterAddress) -> Result<RegisterValue> {
+        self.delay().await;
+        self.state()
+            .registers
+            .get(&register_key(address))
+            .and_then(|history| history.last().copied())
+            .ok_or_else(|| AntnsError::RegisterNotFound(address.to_hex()))
+    }
+
+    async fn register_history(&self, address: &RegisterAddress) -> Result<Vec<RegisterValue>> {
+        self.delay().await;
+        Ok(self
+            .state()
+            .registers
+            .get(&register_key(address))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn data_put_public(
+        &self,
+        data: Bytes,
+        payment: MemoryPayment,
+    ) -> Result<(AttoTokens, [u8; 32])> {
+        let cost = payment.charge("data upload", MEMORY_DATA_COST)?;
+        let address = self.insert_data(data);
+
+        Ok((cost, address))
+    }
+
+    async fn data_get_public(&self, address: [u8; 32]) -> Result<Bytes> {
+        self.delay().await;
+        self.state()
+            .data
+            .get(&address)
+            .cloned()
+            .ok_or_else(|| missing("data download", "data"))
+    }
+
+    async fn archive_get_public(&self, address: [u8; 32]) -> Result<PublicData> {
+        self.delay().await;
+        let state = self.state();
+        let data = state
+            .data
+            .get(&address)
+            .ok_or_else(|| missing("archive download", "archive"))?;
+
+        Ok(match state.archives.get(&address) {
+            Some(entries) => PublicData::Archive(entries.clone()),
+            None => PublicData::File(data.clone()),
+        })
+    }
+
+    async fn chunk_get(&self, address: [u8; 32]) -> Result<Bytes> {
+        self.delay().await;
+        self.state()
+            .data
+            .get(&address)
+            .cloned()
+            .ok_or_else(|| missing("chunk download", "chunk"))
+    }
+
+    async fn vault_put(
+        &self,
+        data: Bytes,
+        payment: MemoryPayment,
+        key: &VaultSecretKey,
+        content_type: VaultContentType,
+    ) -> Result<AttoTokens> {
+        let cost = payment.charge("vault put", MEMORY_VAULT_COST)?;
+        self.state()
+            .vaults
+            .insert(vault_id(key), (data, content_type));
+
+        Ok(cost)
+    }
+
+    async fn vault_get(&self, key: &VaultSecretKey) -> Result<(Bytes, VaultContentType)> {
+        self.state()
+            .vaults
+            .get(&vault_id(key))
+            .cloned()
+            .ok_or(AntnsError::VaultNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register::history::calculate_history_stats;
+    use crate::register::{domain_register_key, DnsRecord, DomainRecordsDocument};
+    use crate::storage::chunks::upload_document_as_chunk;
+    use crate::testing::TempDir;
+    use crate::DomainStatus;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    const DOMAIN: &str = "test.ant";
+    const WALLET_KEY: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    fn ant_record(target: &str) -> DnsRecord {
+        DnsRecord {
+            record_type: "ant".to_string(),
+            name: ".".to_string(),
+            value: target.to_string(),
+            ttl: None,
+        }
+    }
+
+    /// Append raw data to a domain's register, like anyone with the shared key can
+    async fn append(network: &MemoryNetwork, domain: &str, address: [u8; 32]) {
+        let key = domain_register_key(domain).unwrap();
+        network
+            .register_update(&key, address, MemoryPayment::unlimited())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_register_and_lookup() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        assert!(registration.ownership_verified);
+
+        let info = crate::inspect_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(info.status, DomainStatus::RegisteredNoRecords);
+
+        crate::add_domain_record(
+            &network,
+            DOMAIN,
+            ant_record("abc123"),
+            &registration.owner_key,
+            payment,
+        )
+        .await
+        .unwrap();
+
+        let resolution = crate::lookup_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(resolution.target, "abc123");
+
+        let info = crate::inspect_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(info.status, DomainStatus::Active);
+
+        assert!(matches!(
+            crate::lookup_domain(&network, "other.ant").await,
+            Err(AntnsError::RegisterNotFound(_))
+        ));
+        let info = crate::inspect_domain(&network, "other.ant").await.unwrap();
+        assert_eq!(info.status, DomainStatus::Unregistered);
+    }
+
+    #[tokio::test]
+    async fn test_taken_domain_costs_nothing() {
+        let network = MemoryNetwork::new();
+        let first = crate::register_domain(&network, DOMAIN, MemoryPayment::unlimited())
+            .await
+            .unwrap();
+
+        let payment = MemoryPayment::with_balance(1_000);
+        let result = crate::register_domain(&network, DOMAIN, payment.clone()).await;
+
+        match result {
+            Err(AntnsError::AlreadyOwnedByOther {
+                owner_public_key, ..
+            }) => assert_eq!(
+                owner_public_key,
+                hex::encode(first.owner_key.verifying_key().to_bytes())
+            ),
+            other => panic!("expected AlreadyOwnedByOther, got {:?}", other.err()),
+        }
+        assert_eq!(payment.remaining(), Some(1_000));
+    }
+
+    #[tokio::test]
+    async fn test_failed_availability_check_costs_nothing() {
+        let network = MemoryNetwork::new();
+        // First entry points at an owner document nobody can fetch
+        let key = domain_register_key(DOMAIN).unwrap();
+        network
+            .register_create(&key, [9u8; 32], MemoryPayment::unlimited())
+            .await
+            .unwrap();
+
+        let payment = MemoryPayment::with_balance(1_000);
+        let result = crate::register_domain(&network, DOMAIN, payment.clone()).await;
+
+        assert!(matches!(
+            result,
+            Err(AntnsError::OwnerDocumentMissing { .. })
+        ));
+        assert_eq!(payment.remaining(), Some(1_000));
+    }
+
+    #[tokio::test]
+    async fn test_lost_create_reply_keeps_the_key() {
+        let network = MemoryNetwork::new().with_lost_create_replies();
+        let payment = MemoryPayment::with_balance(1_000);
+
+        // The register was written even though creating it reported an error
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        assert!(registration.ownership_verified);
+        assert_eq!(
+            registration.total_cost,
+            AttoTokens::from_u64(MEMORY_DATA_COST)
+        );
+        assert_eq!(
+            payment.remaining(),
+            Some(1_000 - MEMORY_DATA_COST - MEMORY_REGISTER_COST)
+        );
+
+        // The returned key is the one that owns the domain
+        crate::update_domain(&network, DOMAIN, "abc123", &registration.owner_key, payment)
+            .await
+            .unwrap();
+        let resolution = crate::lookup_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(resolution.target, "abc123");
+    }
+
+    #[tokio::test]
+    async fn test_spam_is_filtered() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        crate::update_domain(
+            &network,
+            DOMAIN,
+            "abc123",
+            &registration.owner_key,
+            payment.clone(),
+        )
+        .await
+        .unwrap();
+
+        // Validly signed, but by someone who does not own the domain
+        let attacker = SigningKey::generate(&mut OsRng);
+        let spam =
+            DomainRecordsDocument::sign(DOMAIN, vec![ant_record("evil")], 99, &attacker).unwrap();
+        let (_, spam_addr) = upload_document_as_chunk(&network, &spam, payment.clone())
+            .await
+            .unwrap();
+        append(&network, DOMAIN, spam_addr).await;
+
+        // Not a records document at all
+        let junk_addr = network.insert_data(Bytes::from_static(b"not json"));
+        append(&network, DOMAIN, junk_addr).await;
+
+        let resolution = crate::lookup_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(resolution.target, "abc123");
+
+        let history = crate::get_domain_history(&network, DOMAIN).await.unwrap();
+        let stats = calculate_history_stats(&history);
+        assert_eq!(stats.total_entries, 4);
+        assert_eq!(stats.valid_entries, 2);
+        assert_eq!(stats.spam_entries, 1);
+        assert_eq!(stats.invalid_entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_records_are_ignored() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let key = &registration.owner_key;
+
+        crate::update_domain(&network, DOMAIN, "old", key, payment.clone())
+            .await
+            .unwrap();
+        let history = network
+            .register_history(&crate::register::get_register_address_for_domain(DOMAIN).unwrap())
+            .await
+            .unwrap();
+        let old_addr = history[1];
+
+        crate::update_domain(&network, DOMAIN, "new", key, payment)
+            .await
+            .unwrap();
+
+        // Re-append the older, validly signed document
+        append(&network, DOMAIN, old_addr).await;
+
+        let resolution = crate::lookup_domain(&network, DOMAIN).await.unwrap();
+        assert_eq!(resolution.target, "new");
+    }
+
+    #[tokio::test]
+    async fn test_subdomain_lookup() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let records = vec![
+            ant_record("root"),
+            DnsRecord {
+                record_type: "ANT".to_string(),
+                name: "www".to_string(),
+                value: "web".to_string(),
+                ttl: None,
+            },
+        ];
+        crate::update_domain_records(&network, DOMAIN, records, &registration.owner_key, payment)
+            .await
+            .unwrap();
+
+        let www = crate::lookup_domain(&network, "WWW.Test.ant.")
+            .await
+            .unwrap();
+        assert_eq!(www.domain, "www.test.ant");
+        assert_eq!(www.target, "web");
+        assert_eq!(
+            crate::lookup_domain(&network, DOMAIN).await.unwrap().target,
+            "root"
+        );
+
+        // No record named "api", and no fallback to the root record
+        assert!(matches!(
+            crate::lookup_domain(&network, "api.test.ant").await,
+            Err(AntnsError::NoValidRecords(_))
+        ));
+
+        // Subdomains cannot be registered on their own
+        assert!(matches!(
+            crate::register_domain(&network, "www.other.ant", MemoryPayment::unlimited()).await,
+            Err(AntnsError::InvalidDomainName { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_finds_case_sensitive_registration() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        // Registered before names were lowercased: the register is keyed as typed
+        let owner_key = SigningKey::generate(&mut OsRng);
+        let owner = crate::register::DomainOwnerDocument {
+            public_key: hex::encode(owner_key.verifying_key().to_bytes()),
+        };
+        let (_, owner_chunk) = upload_document_as_chunk(&network, &owner, payment.clone())
+            .await
+            .unwrap();
+        let key = domain_register_key("MyDomain.ant").unwrap();
+        network
+            .register_create(&key, owner_chunk, payment.clone())
+            .await
+            .unwrap();
+
+        let found = crate::find_case_sensitive_registration(&network, "MyDomain.ant.")
+            .await
+            .unwrap();
+        assert_eq!(found, Some(RegisterAddress::new(key.public_key())));
+
+        // Updates and lookups of that spelling still use the old register
+        crate::update_domain(&network, "MyDomain.ant", "abc123", &owner_key, payment)
+            .await
+            .unwrap();
+        assert_eq!(
+            network.state().registers[&register_key(&found.unwrap())].len(),
+            2
+        );
+        let resolution = crate::lookup_domain(&network, "MyDomain.ant")
+            .await
+            .unwrap();
+        assert_eq!(resolution.target, "abc123");
+        let quick = crate::register::lookup::quick_lookup(&network, "MyDomain.ant").await;
+        assert_eq!(quick.unwrap(), "abc123");
+        assert!(matches!(
+            crate::lookup_domain(&network, "mydomain.ant").await,
+            Err(AntnsError::RegisterNotFound(_))
+        ));
+
+        // Canonical names and unused spellings have nothing to report
+        for name in ["mydomain.ant", "MYDOMAIN.ant"] {
+            let found = crate::find_case_sensitive_registration(&network, name)
+                .await
+                .unwrap();
+            assert_eq!(found, None, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deleted_root_record_stops_resolution() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let key = &registration.owner_key;
+        crate::update_domain(&network, DOMAIN, "abc123", key, payment.clone())
+            .await
+            .unwrap();
+        crate::add_domain_record(
+            &network,
+            DOMAIN,
+            DnsRecord {
+                record_type: "TXT".to_string(),
+                name: ".".to_string(),
+                value: "hello".to_string(),
+                ttl: None,
+            },
+            key,
+            payment.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            crate::lookup_domain(&network, DOMAIN).await.unwrap().target,
+            "abc123"
+        );
+
+        crate::delete_domain_record(&network, DOMAIN, 0, key, payment)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            crate::lookup_domain(&network, DOMAIN).await,
+            Err(AntnsError::NoValidRecords(_))
+        ));
+        let resolved = crate::Resolver::new(&network)
+            .resolve(DOMAIN)
+            .await
+            .unwrap();
+        assert_eq!(resolved.root_target(), None);
+        assert_eq!(resolved.records_for(".", crate::RecordType::Txt).count(), 1);
+        assert_eq!(
+            crate::lookup_domain_records(&network, DOMAIN)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delegated_subdomain() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+        let record = |record_type: &str, name: &str, value: &str| DnsRecord {
+            record_type: record_type.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            ttl: None,
+        };
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let parent_key = &registration.owner_key;
+        let team_key = SigningKey::generate(&mut OsRng);
+        let team_hex = hex::encode(team_key.verifying_key().to_bytes());
+
+        let records = vec![
+            ant_record("root"),
+            // Shadowed by the delegation below
+            record("ANT", "www.team", "parent-www"),
+            record("DELEGATE", "team", &team_hex),
+        ];
+        crate::update_domain_records(&network, DOMAIN, records, parent_key, payment.clone())
+            .await
+            .unwrap();
+
+        // Delegated, but nothing published yet
+        assert!(matches!(
+            crate::lookup_domain(&network, "team.test.ant").await,
+            Err(AntnsError::NoValidRecords(_))
+        ));
+
+        let team_records = vec![ant_record("team-root"), record("ANT", "www", "team-www")];
+        crate::update_domain_records(
+            &network,
+            "team.test.ant",
+            team_records,
+            &team_key,
+            payment.clone(),
+        )
+        .await
+        .unwrap();
+
+        let team = crate::lookup_domain(&network, "team.test.ant")
+            .await
+            .unwrap();
+        assert_eq!(team.target, "team-root");
+        assert_eq!(team.owner_public_key, team_hex);
+        assert_eq!(
+            crate::lookup_domain(&network, "www.team.test.ant")
+                .await
+                .unwrap()
+                .target,
+            "team-www"
+        );
+
+        // The parent key cannot write into the delegated zone
+        let forged =
+            DomainRecordsDocument::sign("team.test.ant", vec![ant_record("evil")], 99, parent_key)
+                .unwrap();
+        let (_, forged_addr) = upload_document_as_chunk(&network, &forged, payment.clone())
+            .await
+            .unwrap();
+        append(&network, "team.test.ant", forged_addr).await;
+        assert_eq!(
+            crate::lookup_domain(&network, "team.test.ant")
+                .await
+                .unwrap()
+                .target,
+            "team-root"
+        );
+        assert!(matches!(
+            crate::update_domain(&network, "team.test.ant", "x", parent_key, payment.clone()).await,
+            Err(AntnsError::Delegation { .. })
+        ));
+
+        // Subdomains that are plain records cannot publish their own
+        assert!(matches!(
+            crate::update_domain(&network, "www.test.ant", "x", parent_key, payment.clone()).await,
+            Err(AntnsError::Delegation { .. })
+        ));
+
+        // A second level of delegation
+        let dev_key = SigningKey::generate(&mut OsRng);
+        let dev_hex = hex::encode(dev_key.verifying_key().to_bytes());
+        crate::add_domain_record(
+            &network,
+            "team.test.ant",
+            record("DELEGATE", "dev", &dev_hex),
+            &team_key,
+            payment.clone(),
+        )
+        .await
+        .unwrap();
+        crate::update_domain(&network, "dev.team.test.ant", "dev-root", &dev_key, payment)
+            .await
+            .unwrap();
+
+        let resolved = crate::Resolver::new(&network)
+            .resolve_name(&crate::DomainName::parse("dev.team.test.ant").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resolved.target(), Some("dev-root"));
+        assert_eq!(resolved.depth, 2);
+        assert!(matches!(
+            &resolved.zone.authority,
+            crate::ZoneAuthority::Delegated { parent, .. } if parent == "team.test.ant"
+        ));
+
+        let options = crate::LookupOptions {
+            max_delegation_depth: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            crate::lookup_domain_with(&network, "dev.team.test.ant", &options).await,
+            Err(AntnsError::Delegation { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_lookup_matches_sequential() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let key = &registration.owner_key;
+        let attacker = SigningKey::generate(&mut OsRng);
+
+        for i in 0..12u64 {
+            crate::update_domain(
+                &network,
+                DOMAIN,
+                &format!("target-{}", i),
+                key,
+                payment.clone(),
+            )
+            .await
+            .unwrap();
+            let spam =
+                DomainRecordsDocument::sign(DOMAIN, vec![ant_record("evil")], 100 + i, &attacker)
+                    .unwrap();
+            let (_, spam_addr) = upload_document_as_chunk(&network, &spam, payment.clone())
+                .await
+                .unwrap();
+            append(&network, DOMAIN, spam_addr).await;
+        }
+
+        let sequential = crate::LookupOptions {
+            concurrency: 1,
+            ..Default::default()
+        };
+        let concurrent = crate::LookupOptions {
+            concurrency: 16,
+            ..Default::default()
+        };
+
+        let (resolution, stats) = crate::lookup_domain_with(&network, DOMAIN, &concurrent)
+            .await
+            .unwrap();
+        let (expected, _) = crate::lookup_domain_with(&network, DOMAIN, &sequential)
+            .await
+            .unwrap();
+        assert_eq!(resolution.target, "target-11");
+        assert_eq!(resolution.target, expected.target);
+        assert_eq!(stats.entries, 24);
+        assert_eq!(stats.valid_entries, 12);
+        assert_eq!(stats.invalid_entries, 12);
+        assert_eq!(stats.concurrency, 16);
+
+        let (history, _) = crate::get_domain_history_with(&network, DOMAIN, &concurrent)
+            .await
+            .unwrap();
+        let (expected, _) = crate::get_domain_history_with(&network, DOMAIN, &sequential)
+            .await
+            .unwrap();
+        assert_eq!(
+            calculate_history_stats(&history),
+            calculate_history_stats(&expected)
+        );
+        let chunks = |entries: &[crate::register::HistoryEntry]| -> Vec<String> {
+            entries
+                .iter()
+                .map(|entry| match entry {
+                    crate::register::HistoryEntry::Owner { chunk_address, .. }
+                    | crate::register::HistoryEntry::Records { chunk_address, .. } => {
+                        chunk_address.clone()
+                    }
+                })
+                .collect()
+        };
+        assert_eq!(chunks(&history), chunks(&expected));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_resumes_from_checkpoint() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+        let dir = TempDir::new("lookup-checkpoints");
+        let options = crate::LookupOptions {
+            checkpoints: Some(crate::storage::CheckpointCache::new(dir.path(), u64::MAX)),
+            ..Default::default()
+        };
+
+        let registration = crate::register_domain(&network, DOMAIN, payment.clone())
+            .await
+            .unwrap();
+        let key = &registration.owner_key;
+        for target in ["one", "two", "three"] {
+            crate::update_domain(&network, DOMAIN, target, key, payment.clone())
+                .await
+                .unwrap();
+        }
+
+        let (resolution, stats) = crate::lookup_domain_with(&network, DOMAIN, &options)
+            .await
+            .unwrap();
+        assert_eq!(resolution.target, "three");
+        assert_eq!((stats.entries, stats.cached_entries), (3, 0));
+
+        // An entry whose data is not retrievable yet, followed by a newer update
+        let pending =
+            DomainRecordsDocument::sign(DOMAIN, vec![ant_record("four")], 4, key).unwrap();
+        let pending_bytes = Bytes::from(serde_json::to_vec(&pending).unwrap());
+        append(&network, DOMAIN, content_address(&pending_bytes)).await;
+        crate::update_domain(&network, DOMAIN, "five", key, payment.clone())
+            .await
+            .unwrap();
+
+        let (resolution, stats) = crate::lookup_domain_with(&network, DOMAIN, &options)
+            .await
+            .unwrap();
+        assert_eq!(resolution.target, "five");
+        assert_eq!((stats.entries, stats.cached_entries), (2, 3));
+        assert_eq!(stats.invalid_entries, 1);
+
+        // The failed download is retried once the data shows up
+        network.insert_data(pending_bytes);
+        let (document, stats) = crate::lookup_domain_document_with(&network, DOMAIN, &options)
+            .await
+            .unwrap();
+        assert_eq!((stats.entries, stats.cached_entries), (2, 3));
+        assert_eq!(stats.invalid_entries, 0);
+
+        // Same result as walking the full history
+        let full = crate::lookup_domain_document(&network, DOMAIN)
+            .await
+            .unwrap();
+        assert_eq!(document.unwrap().signature, full.unwrap().signature);
+
+        // Nothing new: everything comes from the checkpoint
+        let (_, stats) = crate::lookup_domain_with(&network, DOMAIN, &options)
+            .await
+            .unwrap();
+        assert_eq!((stats.entries, stats.cached_entries), (0, 5));
+    }
+
+    #[tokio::test]
+    async fn test_key_backup_and_restore() {
+        let network = MemoryNetwork::new();
+        let source = TempDir::new("backup-src");
+        let target = TempDir::new("backup-dst");
+
+        let keypair = crate::crypto::DomainKeypair::generate();
+        crate::crypto::save_keypair_in(source.path(), DOMAIN, &keypair).unwrap();
+
+        crate::vault::backup_keys_from_dir(
+            &network,
+            source.path(),
+            WALLET_KEY,
+            MemoryPayment::unlimited(),
+        )
+        .await
+        .unwrap();
+        crate::vault::restore_keys_to_dir(&network, target.path(), WALLET_KEY)
+            .await
+            .unwrap();
+
+        let restored =
+            std::fs::read_to_string(target.path().join(format!("domain-key-{}.txt", DOMAIN)))
+                .unwrap();
+        assert_eq!(restored, hex::encode(keypair.to_bytes()));
+
+        // A different wallet has no backup
+        let other_wallet = "0x2222222222222222222222222222222222222222222222222222222222222222";
+        assert!(matches!(
+            crate::vault::restore_keys_to_dir(&network, target.path(), other_wallet).await,
+            Err(AntnsError::VaultNotFound)
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Network backends for name operations
+//!
+//! Everything AntNS stores lives in registers, public data and vaults, and
+//! the HTTP proxy reads the public archives names point at. The
+//! [`NameNetwork`] trait covers exactly those operations so that the
+//! register, storage, vault and proxy code can run against the live Autonomi
+//! network or against [`memory::MemoryNetwork`] in tests.
+
+pub mod autonomi;
+pub mod memory;
+
+pub use memory::{MemoryNetwork, MemoryPayment};
+
+use crate::error::Result;
+use ::autonomi::client::vault::{VaultContentType, VaultSecretKey};
+use ::autonomi::register::{RegisterAddress, RegisterValue};
+use ::autonomi::{AttoTokens, SecretKey};
+use async_trait::async_trait;
+use bytes::Bytes;
+
+/// A file in a public archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file inside the archive, `/`-separated
+    pub path: String,
+    /// Address of the file's public data
+    pub address: [u8; 32],
+    /// File size in bytes
+    pub size: u64,
+}
+
+/// Public data fetched without knowing whether it is an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicData {
+    /// Files of a public archive
+    Archive(Vec<ArchiveEntry>),
+    /// Any other data, already downloaded
+    File(Bytes),
+}
+
+/// Storage operations a name system backend must provide
+///
+/// Implementations classify their failures into [`crate::AntnsError`]
+/// variants (timeouts, payment failures, ...) so callers never inspect
+/// backend-specific error types.
+#[async_trait]
+pub trait NameNetwork: Send + Sync {
+    /// How paid operations are funded
+    type Payment: Clone + Send + Sync;
+
+    /// Create a register owned by `key` with an initial value
+    async fn register_create(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: Self::Payment,
+    ) -> Result<(AttoTokens, RegisterAddress)>;
+
+    /// Append a new value to the register owned by `key`
+    async fn register_update(
+        &self,
+        key: &SecretKey,
+        value: RegisterValue,
+        payment: Self::Payment,
+    ) -> Result<AttoTokens>;
+
+    /// Current (latest) value of a register
+    async fn register_get(&self, address: &RegisterAddress) -> Result<RegisterValue>;
+
+    /// All values of a register, oldest first
+    ///
+    /// A register that does not exist has an empty history.
+    async fn register_history(&self, address: &RegisterAddress) -> Result<Vec<RegisterValue>>;
+
+    /// Store public data and return its 32-byte address
+    async fn data_put_public(
+        &self,
+        data: Bytes,
+        payment: Self::Payment,
+    ) -> Result<(AttoTokens, [u8; 32])>;
+
+    /// Fetch public data stored with [`NameNetwork::data_put_public`]
+    async fn data_get_public(&self, address: [u8; 32]) -> Result<Bytes>;
+
+    /// Files of the public archive at `address`, or the data itself if it
+    /// is not an archive
+    ///
+    /// Telling the two apart takes downloading the data, so plain files are
+    /// returned as they are rather than fetched a second time.
+    async fn archive_get_public(&self, address: [u8; 32]) -> Result<PublicData>;
+
+    /// Fetch a single chunk by address
+    async fn chunk_get(&self, address: [u8; 32]) -> Result<Bytes>;
+
+    /// Store data in the vault owned by `key`, replacing previous content
+    async fn vault_put(
+        &self,
+        data: Bytes,
+        payment: Self::Payment,
+        key: &VaultSecretKey,
+        content_type: VaultContentType,
+    ) -> Result<AttoTokens>;
+
+    /// Fetch the content of the vault owned by `key`
+    async fn vault_get(&self, key: &VaultSecretKey) -> Result<(Bytes, VaultContentType)>;
+}
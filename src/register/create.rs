@@ -3,68 +3,126 @@
 
 //! Domain registration operations
 
-use crate::constants::DNS_REGISTER_KEY_HEX;
 use crate::crypto::DomainKeypair;
-use crate::register::{DomainOwnerDocument, DomainRegistration};
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
+use crate::register::lookup::lookup_domain_owner;
+use crate::register::name::registered_domain;
+use crate::register::{domain_register_key, DomainOwnerDocument, DomainRegistration};
 use crate::storage::chunks::upload_document_as_chunk;
-use anyhow::{Context, Result};
-use autonomi::client::payment::PaymentOption;
-use autonomi::{Client, SecretKey};
+use anyhow::Context;
+use autonomi::register::RegisterAddress;
+use autonomi::Client;
+use std::time::Duration;
+
+/// How many times to read back the register after creating it
+const OWNERSHIP_CHECK_ATTEMPTS: u32 = 5;
+
+/// Delay between ownership read-back attempts
+const OWNERSHIP_CHECK_DELAY: Duration = Duration::from_secs(2);
 
 /// Register a new domain on the Autonomi network
 ///
 /// This only creates the domain ownership, no records are added.
 /// Use add_domain_record to add records after registration.
 ///
+/// The register address is derived from the domain name, so two clients can
+/// race to create it. Before paying, the register is checked for an existing
+/// owner, and registration stops before any payment if that check fails.
+/// After creation the first register entry is read back and compared
+/// against the generated key; only the owner document in that entry counts.
+///
 /// # Arguments
-/// * `client` - Autonomi client instance
+/// * `client` - Network backend (e.g. an Autonomi client)
 /// * `domain` - Domain name (e.g., "mydomain.ant")
 /// * `payment` - Payment option for network storage
 ///
 /// # Returns
-/// Domain registration details including the generated keypair
-pub async fn register_domain(
-    client: &Client,
+/// Domain registration details including the generated keypair, or a
+/// [`AntnsError::AlreadyOwnedByOther`] / [`AntnsError::RegistrationLost`]
+/// naming who owns the domain instead
+pub async fn register_domain<N: NameNetwork>(
+    client: &N,
     domain: &str,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<DomainRegistration> {
-    // Step 1: Generate Ed25519 keypair for domain ownership
+    // Names are registered in canonical (lowercase) form; subdomains are records
+    let domain = registered_domain(domain)?;
+    let domain = domain.as_str();
+
+    // Step 1: Make sure nobody owns the domain yet
+    match lookup_domain_owner(client, domain).await {
+        Ok(Some(owner)) => {
+            return Err(AntnsError::AlreadyOwnedByOther {
+                domain: domain.to_string(),
+                owner_public_key: owner.document.public_key,
+            })
+        }
+        Ok(None) | Err(AntnsError::RegisterNotFound(_)) => {}
+        // Nothing has been paid yet; never pay for a name we could not check
+        Err(e) => return Err(e),
+    }
+
+    // Step 2: Generate Ed25519 keypair for domain ownership
     let keypair = DomainKeypair::generate();
+    let public_key_hex = keypair.public_key_hex();
 
-    // Step 2: Create owner document
+    // Step 3: Create owner document
     let owner_doc = DomainOwnerDocument {
-        public_key: keypair.public_key_hex(),
+        public_key: public_key_hex.clone(),
     };
 
-    // Step 3: Upload owner document as public chunk
+    // Step 4: Upload owner document as public chunk
     let (owner_cost, owner_chunk_addr) =
-        upload_document_as_chunk(client, &owner_doc, payment.clone())
-            .await
-            .context("Failed to upload owner document")?;
+        upload_document_as_chunk(client, &owner_doc, payment.clone()).await?;
 
     tracing::debug!(
         "Owner document uploaded to chunk: {}",
         hex::encode(owner_chunk_addr)
     );
 
-    // Step 4: Convert chunk address to RegisterValue (32 bytes)
+    // Step 5: Convert chunk address to RegisterValue (32 bytes)
     let owner_value = Client::register_value_from_bytes(&owner_chunk_addr)
         .context("Failed to create register value from chunk address")?;
 
-    // Step 5: Create register with shared DNS key
-    let shared_key_bytes = hex::decode(DNS_REGISTER_KEY_HEX)?;
-    let shared_key_array: [u8; 32] = shared_key_bytes
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("Invalid DNS register key length"))?;
-    let shared_key = SecretKey::from_bytes(shared_key_array)
-        .map_err(|e| anyhow::anyhow!("Invalid DNS register key: {:?}", e))?;
-
-    let register_key = Client::register_key_from_name(&shared_key, domain);
+    // Step 6: Create register with shared DNS key
+    let register_key = domain_register_key(domain)?;
 
-    let (register_cost, register_addr) = client
+    let (register_cost, register_addr) = match client
         .register_create(&register_key, owner_value, payment.clone())
         .await
-        .context("Failed to create register")?;
+    {
+        Ok(created) => created,
+        // Creation fails if someone else got there first, but also when the
+        // write landed and only its confirmation was lost
+        Err(e) => match lookup_domain_owner(client, domain).await {
+            Ok(Some(owner)) if owner.is_key(&public_key_hex) => {
+                tracing::warn!(
+                    "Creating the register for '{}' reported an error, but it names our key: {}",
+                    domain,
+                    e
+                );
+                // The register's cost is unknown, so only the owner document is counted
+                return Ok(DomainRegistration {
+                    domain: domain.to_string(),
+                    register_address: RegisterAddress::new(register_key.public_key()),
+                    owner_key: keypair.signing_key,
+                    total_cost: owner_cost,
+                    ownership_verified: true,
+                });
+            }
+            Ok(Some(owner)) => {
+                return Err(AntnsError::RegistrationLost {
+                    domain: domain.to_string(),
+                    owner_public_key: owner.document.public_key,
+                })
+            }
+            _ => return Err(e),
+        },
+    };
+
+    // Step 7: Read back the first entry to confirm we own the domain
+    let ownership_verified = verify_ownership(client, domain, &public_key_hex).await?;
 
     tracing::info!("Domain '{}' registered at: {}", domain, register_addr);
 
@@ -78,20 +136,55 @@ pub async fn register_domain(
         register_address: register_addr,
         owner_key: keypair.signing_key,
         total_cost,
+        ownership_verified,
     })
 }
 
+/// Confirm that the first register entry of a domain names our public key
+///
+/// Retries while the register is not yet readable, since a freshly created
+/// register may take a moment to replicate. Returns false if the entry could
+/// not be read at all; the caller still holds the only copy of the key then.
+async fn verify_ownership<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    public_key_hex: &str,
+) -> Result<bool> {
+    for attempt in 1..=OWNERSHIP_CHECK_ATTEMPTS {
+        match lookup_domain_owner(client, domain).await {
+            Ok(Some(owner)) if owner.is_key(public_key_hex) => return Ok(true),
+            Ok(Some(owner)) => {
+                return Err(AntnsError::RegistrationLost {
+                    domain: domain.to_string(),
+                    owner_public_key: owner.document.public_key,
+                })
+            }
+            Ok(None) => {
+                tracing::debug!("Register for '{}' not readable yet", domain);
+            }
+            Err(e) => {
+                tracing::debug!("Ownership check attempt {} failed: {:#}", attempt, e);
+            }
+        }
+
+        if attempt < OWNERSHIP_CHECK_ATTEMPTS {
+            tokio::time::sleep(OWNERSHIP_CHECK_DELAY).await;
+        }
+    }
+
+    tracing::warn!("Could not read back the register for '{}'", domain);
+    Ok(false)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_domain_validation() {
-        // Add domain name validation tests
-        assert!(validate_domain_name("mydomain.ant"));
-        assert!(validate_domain_name("test.autonomi"));
-        assert!(!validate_domain_name("invalid"));
-    }
-
-    fn validate_domain_name(domain: &str) -> bool {
-        domain.ends_with(".ant") || domain.ends_with(".autonomi")
+        assert!(registered_domain("mydomain.ant").is_ok());
+        assert!(registered_domain("test.autonomi").is_ok());
+        assert!(registered_domain("invalid").is_err());
+        assert!(registered_domain("www.mydomain.ant").is_err());
     }
 }
@@ -0,0 +1,186 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Delegated subdomains
+//!
+//! A `DELEGATE` record in a domain's signed records hands a label over to a
+//! different key. The record is named after the label (e.g. `team` in
+//! `company.ant`) and its value is the child's Ed25519 public key in hex,
+//! optionally followed by a space and the hex address of the register that
+//! holds the child's records:
+//!
+//! ```text
+//! DELEGATE team 3b6a27bc...e1a2
+//! DELEGATE team 3b6a27bc...e1a2 8f2c...register-address
+//! ```
+//!
+//! Without an explicit register, the child's records live in the register
+//! derived from the full child name (`team.company.ant`). Every entry in the
+//! child register must be a records document signed by the delegated key for
+//! the child name; there is no owner document.
+//!
+//! A delegation is a zone cut: once `team` is delegated, every name at or
+//! below `team.company.ant` is resolved from the child's records, even if
+//! the parent also has records named `team` or `www.team`.
+
+use crate::error::{AntnsError, Result};
+use crate::register::record::RecordType;
+use crate::register::{get_register_address_for_domain, DnsRecord};
+use anyhow::Context;
+use autonomi::register::RegisterAddress;
+use ed25519_dalek::VerifyingKey;
+
+/// A label handed over to a child key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    /// Key that signs the child's records documents
+    pub public_key: VerifyingKey,
+    /// Register holding the child's records, if not the derived one
+    pub register: Option<RegisterAddress>,
+}
+
+impl Delegation {
+    /// Parse the value of a `DELEGATE` record for the child name `child`
+    pub fn parse(child: &str, value: &str) -> Result<Self> {
+        Self::from_value(value).map_err(|reason| AntnsError::Delegation {
+            name: child.to_string(),
+            reason,
+        })
+    }
+
+    /// Parse a `DELEGATE` record value, returning the reason it is invalid
+    pub(crate) fn from_value(value: &str) -> std::result::Result<Self, String> {
+        let mut parts = value.split_whitespace();
+        let key_hex = parts.next().ok_or("missing public key")?;
+        let register_hex = parts.next();
+        if parts.next().is_some() {
+            return Err("expected '<public key> [register address]'".to_string());
+        }
+
+        let key_bytes: [u8; 32] = hex::decode(key_hex)
+            .map_err(|e| format!("invalid public key: {}", e))?
+            .try_into()
+            .map_err(|_| "public key must be 32 bytes")?;
+        let public_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| format!("invalid public key: {}", e))?;
+
+        let register = register_hex
+            .map(|hex| {
+                RegisterAddress::from_hex(hex)
+                    .map_err(|e| format!("invalid register address: {}", e))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            public_key,
+            register,
+        })
+    }
+
+    /// Record value for this delegation
+    pub fn to_value(&self) -> String {
+        match &self.register {
+            Some(register) => format!("{} {}", self.public_key_hex(), register.to_hex()),
+            None => self.public_key_hex(),
+        }
+    }
+
+    /// Delegated public key (hex)
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.public_key.to_bytes())
+    }
+
+    /// Register holding the records of the child name `child`
+    pub fn register_for(&self, child: &str) -> Result<RegisterAddress> {
+        match self.register {
+            Some(register) => Ok(register),
+            None => Ok(get_register_address_for_domain(child)
+                .context("Failed to derive register address")?),
+        }
+    }
+}
+
+/// Find the zone cut for a record name within a zone's records
+///
+/// `record_name` is relative to the zone (`.` for the zone itself). Labels
+/// are checked from the one closest to the zone outwards, so `www.team`
+/// is cut at `team` if that label is delegated. Returns the delegated label
+/// (relative to the zone), the remaining record name relative to the child
+/// (`.` if the name is the child itself) and the delegation record.
+pub fn find_zone_cut<'a>(
+    records: &'a [DnsRecord],
+    record_name: &str,
+) -> Option<(String, String, &'a DnsRecord)> {
+    if record_name == crate::constants::ROOT_RECORD_NAME {
+        return None;
+    }
+
+    let labels: Vec<&str> = record_name.split('.').collect();
+    for split in (0..labels.len()).rev() {
+        let label = labels[split..].join(".");
+        if let Some(record) = records
+            .iter()
+            .find(|r| r.is_type(RecordType::Delegate) && r.name.eq_ignore_ascii_case(&label))
+        {
+            let remaining = if split == 0 {
+                crate::constants::ROOT_RECORD_NAME.to_string()
+            } else {
+                labels[..split].join(".")
+            };
+            return Some((label, remaining, record));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn record(record_type: &str, name: &str, value: &str) -> DnsRecord {
+        DnsRecord {
+            record_type: record_type.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            ttl: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_delegation() {
+        let key = SigningKey::generate(&mut OsRng).verifying_key();
+        let key_hex = hex::encode(key.to_bytes());
+
+        let delegation = Delegation::parse("team.company.ant", &key_hex).unwrap();
+        assert_eq!(delegation.public_key, key);
+        assert!(delegation.register.is_none());
+        assert_eq!(delegation.to_value(), key_hex);
+
+        assert!(Delegation::parse("team.company.ant", "").is_err());
+        assert!(Delegation::parse("team.company.ant", "abcd").is_err());
+        assert!(Delegation::parse("team.company.ant", &format!("{} zz", key_hex)).is_err());
+        assert!(Delegation::parse("team.company.ant", &format!("{} a b", key_hex)).is_err());
+    }
+
+    #[test]
+    fn test_find_zone_cut() {
+        let records = vec![
+            record("ANT", "www.team", "parent-record"),
+            record("DELEGATE", "team", "key"),
+            record("ANT", "www", "web"),
+        ];
+
+        assert!(find_zone_cut(&records, ".").is_none());
+        assert!(find_zone_cut(&records, "www").is_none());
+
+        let (label, remaining, _) = find_zone_cut(&records, "team").unwrap();
+        assert_eq!((label.as_str(), remaining.as_str()), ("team", "."));
+
+        // The delegation wins over the parent's own "www.team" record
+        let (label, remaining, _) = find_zone_cut(&records, "api.www.team").unwrap();
+        assert_eq!((label.as_str(), remaining.as_str()), ("team", "api.www"));
+    }
+}
@@ -3,100 +3,46 @@
 
 //! Domain history operations
 
-use crate::crypto::verify_records;
-use crate::register::get_register_address_for_domain;
-use crate::register::{DomainOwnerDocument, DomainRecordsDocument, HistoryEntry};
-use anyhow::{Context, Result};
-use autonomi::chunk::ChunkAddress;
-use autonomi::Client;
-use ed25519_dalek::VerifyingKey;
-use xor_name::XorName;
+use crate::error::Result;
+use crate::network::NameNetwork;
+use crate::register::lookup::{LookupOptions, LookupStats};
+use crate::register::resolver::Resolver;
+use crate::register::HistoryEntry;
 
 /// Get the full history of a domain including all entries and their validation status
 ///
 /// # Arguments
-/// * `client` - Autonomi client instance
+/// * `client` - Network backend (e.g. an Autonomi client)
 /// * `domain` - Domain name to query
 ///
 /// # Returns
 /// Vector of history entries with validation status
-pub async fn get_domain_history(client: &Client, domain: &str) -> Result<Vec<HistoryEntry>> {
-    // Get register address
-    let register_addr =
-        get_register_address_for_domain(domain).context("Failed to derive register address")?;
-
-    tracing::debug!(
-        "Fetching history for domain '{}' at register: {}",
-        domain,
-        register_addr
-    );
-
-    // Fetch register history
-    let mut history = client.register_history(&register_addr);
-
-    let mut entries = Vec::new();
-
-    // First entry: owner document
-    let owner_chunk_addr = history
-        .next()
-        .await?
-        .ok_or_else(|| anyhow::anyhow!("Register not found for domain: {}", domain))?;
-
-    let owner_chunk = ChunkAddress::new(XorName(owner_chunk_addr));
-    let owner_chunk_data = client
-        .chunk_get(&owner_chunk)
-        .await
-        .context("Failed to download owner document chunk")?;
-
-    let owner_doc: DomainOwnerDocument = serde_json::from_slice(owner_chunk_data.value.as_ref())
-        .context("Failed to parse owner document")?;
-
-    // Parse owner public key for verification
-    let owner_pubkey_bytes = hex::decode(&owner_doc.public_key)?;
-    let owner_pubkey = VerifyingKey::from_bytes(owner_pubkey_bytes.as_slice().try_into()?)?;
-
-    entries.push(HistoryEntry::Owner {
-        public_key: owner_doc.public_key.clone(),
-        chunk_address: hex::encode(owner_chunk_addr),
-    });
-
-    // Subsequent entries: records
-    while let Some(chunk_addr) = history.next().await? {
-        let chunk = ChunkAddress::new(XorName(chunk_addr));
-
-        // Try to download and parse
-        let (records, signature, is_valid) = match client.chunk_get(&chunk).await {
-            Ok(chunk_data) => {
-                match serde_json::from_slice::<DomainRecordsDocument>(chunk_data.value.as_ref()) {
-                    Ok(doc) => {
-                        // Verify signature
-                        let is_valid = verify_records(&doc.records, &doc.signature, &owner_pubkey);
-                        (Some(doc.records), Some(doc.signature), is_valid)
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to parse records document: {}", e);
-                        (None, None, false)
-                    }
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Failed to download chunk: {}", e);
-                (None, None, false)
-            }
-        };
-
-        entries.push(HistoryEntry::Records {
-            chunk_address: hex::encode(chunk_addr),
-            records,
-            signature,
-            is_valid,
-        });
-    }
-
+pub async fn get_domain_history<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<Vec<HistoryEntry>> {
+    let (entries, _) = get_domain_history_with(client, domain, &LookupOptions::default()).await?;
     Ok(entries)
 }
 
+/// Get the full history of a domain with explicit options
+///
+/// Also returns entry counts and timings for the walk.
+pub async fn get_domain_history_with<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    options: &LookupOptions,
+) -> Result<(Vec<HistoryEntry>, LookupStats)> {
+    let resolved = Resolver::with_options(client, options.clone())
+        .resolve_with_history(domain)
+        .await?;
+
+    let entries = resolved.history().unwrap_or_default().to_vec();
+    Ok((entries, resolved.stats))
+}
+
 /// Get statistics about a domain's history
+#[derive(Debug, Clone, PartialEq)]
 pub struct HistoryStats {
     pub total_entries: usize,
     pub valid_entries: usize,
@@ -104,6 +50,17 @@ pub struct HistoryStats {
     pub spam_entries: usize,
 }
 
+impl HistoryStats {
+    /// Fraction of records entries (everything after the owner entry) that are spam
+    pub fn spam_ratio(&self) -> f64 {
+        let records_entries = self.total_entries.saturating_sub(1);
+        if records_entries == 0 {
+            return 0.0;
+        }
+        self.spam_entries as f64 / records_entries as f64
+    }
+}
+
 /// Calculate statistics from history entries
 pub fn calculate_history_stats(entries: &[HistoryEntry]) -> HistoryStats {
     let mut stats = HistoryStats {
@@ -136,3 +93,45 @@ pub fn calculate_history_stats(entries: &[HistoryEntry]) -> HistoryStats {
 
     stats
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records_entry(
+        records: Option<Vec<crate::register::DnsRecord>>,
+        is_valid: bool,
+    ) -> HistoryEntry {
+        HistoryEntry::Records {
+            chunk_address: String::new(),
+            records,
+            sequence: None,
+            signature: None,
+            is_valid,
+        }
+    }
+
+    #[test]
+    fn test_history_stats() {
+        let entries = vec![
+            HistoryEntry::Owner {
+                public_key: String::new(),
+                chunk_address: String::new(),
+            },
+            records_entry(Some(vec![]), true),
+            records_entry(Some(vec![]), false),
+            records_entry(Some(vec![]), false),
+            records_entry(None, false),
+        ];
+
+        let stats = calculate_history_stats(&entries);
+        assert_eq!(stats.total_entries, 5);
+        assert_eq!(stats.valid_entries, 2);
+        assert_eq!(stats.spam_entries, 2);
+        assert_eq!(stats.invalid_entries, 1);
+        assert_eq!(stats.spam_ratio(), 0.5);
+
+        // Owner only: nothing to be spam
+        assert_eq!(calculate_history_stats(&entries[..1]).spam_ratio(), 0.0);
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Domain availability and ownership inspection
+
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
+use crate::register::get_register_address_for_domain;
+use crate::register::history::{calculate_history_stats, HistoryStats};
+use crate::register::lookup::LookupOptions;
+use crate::register::name::registered_domain;
+use crate::register::resolver::Resolver;
+use crate::register::{DnsRecord, DomainRecordsDocument};
+use anyhow::Context;
+use autonomi::register::RegisterAddress;
+
+/// Registration status of a domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomainStatus {
+    /// Nobody has created the domain's register yet
+    Unregistered,
+    /// The domain has an owner but no valid records
+    RegisteredNoRecords,
+    /// The domain has an owner and valid records
+    Active,
+}
+
+impl DomainStatus {
+    /// Status implied by the current records document of a registered domain
+    pub fn from_current(current: Option<&DomainRecordsDocument>) -> Self {
+        match current {
+            Some(doc) if !doc.records.is_empty() => DomainStatus::Active,
+            _ => DomainStatus::RegisteredNoRecords,
+        }
+    }
+
+    /// Whether the domain can still be registered
+    pub fn is_available(&self) -> bool {
+        *self == DomainStatus::Unregistered
+    }
+}
+
+impl std::fmt::Display for DomainStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            DomainStatus::Unregistered => "unregistered",
+            DomainStatus::RegisteredNoRecords => "registered (no records)",
+            DomainStatus::Active => "active",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// WHOIS-style information about a domain
+#[derive(Debug, Clone)]
+pub struct DomainInfo {
+    pub domain: String,
+    pub status: DomainStatus,
+    /// Register address (hex) the domain is read from
+    pub register_address: String,
+    /// Owner's Ed25519 public key (hex), if registered
+    pub owner_public_key: Option<String>,
+    /// Chunk address (hex) of the owner document, if registered
+    pub owner_chunk_address: Option<String>,
+    /// Current records, empty unless the domain is active
+    pub records: Vec<DnsRecord>,
+    /// Entry counts for the register history, if registered
+    pub stats: Option<HistoryStats>,
+}
+
+/// Inspect a domain's registration status, ownership and history
+///
+/// Unlike [`crate::lookup_domain`], an unregistered domain is not an error.
+pub async fn inspect_domain<N: NameNetwork>(client: &N, domain: &str) -> Result<DomainInfo> {
+    inspect_domain_with(client, domain, &LookupOptions::default()).await
+}
+
+/// Inspect a domain with explicit lookup options
+pub async fn inspect_domain_with<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    options: &LookupOptions,
+) -> Result<DomainInfo> {
+    let resolved = Resolver::with_options(client, options.clone())
+        .walk(domain, true)
+        .await?;
+    let domain = registered_domain(domain)?;
+
+    let info = match resolved {
+        None => DomainInfo {
+            status: DomainStatus::Unregistered,
+            register_address: get_register_address_for_domain(&domain)
+                .context("Failed to derive register address")?
+                .to_string(),
+            domain,
+            owner_public_key: None,
+            owner_chunk_address: None,
+            records: Vec::new(),
+            stats: None,
+        },
+        Some(resolved) => DomainInfo {
+            domain,
+            status: DomainStatus::from_current(resolved.document()),
+            register_address: resolved.register().to_string(),
+            owner_public_key: Some(resolved.owner_public_key()),
+            owner_chunk_address: resolved.owner_chunk_address().map(hex::encode),
+            records: resolved.records().to_vec(),
+            stats: resolved.history().map(calculate_history_stats),
+        },
+    };
+
+    Ok(info)
+}
+
+/// Register of a domain registered before names were lowercased
+///
+/// Names used to be passed to the register derivation as typed, so a domain
+/// registered as `MyDomain.ant` lives in a different register than
+/// `mydomain.ant`. Lookups of `MyDomain.ant` still fall back to it while the
+/// lowercase register is empty, but other spellings do not, and whoever
+/// registers `mydomain.ant` takes the name over. Returns that register's
+/// address if `domain` is not in canonical form and the as-typed register
+/// has entries.
+pub async fn find_case_sensitive_registration<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<Option<RegisterAddress>> {
+    let canonical = registered_domain(domain)?;
+    let typed = domain.strip_suffix('.').unwrap_or(domain);
+    if typed == canonical {
+        return Ok(None);
+    }
+
+    let address =
+        get_register_address_for_domain(typed).context("Failed to derive register address")?;
+    match client.register_history(&address).await {
+        Ok(history) if !history.is_empty() => Ok(Some(address)),
+        Ok(_) | Err(AntnsError::RegisterNotFound(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(records: Vec<DnsRecord>) -> DomainRecordsDocument {
+        DomainRecordsDocument {
+            version: Some(1),
+            records,
+            sequence: Some(1),
+            timestamp: Some(0),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_status_from_current() {
+        assert_eq!(
+            DomainStatus::from_current(None),
+            DomainStatus::RegisteredNoRecords
+        );
+        assert_eq!(
+            DomainStatus::from_current(Some(&document(vec![]))),
+            DomainStatus::RegisteredNoRecords
+        );
+
+        let active = document(vec![DnsRecord {
+            record_type: "ant".to_string(),
+            name: ".".to_string(),
+            value: "abc123".to_string(),
+            ttl: None,
+        }]);
+        assert_eq!(
+            DomainStatus::from_current(Some(&active)),
+            DomainStatus::Active
+        );
+
+        assert!(DomainStatus::Unregistered.is_available());
+        assert!(!DomainStatus::Active.is_available());
+    }
+}
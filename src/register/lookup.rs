@@ -3,238 +3,399 @@
 
 //! Domain lookup and resolution operations
 
-use crate::crypto::verify_records;
+use crate::constants::{DEFAULT_LOOKUP_CONCURRENCY, MAX_DELEGATION_DEPTH};
+use crate::crypto::VerifyMode;
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
 use crate::register::get_register_address_for_domain;
-use crate::register::{DomainOwnerDocument, DomainRecordsDocument, DomainResolution};
-use anyhow::{Context, Result};
-use autonomi::data::DataAddress;
-use autonomi::Client;
+use crate::register::name::{registered_domain, DomainName};
+use crate::register::resolver::Resolver;
+use crate::register::{DomainOwner, DomainOwnerDocument, DomainRecordsDocument, DomainResolution};
+use crate::storage::checkpoint::CheckpointCache;
+use anyhow::Context;
+use autonomi::register::{RegisterAddress, RegisterValue};
+use bytes::Bytes;
 use ed25519_dalek::VerifyingKey;
-use xor_name::XorName;
+use futures::stream::{self, Stream, StreamExt};
+use std::future::Future;
+use std::time::Duration;
 
-/// Look up a domain and return its current target address
-///
-/// # Arguments
-/// * `client` - Autonomi client instance
-/// * `domain` - Domain name to look up
+/// Tracks the current records document while walking a register history
 ///
-/// # Returns
-/// Domain resolution with target address and owner public key
-pub async fn lookup_domain(client: &Client, domain: &str) -> Result<DomainResolution> {
-    // Step 1: Get register address (deterministic from domain name)
-    let register_addr =
-        get_register_address_for_domain(domain).context("Failed to derive register address")?;
-
-    tracing::debug!(
-        "Looking up domain '{}' at register: {}",
-        domain,
-        register_addr
-    );
-
-    // Step 2: Fetch register history (all chunk addresses)
-    let mut history = client.register_history(&register_addr);
-
-    // Step 3: Download first entry (owner document)
-    let owner_chunk_addr = history
-        .next()
-        .await
-        .context("Failed to get first history entry")?
-        .ok_or_else(|| anyhow::anyhow!("Register not found for domain: {}", domain))?;
+/// The register is writable by anyone holding the shared key, so an older,
+/// validly signed document can be re-appended at any time. A document only
+/// replaces the current one if its sequence number is strictly higher.
+/// Unsequenced legacy documents keep the original "last valid entry wins"
+/// behaviour, but only until the first sequenced document is seen.
+#[derive(Debug, Default)]
+pub struct LatestRecords {
+    current: Option<DomainRecordsDocument>,
+}
 
-    let owner_data_addr = DataAddress::new(XorName(owner_chunk_addr));
-    let owner_data = client
-        .data_get_public(&owner_data_addr)
-        .await
-        .context("Failed to download owner document")?;
-
-    let owner_doc: DomainOwnerDocument =
-        serde_json::from_slice(&owner_data).context("Failed to parse owner document")?;
-
-    tracing::debug!("Owner public key: {}", owner_doc.public_key);
-
-    // Parse owner's Ed25519 public key
-    let owner_pubkey_bytes =
-        hex::decode(&owner_doc.public_key).context("Invalid hex in owner public key")?;
-    let owner_pubkey = VerifyingKey::from_bytes(
-        owner_pubkey_bytes
-            .as_slice()
-            .try_into()
-            .context("Invalid owner public key length")?,
-    )
-    .context("Invalid Ed25519 public key")?;
-
-    // Step 4: Process remaining entries (records), verify signatures
-    let mut last_valid_target: Option<String> = None;
-    let mut valid_count = 0;
-    let mut invalid_count = 0;
-
-    while let Some(chunk_addr) = history.next().await? {
-        let data_addr = DataAddress::new(XorName(chunk_addr));
-
-        // Download data
-        let data_bytes = match client.data_get_public(&data_addr).await {
-            Ok(d) => d,
-            Err(e) => {
-                tracing::warn!("Failed to download data {}: {}", hex::encode(chunk_addr), e);
-                invalid_count += 1;
-                continue; // Skip corrupted entries
-            }
-        };
+impl LatestRecords {
+    /// Continue tracking from a previously selected current document
+    pub fn resume(current: Option<DomainRecordsDocument>) -> Self {
+        Self { current }
+    }
 
-        // Parse records document
-        let records_doc: DomainRecordsDocument = match serde_json::from_slice(&data_bytes) {
-            Ok(d) => d,
-            Err(e) => {
-                tracing::warn!("Failed to parse data as records document: {}", e);
-                invalid_count += 1;
-                continue; // Skip invalid JSON
-            }
+    /// Offer a document whose signature has already been verified
+    ///
+    /// Returns true if the document became the current one.
+    pub fn offer(&mut self, doc: DomainRecordsDocument) -> bool {
+        let accept = match (&self.current, doc.sequence) {
+            (None, _) => true,
+            // Unsequenced documents never override a sequenced one
+            (Some(current), None) => current.sequence.is_none(),
+            (Some(current), Some(sequence)) => match current.sequence {
+                Some(current_sequence) => sequence > current_sequence,
+                None => true,
+            },
         };
 
-        // Verify signature
-        if verify_records(&records_doc.records, &records_doc.signature, &owner_pubkey) {
-            // Valid signature - extract target
-            if let Some(record) = records_doc
-                .records
-                .iter()
-                .find(|r| r.record_type.eq_ignore_ascii_case("ant") && r.name == ".")
-            {
-                last_valid_target = Some(record.value.clone());
-                valid_count += 1;
-                tracing::debug!("Valid record found: {}", record.value);
-            }
-        } else {
-            // Invalid signature - spam entry, ignore
-            tracing::debug!(
-                "Invalid signature on chunk {}, ignoring",
-                hex::encode(chunk_addr)
-            );
-            invalid_count += 1;
+        if accept {
+            self.current = Some(doc);
         }
+
+        accept
     }
 
-    tracing::info!(
-        "Domain lookup complete: {} valid entries, {} invalid/spam entries",
-        valid_count,
-        invalid_count
-    );
+    /// The current document, if any valid document was offered
+    pub fn current(&self) -> Option<&DomainRecordsDocument> {
+        self.current.as_ref()
+    }
 
-    // Step 5: Return last valid target
-    let target = last_valid_target
-        .ok_or_else(|| anyhow::anyhow!("No valid DNS records found for domain: {}", domain))?;
+    /// Consume the tracker and return the current document
+    pub fn into_current(self) -> Option<DomainRecordsDocument> {
+        self.current
+    }
+}
 
-    Ok(DomainResolution {
-        domain: domain.to_string(),
+/// Options controlling how a lookup walks a register history
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    /// Maximum number of entries downloaded at the same time
+    pub concurrency: usize,
+    /// Resume from and save verified checkpoints; `None` walks the full history
+    pub checkpoints: Option<CheckpointCache>,
+    /// Maximum number of nested delegations followed for a name
+    pub max_delegation_depth: usize,
+    /// Which records signature formats are accepted
+    ///
+    /// Lookups that accept legacy signatures neither read nor write checkpoints,
+    /// so documents they select are never trusted by strict lookups.
+    pub verify_mode: VerifyMode,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_LOOKUP_CONCURRENCY,
+            checkpoints: None,
+            max_delegation_depth: MAX_DELEGATION_DEPTH,
+            verify_mode: VerifyMode::Strict,
+        }
+    }
+}
+
+/// Entry counts and timings for a single lookup
+#[derive(Debug, Clone, Default)]
+pub struct LookupStats {
+    /// Register entries processed by this lookup, not counting owner documents
+    pub entries: usize,
+    /// Entries skipped because a checkpoint already covered them
+    pub cached_entries: usize,
+    pub valid_entries: usize,
+    /// Entries that failed to download, parse or verify
+    pub invalid_entries: usize,
+    pub concurrency: usize,
+    /// Time spent fetching the register history and owner document
+    pub history_time: Duration,
+    /// Wall time for downloading and verifying all entries
+    pub entries_time: Duration,
+    /// Time spent verifying signatures (included in `entries_time`)
+    pub verify_time: Duration,
+    pub total_time: Duration,
+}
+
+impl std::fmt::Display for LookupStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} entries ({} valid, {} invalid, {} from checkpoint) in {:.2?} [history {:.2?}, entries {:.2?}, verify {:.2?}, concurrency {}]",
+            self.entries,
+            self.valid_entries,
+            self.invalid_entries,
+            self.cached_entries,
+            self.total_time,
+            self.history_time,
+            self.entries_time,
+            self.verify_time,
+            self.concurrency
+        )
+    }
+}
+
+impl LookupStats {
+    /// Add the counts and timings of another walk, e.g. of a delegated zone
+    pub(crate) fn add(&mut self, other: &LookupStats) {
+        self.entries += other.entries;
+        self.cached_entries += other.cached_entries;
+        self.valid_entries += other.valid_entries;
+        self.invalid_entries += other.invalid_entries;
+        self.history_time += other.history_time;
+        self.entries_time += other.entries_time;
+        self.verify_time += other.verify_time;
+        self.total_time += other.total_time;
+    }
+}
+
+/// Download register entries with bounded concurrency, yielding them in register order
+///
+/// Downloads run ahead of the consumer, so verification of earlier entries
+/// overlaps with fetching later ones.
+pub(crate) fn download_in_order<'a, F, Fut>(
+    addresses: Vec<[u8; 32]>,
+    concurrency: usize,
+    fetch: F,
+) -> impl Stream<Item = ([u8; 32], Result<Bytes>)> + 'a
+where
+    F: Fn([u8; 32]) -> Fut + 'a,
+    Fut: Future<Output = Result<Bytes>> + 'a,
+{
+    stream::iter(addresses)
+        .map(move |addr| {
+            let download = fetch(addr);
+            async move { (addr, download.await) }
+        })
+        .buffered(concurrency.max(1))
+}
+
+/// Look up a domain and return its current target address
+///
+/// # Arguments
+/// * `client` - Network backend (e.g. an Autonomi client)
+/// * `domain` - Domain name to look up
+///
+/// # Returns
+/// Domain resolution with target address and owner public key
+pub async fn lookup_domain<N: NameNetwork>(client: &N, domain: &str) -> Result<DomainResolution> {
+    let (resolution, _) = lookup_domain_with(client, domain, &LookupOptions::default()).await?;
+    Ok(resolution)
+}
+
+/// Look up a domain's current target address with explicit options
+///
+/// The target is the `ant` record of the current records document named
+/// after the subdomain (`.` for the domain itself), so `www.mydomain.ant`
+/// selects the record `www` of `mydomain.ant`. Delegated labels are
+/// followed to the child zone first. A name without a matching record in
+/// the current document of its zone does not resolve.
+/// Also returns entry counts and timings for the lookup.
+pub async fn lookup_domain_with<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    options: &LookupOptions,
+) -> Result<(DomainResolution, LookupStats)> {
+    let name = DomainName::parse(domain)?;
+    let resolved = Resolver::with_options(client, options.clone())
+        .resolve_name(&name)
+        .await?;
+
+    let target = resolved
+        .target()
+        .ok_or_else(|| AntnsError::NoValidRecords(name.to_string()))?
+        .to_string();
+
+    tracing::debug!("Valid record found: {}", target);
+
+    let resolution = DomainResolution {
+        domain: name.to_string(),
         target,
-        owner_public_key: owner_doc.public_key,
-    })
+        owner_public_key: resolved.zone.owner_public_key(),
+    };
+
+    Ok((resolution, resolved.stats))
 }
 
 /// Look up all current records for a domain
 ///
 /// Returns the latest valid records (verified signature)
-pub async fn lookup_domain_records(
-    client: &Client,
+pub async fn lookup_domain_records<N: NameNetwork>(
+    client: &N,
     domain: &str,
 ) -> Result<Vec<crate::register::DnsRecord>> {
-    use crate::register::DnsRecord;
+    let document = lookup_domain_document(client, domain).await?;
 
-    // Step 1: Get register address
-    let register_addr =
-        get_register_address_for_domain(domain).context("Failed to derive register address")?;
+    // Return current records or empty if none found
+    Ok(document.map(|doc| doc.records).unwrap_or_default())
+}
 
-    tracing::debug!(
-        "Looking up records for domain '{}' at register: {}",
-        domain,
-        register_addr
-    );
+/// Look up the current signed records document for a domain
+///
+/// Returns the valid document with the highest sequence number, or `None`
+/// if the domain is registered but has no valid records yet.
+pub async fn lookup_domain_document<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<Option<DomainRecordsDocument>> {
+    let (document, _) =
+        lookup_domain_document_with(client, domain, &LookupOptions::default()).await?;
+    Ok(document)
+}
 
-    // Step 2: Fetch register history
-    let mut history = client.register_history(&register_addr);
+/// Look up the current signed records document with explicit options
+///
+/// For a name at or below a delegated label this is the document of the
+/// delegated zone.
+pub async fn lookup_domain_document_with<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    options: &LookupOptions,
+) -> Result<(Option<DomainRecordsDocument>, LookupStats)> {
+    // Subdomains share the records document of the zone holding them
+    let name = DomainName::parse(domain)?;
+    let resolved = Resolver::with_options(client, options.clone())
+        .resolve_name(&name)
+        .await?;
+    Ok((resolved.zone.into_document(), resolved.stats))
+}
 
-    // Step 3: Download first entry (owner document)
-    let owner_chunk_addr = history
-        .next()
-        .await
-        .context("Failed to get first history entry")?
-        .ok_or_else(|| anyhow::anyhow!("Register not found for domain: {}", domain))?;
+/// Register of a registered domain together with its entries
+pub(crate) struct DomainRegister {
+    /// Name the register is derived from: the canonical name, or the name
+    /// as typed for a domain registered before names were lowercased
+    pub name: String,
+    pub address: RegisterAddress,
+    pub history: Vec<RegisterValue>,
+}
 
-    let owner_data_addr = DataAddress::new(XorName(owner_chunk_addr));
+/// Find the register of a registered domain and read its entries
+///
+/// Reads the register of the canonical (lowercase) name. If that one is
+/// empty and `domain` was typed with uppercase letters, the register
+/// derived from the name as typed is used instead when it has entries.
+pub(crate) async fn domain_register<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<DomainRegister> {
+    let canonical = registered_domain(domain)?;
+    let typed = domain.strip_suffix('.').unwrap_or(domain);
+    if typed == canonical {
+        return read_register(client, canonical).await;
+    }
+
+    let missing = match read_register(client, canonical).await {
+        Ok(register) if !register.history.is_empty() => return Ok(register),
+        Ok(register) => Ok(register),
+        Err(e @ AntnsError::RegisterNotFound(_)) => Err(e),
+        Err(e) => return Err(e),
+    };
+
+    match read_register(client, typed.to_string()).await {
+        Ok(legacy) if !legacy.history.is_empty() => {
+            tracing::debug!("'{}' is registered as typed, not in lowercase", typed);
+            Ok(legacy)
+        }
+        Ok(_) | Err(AntnsError::RegisterNotFound(_)) => missing,
+        Err(e) => Err(e),
+    }
+}
+
+async fn read_register<N: NameNetwork>(client: &N, name: String) -> Result<DomainRegister> {
+    let address =
+        get_register_address_for_domain(&name).context("Failed to derive register address")?;
+    let history = client.register_history(&address).await?;
+    Ok(DomainRegister {
+        name,
+        address,
+        history,
+    })
+}
+
+/// Look up the owner document (first register entry) of a domain
+///
+/// Returns `None` if the domain's register has no entries, i.e. the domain
+/// has not been registered yet.
+pub async fn lookup_domain_owner<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<Option<DomainOwner>> {
+    let history = domain_register(client, domain).await?.history;
+    let domain = registered_domain(domain)?;
+    let domain = domain.as_str();
+
+    let owner_chunk_addr = match history.first() {
+        Some(addr) => *addr,
+        None => return Ok(None),
+    };
+
+    let document = fetch_owner_document(client, domain, owner_chunk_addr).await?;
+
+    Ok(Some(DomainOwner {
+        chunk_address: owner_chunk_addr,
+        document,
+    }))
+}
+
+/// Download and parse the owner document a register's first entry points to
+pub(crate) async fn fetch_owner_document<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    owner_chunk_addr: [u8; 32],
+) -> Result<DomainOwnerDocument> {
     let owner_data = client
-        .data_get_public(&owner_data_addr)
+        .data_get_public(owner_chunk_addr)
         .await
-        .context("Failed to download owner document")?;
-
-    let owner_doc: DomainOwnerDocument =
-        serde_json::from_slice(&owner_data).context("Failed to parse owner document")?;
-
-    // Parse owner's Ed25519 public key
-    let owner_pubkey_bytes =
-        hex::decode(&owner_doc.public_key).context("Invalid hex in owner public key")?;
-    let owner_pubkey = VerifyingKey::from_bytes(
-        owner_pubkey_bytes
-            .as_slice()
-            .try_into()
-            .context("Invalid owner public key length")?,
-    )
-    .context("Invalid Ed25519 public key")?;
-
-    // Step 4: Process remaining entries, find latest valid records
-    let mut last_valid_records: Option<Vec<DnsRecord>> = None;
-
-    while let Some(chunk_addr) = history.next().await? {
-        let data_addr = DataAddress::new(XorName(chunk_addr));
-
-        // Download data
-        let data_bytes = match client.data_get_public(&data_addr).await {
-            Ok(d) => d,
-            Err(e) => {
-                tracing::warn!("Failed to download data {}: {}", hex::encode(chunk_addr), e);
-                continue;
-            }
-        };
+        .map_err(|e| AntnsError::OwnerDocumentMissing {
+            domain: domain.to_string(),
+            reason: e.to_string(),
+        })?;
 
-        // Parse records document
-        let records_doc: DomainRecordsDocument = match serde_json::from_slice(&data_bytes) {
-            Ok(d) => d,
-            Err(e) => {
-                tracing::warn!("Failed to parse data as records document: {}", e);
-                continue;
-            }
-        };
+    serde_json::from_slice(&owner_data).map_err(|e| AntnsError::OwnerDocumentCorrupt {
+        domain: domain.to_string(),
+        reason: e.to_string(),
+    })
+}
 
-        // Verify signature
-        if verify_records(&records_doc.records, &records_doc.signature, &owner_pubkey) {
-            last_valid_records = Some(records_doc.records);
-        } else {
-            tracing::debug!(
-                "Invalid signature on chunk {}, ignoring",
-                hex::encode(chunk_addr)
-            );
-        }
-    }
+/// Decode the owner's Ed25519 public key from an owner document
+pub(crate) fn parse_owner_key(
+    domain: &str,
+    owner_doc: &DomainOwnerDocument,
+) -> Result<VerifyingKey> {
+    let invalid = |reason: String| AntnsError::InvalidOwnerKey {
+        domain: domain.to_string(),
+        reason,
+    };
+
+    let bytes = hex::decode(&owner_doc.public_key).map_err(|e| invalid(e.to_string()))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| invalid("expected 32 bytes".to_string()))?;
 
-    // Return last valid records or empty if none found
-    Ok(last_valid_records.unwrap_or_default())
+    VerifyingKey::from_bytes(&bytes).map_err(|e| invalid(e.to_string()))
 }
 
 /// Quick lookup that only fetches the current register value
 /// (less thorough but faster - doesn't verify full history)
-pub async fn quick_lookup(client: &Client, domain: &str) -> Result<String> {
-    let register_addr = get_register_address_for_domain(domain)?;
+pub async fn quick_lookup<N: NameNetwork>(client: &N, domain: &str) -> Result<String> {
+    let name = DomainName::parse(domain)?;
+    let register_addr = get_register_address_for_domain(name.base())
+        .context("Failed to derive register address")?;
 
     // Get current value (latest chunk address)
-    let current_value = client
-        .register_get(&register_addr)
-        .await
-        .context("Failed to get current register value")?;
+    let mut current_value = client.register_get(&register_addr).await;
+    if matches!(current_value, Err(AntnsError::RegisterNotFound(_)))
+        && name.typed_base() != name.base()
+    {
+        // Registered before names were lowercased
+        let legacy_addr = get_register_address_for_domain(name.typed_base())
+            .context("Failed to derive register address")?;
+        current_value = client.register_get(&legacy_addr).await;
+    }
+    let current_value = current_value?;
 
     // Download the data
-    let data_addr = DataAddress::new(XorName(current_value));
-    let data_bytes = client
-        .data_get_public(&data_addr)
-        .await
-        .context("Failed to download current records data")?;
+    let data_bytes = client.data_get_public(current_value).await?;
 
     // Parse records
     let records_doc: DomainRecordsDocument =
@@ -242,11 +403,124 @@ pub async fn quick_lookup(client: &Client, domain: &str) -> Result<String> {
 
     // Extract target (note: this doesn't verify signature!)
     let target = records_doc
-        .records
-        .iter()
-        .find(|r| r.record_type.eq_ignore_ascii_case("ant") && r.name == ".")
-        .map(|r| r.value.clone())
-        .ok_or_else(|| anyhow::anyhow!("No target record found"))?;
+        .target_for(&name.record_name())
+        .ok_or_else(|| AntnsError::NoValidRecords(name.to_string()))?
+        .to_string();
 
     Ok(target)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register::DnsRecord;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn signed(value: &str, sequence: u64, key: &SigningKey) -> DomainRecordsDocument {
+        let records = vec![DnsRecord {
+            record_type: "ant".to_string(),
+            name: ".".to_string(),
+            value: value.to_string(),
+            ttl: None,
+        }];
+        DomainRecordsDocument::sign("test.ant", records, sequence, key).unwrap()
+    }
+
+    fn legacy(value: &str) -> DomainRecordsDocument {
+        DomainRecordsDocument {
+            version: None,
+            records: vec![DnsRecord {
+                record_type: "ant".to_string(),
+                name: ".".to_string(),
+                value: value.to_string(),
+                ttl: None,
+            }],
+            sequence: None,
+            timestamp: None,
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_replayed_document_is_ignored() {
+        let key = SigningKey::generate(&mut OsRng);
+        let mut latest = LatestRecords::default();
+
+        assert!(latest.offer(signed("first", 1, &key)));
+        assert!(latest.offer(signed("second", 2, &key)));
+
+        // Re-appending the first document must not roll the domain back
+        assert!(!latest.offer(signed("first", 1, &key)));
+        assert!(!latest.offer(signed("other", 2, &key)));
+        assert_eq!(latest.current().unwrap().root_target().unwrap(), "second");
+    }
+
+    #[test]
+    fn test_legacy_documents_migrate() {
+        let key = SigningKey::generate(&mut OsRng);
+        let mut latest = LatestRecords::default();
+
+        // Legacy documents keep "last valid entry wins" among themselves
+        assert!(latest.offer(legacy("old")));
+        assert!(latest.offer(legacy("newer")));
+
+        // The first sequenced document takes over...
+        assert!(latest.offer(signed("migrated", 1, &key)));
+
+        // ...and legacy documents can no longer override it
+        assert!(!latest.offer(legacy("newer")));
+        assert_eq!(latest.current().unwrap().root_target().unwrap(), "migrated");
+    }
+
+    #[test]
+    fn test_signed_document_verifies() {
+        let key = SigningKey::generate(&mut OsRng);
+        let doc = signed("target", 3, &key);
+
+        assert!(doc.verify_with("test.ant", &key.verifying_key(), VerifyMode::Strict));
+        assert!(!doc.verify_with("other.ant", &key.verifying_key(), VerifyMode::AllowLegacy));
+        assert_eq!(doc.next_sequence(), 4);
+
+        let mut replayed = doc.clone();
+        replayed.sequence = Some(4);
+        assert!(!replayed.verify_with("test.ant", &key.verifying_key(), VerifyMode::Strict));
+
+        // Stripping the version must not downgrade it to a legacy document
+        let mut downgraded = doc.clone();
+        downgraded.version = None;
+        assert!(!downgraded.verify_with("test.ant", &key.verifying_key(), VerifyMode::AllowLegacy));
+    }
+
+    #[test]
+    fn test_legacy_requires_compat_mode() {
+        use ed25519_dalek::Signer;
+
+        let key = SigningKey::generate(&mut OsRng);
+        let mut doc = legacy("old");
+        let json = serde_json::to_string(&doc.records).unwrap();
+        doc.signature = hex::encode(key.sign(json.as_bytes()).to_bytes());
+
+        assert!(!doc.verify_with("test.ant", &key.verifying_key(), VerifyMode::Strict));
+        assert!(doc.verify_with("test.ant", &key.verifying_key(), VerifyMode::AllowLegacy));
+    }
+
+    #[tokio::test]
+    async fn test_downloads_keep_register_order() {
+        let addresses: Vec<[u8; 32]> = (0..10u8).map(|i| [i; 32]).collect();
+
+        // Later entries finish first; the stream must still yield them in order
+        let downloads = download_in_order(addresses.clone(), 4, |addr| async move {
+            let delay = 10 - addr[0] as u64;
+            tokio::time::sleep(Duration::from_millis(delay * 5)).await;
+            Ok(Bytes::from(vec![addr[0]]))
+        });
+        let results: Vec<_> = downloads.collect().await;
+
+        let order: Vec<[u8; 32]> = results.iter().map(|(addr, _)| *addr).collect();
+        assert_eq!(order, addresses);
+        for (addr, data) in results {
+            assert_eq!(data.unwrap()[0], addr[0]);
+        }
+    }
+}
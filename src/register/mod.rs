@@ -4,11 +4,18 @@
 //! Register operations for domain management
 
 pub mod create;
+pub mod delegation;
 pub mod history;
+pub mod inspect;
 pub mod lookup;
+pub mod name;
+pub mod record;
+pub mod resolver;
 pub mod update;
 
 use crate::constants::DNS_REGISTER_KEY_HEX;
+use crate::crypto::VerifyMode;
+use crate::register::record::{RecordData, RecordType};
 use autonomi::register::RegisterAddress;
 use autonomi::{Client, SecretKey};
 
@@ -26,6 +33,21 @@ pub fn get_register_address_for_domain(domain: &str) -> Result<RegisterAddress,
     Ok(RegisterAddress::new(register_key.public_key()))
 }
 
+/// Get the register signing key for a domain
+///
+/// Derived from the shared DNS_REGISTER_KEY, so anyone can append to any
+/// domain's register; ownership is enforced by record signatures instead.
+pub(crate) fn domain_register_key(domain: &str) -> anyhow::Result<SecretKey> {
+    let shared_key_bytes = hex::decode(DNS_REGISTER_KEY_HEX)?;
+    let shared_key_array: [u8; 32] = shared_key_bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid DNS register key length"))?;
+    let shared_key = SecretKey::from_bytes(shared_key_array)
+        .map_err(|e| anyhow::anyhow!("Invalid DNS register key: {:?}", e))?;
+
+    Ok(Client::register_key_from_name(&shared_key, domain))
+}
+
 /// Data structures for DNS records
 use serde::{Deserialize, Serialize};
 
@@ -35,18 +57,166 @@ pub struct DomainOwnerDocument {
     pub public_key: String,
 }
 
+/// Owner document of a registered domain together with its chunk address
+#[derive(Debug, Clone)]
+pub struct DomainOwner {
+    pub chunk_address: [u8; 32],
+    pub document: DomainOwnerDocument,
+}
+
+impl DomainOwner {
+    /// Whether this owner document names the given Ed25519 public key (hex)
+    pub fn is_key(&self, public_key_hex: &str) -> bool {
+        self.document
+            .public_key
+            .eq_ignore_ascii_case(public_key_hex)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainRecordsDocument {
+    /// Signed payload version; absent on legacy documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
     pub records: Vec<DnsRecord>,
+    /// Strictly increasing per domain; absent on the oldest legacy documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// Unix timestamp (seconds) at signing time; absent on the oldest legacy documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
     pub signature: String,
 }
 
+impl DomainRecordsDocument {
+    /// Create a signed records document for a domain with the given sequence number
+    pub fn sign(
+        domain: &str,
+        records: Vec<DnsRecord>,
+        sequence: u64,
+        signing_key: &ed25519_dalek::SigningKey,
+    ) -> anyhow::Result<Self> {
+        let timestamp = chrono::Utc::now().timestamp().max(0) as u64;
+        let signature =
+            crate::crypto::sign_records(domain, &records, sequence, timestamp, signing_key)?;
+
+        Ok(Self {
+            version: Some(crate::constants::RECORDS_PROTOCOL_VERSION),
+            records,
+            sequence: Some(sequence),
+            timestamp: Some(timestamp),
+            signature,
+        })
+    }
+
+    /// Verify the document signature for a domain against the owner's public key
+    ///
+    /// Only versioned, domain-bound signatures are accepted; see
+    /// [`DomainRecordsDocument::verify_with`] for legacy documents.
+    pub fn verify(&self, domain: &str, verifying_key: &ed25519_dalek::VerifyingKey) -> bool {
+        self.verify_with(domain, verifying_key, VerifyMode::Strict)
+    }
+
+    /// Verify the document signature, accepting the formats `mode` allows
+    pub fn verify_with(
+        &self,
+        domain: &str,
+        verifying_key: &ed25519_dalek::VerifyingKey,
+        mode: VerifyMode,
+    ) -> bool {
+        match (self.version, self.sequence, self.timestamp) {
+            (Some(crate::constants::RECORDS_PROTOCOL_VERSION), Some(sequence), Some(timestamp)) => {
+                crate::crypto::verify_records(
+                    domain,
+                    &self.records,
+                    sequence,
+                    timestamp,
+                    &self.signature,
+                    verifying_key,
+                )
+            }
+            (None, None, None) if mode == VerifyMode::AllowLegacy => {
+                crate::crypto::ed25519::verify_legacy_records(
+                    &self.records,
+                    &self.signature,
+                    verifying_key,
+                )
+            }
+            // Unknown versions, legacy documents in strict mode or malformed documents
+            _ => false,
+        }
+    }
+
+    /// Whether this document predates versioned, domain-bound signatures
+    pub fn is_legacy(&self) -> bool {
+        self.version.is_none()
+    }
+
+    /// Sequence number to use for the next document after this one
+    pub fn next_sequence(&self) -> u64 {
+        self.sequence.unwrap_or(0) + 1
+    }
+
+    /// Target of the root `ant` record, if the document has one
+    pub fn root_target(&self) -> Option<&str> {
+        self.target_for(crate::constants::ROOT_RECORD_NAME)
+    }
+
+    /// Target of the `ant` record with the given name (`.` for the root)
+    pub fn target_for(&self, record_name: &str) -> Option<&str> {
+        self.records
+            .iter()
+            .find(|r| r.is_type(RecordType::Ant) && r.name == record_name)
+            .map(|r| r.value.as_str())
+    }
+}
+
+/// A record as stored and signed
+///
+/// Type and value are kept as the strings that were signed; use
+/// [`DnsRecord::data`] for the validated, typed value.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsRecord {
     #[serde(rename = "type")]
     pub record_type: String,
     pub name: String,
     pub value: String,
+    /// Time to live in seconds; absent on records without an explicit TTL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+impl DnsRecord {
+    /// Record with the given name (`.` for the root) and typed data
+    pub fn new(name: impl Into<String>, data: RecordData) -> Self {
+        Self {
+            record_type: data.record_type().to_string(),
+            name: name.into(),
+            value: data.value(),
+            ttl: None,
+        }
+    }
+
+    /// Set the record's time to live in seconds
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Parsed record type, if it is a known one
+    pub fn kind(&self) -> Option<RecordType> {
+        RecordType::parse(&self.record_type)
+    }
+
+    /// Whether the record has the given type (type names are case-insensitive)
+    pub fn is_type(&self, record_type: RecordType) -> bool {
+        self.kind() == Some(record_type)
+    }
+
+    /// Validated, typed value of the record
+    pub fn data(&self) -> crate::Result<RecordData> {
+        RecordData::parse(&self.record_type, &self.value)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +225,8 @@ pub struct DomainRegistration {
     pub register_address: RegisterAddress,
     pub owner_key: ed25519_dalek::SigningKey,
     pub total_cost: autonomi::AttoTokens,
+    /// Whether the first register entry was read back and names `owner_key`
+    pub ownership_verified: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +245,7 @@ pub enum HistoryEntry {
     Records {
         chunk_address: String,
         records: Option<Vec<DnsRecord>>,
+        sequence: Option<u64>,
         signature: Option<String>,
         is_valid: bool,
     },
@@ -0,0 +1,257 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Domain name parsing
+//!
+//! A name such as `api.v2.mydomain.ant` is split into the registered domain
+//! (`mydomain.ant`, always the last two labels) and a record name relative
+//! to it (`api.v2`). The registered domain selects the register; the record
+//! name selects the `ANT` record in its current document:
+//!
+//! - `mydomain.ant` uses the root record, named `.`
+//! - `www.mydomain.ant` uses the record named `www`
+//! - `api.v2.mydomain.ant` uses the record named `api.v2`; there is no
+//!   fallback to `v2` or to the root and no wildcard matching
+//!
+//! Names are case-insensitive and normalised to lowercase. A trailing dot
+//! (DNS wire form) is accepted. Hosts under the `.antns.net` gateway suffix
+//! map onto `.ant`.
+//!
+//! Registers used to be derived from names as typed, so domains registered
+//! with uppercase letters live in a different register than their lowercase
+//! name. The registered domain is therefore also kept as typed: when the
+//! lowercase register is empty, lookups and updates fall back to the
+//! as-typed one; see
+//! [`crate::register::inspect::find_case_sensitive_registration`].
+
+use crate::constants::{DOMAIN_SUFFIX, DOMAIN_SUFFIX_ALT, GATEWAY_SUFFIX, ROOT_RECORD_NAME};
+use crate::error::{AntnsError, Result};
+
+/// Maximum length of a full name, as in DNS
+const MAX_NAME_LENGTH: usize = 253;
+
+/// Maximum length of a single label, as in DNS
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// A parsed AntNS name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DomainName {
+    /// Registered domain, e.g. `mydomain.ant`
+    base: String,
+    /// Registered domain as typed, e.g. `MyDomain.ant`
+    typed_base: String,
+    /// Labels in front of the registered domain, e.g. `["api", "v2"]`
+    sub_labels: Vec<String>,
+}
+
+impl DomainName {
+    /// Parse a `.ant` or `.autonomi` name
+    pub fn parse(name: &str) -> Result<Self> {
+        let typed = name.strip_suffix('.').unwrap_or(name);
+        let name = typed.to_ascii_lowercase();
+        let invalid = |reason: &str| AntnsError::InvalidDomainName {
+            name: name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if !name.ends_with(DOMAIN_SUFFIX) && !name.ends_with(DOMAIN_SUFFIX_ALT) {
+            return Err(invalid("must end in .ant or .autonomi"));
+        }
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(invalid("name is too long"));
+        }
+
+        let labels: Vec<&str> = name.split('.').collect();
+        if labels.len() < 2 {
+            return Err(invalid("missing domain label"));
+        }
+
+        let (sub, base) = labels.split_at(labels.len() - 2);
+        validate_label(base[0], false).map_err(invalid)?;
+        for label in sub {
+            // Underscore labels (e.g. `_dmarc`) are allowed below the domain
+            validate_label(label, true).map_err(invalid)?;
+        }
+
+        let base = base.join(".");
+        Ok(Self {
+            // Lowercasing ASCII keeps byte offsets
+            typed_base: typed[typed.len() - base.len()..].to_string(),
+            base,
+            sub_labels: sub.iter().map(|label| label.to_string()).collect(),
+        })
+    }
+
+    /// Parse an HTTP `Host` header value
+    ///
+    /// Strips the port and maps gateway hosts (`*.antns.net`) onto `.ant`.
+    pub fn from_host(host: &str) -> Result<Self> {
+        let host = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+            .map_or(host, |(host, _)| host);
+        let host = host.strip_suffix('.').unwrap_or(host);
+
+        match host.to_ascii_lowercase().strip_suffix(GATEWAY_SUFFIX) {
+            Some(name) => Self::parse(&format!("{}{}", &host[..name.len()], DOMAIN_SUFFIX)),
+            None => Self::parse(host),
+        }
+    }
+
+    /// Registered domain whose register holds the records, e.g. `mydomain.ant`
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Registered domain as typed, e.g. `MyDomain.ant`
+    ///
+    /// Only used to find registrations made before names were lowercased.
+    pub fn typed_base(&self) -> &str {
+        &self.typed_base
+    }
+
+    /// Name of the record selected within the registered domain (`.` for the root)
+    pub fn record_name(&self) -> String {
+        if self.sub_labels.is_empty() {
+            ROOT_RECORD_NAME.to_string()
+        } else {
+            self.sub_labels.join(".")
+        }
+    }
+
+    /// Whether this is the registered domain itself
+    pub fn is_root(&self) -> bool {
+        self.sub_labels.is_empty()
+    }
+}
+
+/// Whether a name is in the `.ant` or `.autonomi` namespace
+///
+/// Unlike [`DomainName::parse`] this does not validate the labels, so
+/// malformed AntNS names are still recognised as AntNS names.
+pub fn is_ant_name(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase();
+    [DOMAIN_SUFFIX, DOMAIN_SUFFIX_ALT]
+        .iter()
+        .any(|suffix| name.ends_with(suffix) || name == suffix[1..])
+}
+
+/// Canonical form of a name that must be a registered domain, not a subdomain
+///
+/// Subdomains are records of their parent domain and have no register.
+pub(crate) fn registered_domain(name: &str) -> Result<String> {
+    let parsed = DomainName::parse(name)?;
+    if !parsed.is_root() {
+        return Err(AntnsError::InvalidDomainName {
+            name: parsed.to_string(),
+            reason: format!(
+                "subdomains are records of '{}', not separate domains",
+                parsed.base()
+            ),
+        });
+    }
+
+    Ok(parsed.base)
+}
+
+impl std::fmt::Display for DomainName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for label in &self.sub_labels {
+            write!(f, "{}.", label)?;
+        }
+        write!(f, "{}", self.base)
+    }
+}
+
+impl std::str::FromStr for DomainName {
+    type Err = AntnsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+pub(crate) fn validate_label(
+    label: &str,
+    allow_underscore: bool,
+) -> std::result::Result<(), &'static str> {
+    if label.is_empty() {
+        return Err("empty label");
+    }
+    if label.len() > MAX_LABEL_LENGTH {
+        return Err("label is longer than 63 characters");
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err("labels cannot start or end with '-'");
+    }
+
+    let valid = label.chars().enumerate().all(|(i, c)| {
+        c.is_ascii_alphanumeric() || c == '-' || (allow_underscore && c == '_' && i == 0)
+    });
+    if !valid {
+        return Err("labels may only contain letters, digits and '-'");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names() {
+        let root = DomainName::parse("MyDomain.ant.").unwrap();
+        assert_eq!(root.base(), "mydomain.ant");
+        assert_eq!(root.typed_base(), "MyDomain.ant");
+        assert_eq!(root.record_name(), ".");
+        assert!(root.is_root());
+
+        let www = DomainName::parse("www.mydomain.ant").unwrap();
+        assert_eq!(www.base(), "mydomain.ant");
+        assert_eq!(www.record_name(), "www");
+
+        let nested = DomainName::parse("api.v2.mydomain.autonomi").unwrap();
+        assert_eq!(nested.base(), "mydomain.autonomi");
+        assert_eq!(nested.record_name(), "api.v2");
+        assert_eq!(nested.to_string(), "api.v2.mydomain.autonomi");
+
+        assert!(DomainName::parse("_dmarc.mydomain.ant").is_ok());
+        assert!(DomainName::parse("my_domain.ant").is_err());
+        assert!(DomainName::parse("ant").is_err());
+        assert!(DomainName::parse(".ant").is_err());
+        assert!(DomainName::parse("www..mydomain.ant").is_err());
+        assert!(DomainName::parse("-bad.ant").is_err());
+        assert!(DomainName::parse("mydomain.com").is_err());
+
+        assert!(is_ant_name("my_domain.ant."));
+        assert!(is_ant_name("AUTONOMI"));
+        assert!(!is_ant_name("giant"));
+        assert!(!is_ant_name("example.com."));
+
+        assert_eq!(registered_domain("MyDomain.ant").unwrap(), "mydomain.ant");
+        assert!(matches!(
+            registered_domain("www.mydomain.ant"),
+            Err(AntnsError::InvalidDomainName { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_hosts() {
+        let gateway = DomainName::from_host("www.mydomain.antns.net:8080").unwrap();
+        assert_eq!(gateway.base(), "mydomain.ant");
+        assert_eq!(gateway.record_name(), "www");
+        assert_eq!(
+            DomainName::from_host("MyDomain.antns.net")
+                .unwrap()
+                .typed_base(),
+            "MyDomain.ant"
+        );
+
+        let local = DomainName::from_host("mydomain.ant:80").unwrap();
+        assert_eq!(local.to_string(), "mydomain.ant");
+
+        assert!(DomainName::from_host("antns.net").is_err());
+        assert!(DomainName::from_host("example.com").is_err());
+    }
+}
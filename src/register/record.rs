@@ -0,0 +1,354 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Typed DNS record data
+//!
+//! Records are stored and signed as three strings (`type`, `name`, `value`)
+//! so that documents signed by any version keep verifying. [`RecordData`] is
+//! the typed view of a record's type and value: it validates values when
+//! records are created and gives resolvers parsed data to answer from.
+//!
+//! Record types are case-insensitive; `TEXT` is accepted as an alias of
+//! `TXT` because earlier versions of the CLI only offered `TEXT`.
+
+use crate::error::{AntnsError, Result};
+use crate::register::delegation::Delegation;
+use crate::register::name::validate_label;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Length of a hex-encoded Autonomi address (32 bytes)
+const ADDRESS_HEX_LENGTH: usize = 64;
+
+/// Supported record types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Txt,
+    Mx,
+    Srv,
+    Caa,
+    /// Autonomi content address the name points to
+    Ant,
+    /// Hands a label to another key, see [`crate::register::delegation`]
+    Delegate,
+}
+
+impl RecordType {
+    /// Parse a record type name (case-insensitive)
+    pub fn parse(record_type: &str) -> Option<Self> {
+        let record_type = match record_type.to_ascii_uppercase().as_str() {
+            "A" => Self::A,
+            "AAAA" => Self::Aaaa,
+            "CNAME" => Self::Cname,
+            "TXT" | "TEXT" => Self::Txt,
+            "MX" => Self::Mx,
+            "SRV" => Self::Srv,
+            "CAA" => Self::Caa,
+            "ANT" => Self::Ant,
+            "DELEGATE" => Self::Delegate,
+            _ => return None,
+        };
+        Some(record_type)
+    }
+
+    /// Canonical (uppercase) name of the type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Txt => "TXT",
+            Self::Mx => "MX",
+            Self::Srv => "SRV",
+            Self::Caa => "CAA",
+            Self::Ant => "ANT",
+            Self::Delegate => "DELEGATE",
+        }
+    }
+}
+
+impl std::fmt::Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Validated value of a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    /// Canonical name, stored without a trailing dot
+    Cname(String),
+    Txt(String),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Caa {
+        flags: u8,
+        tag: String,
+        value: String,
+    },
+    /// Hex-encoded Autonomi address
+    Ant(String),
+    Delegate(Delegation),
+}
+
+impl RecordData {
+    /// Parse and validate a record value for the given type
+    ///
+    /// Value formats follow zone-file presentation: `MX` is
+    /// `<preference> <exchange>`, `SRV` is
+    /// `<priority> <weight> <port> <target>` and `CAA` is
+    /// `<flags> <tag> <value>`.
+    pub fn parse(record_type: &str, value: &str) -> Result<Self> {
+        let invalid = |reason: String| AntnsError::InvalidRecord {
+            record_type: record_type.to_string(),
+            value: value.to_string(),
+            reason,
+        };
+
+        let kind = RecordType::parse(record_type).ok_or_else(|| {
+            invalid(
+                "unknown record type, expected A, AAAA, CNAME, TXT, MX, SRV, CAA, ANT or DELEGATE"
+                    .to_string(),
+            )
+        })?;
+
+        let data = match kind {
+            RecordType::A => Self::A(
+                value
+                    .parse()
+                    .map_err(|_| invalid("not an IPv4 address".to_string()))?,
+            ),
+            RecordType::Aaaa => Self::Aaaa(
+                value
+                    .parse()
+                    .map_err(|_| invalid("not an IPv6 address".to_string()))?,
+            ),
+            RecordType::Cname => Self::Cname(hostname(value).map_err(invalid)?),
+            RecordType::Txt => Self::Txt(value.to_string()),
+            RecordType::Mx => {
+                let (preference, exchange) = value
+                    .split_once(' ')
+                    .ok_or_else(|| invalid("expected '<preference> <exchange>'".to_string()))?;
+                Self::Mx {
+                    preference: number(preference, "preference").map_err(invalid)?,
+                    exchange: hostname(exchange.trim()).map_err(invalid)?,
+                }
+            }
+            RecordType::Srv => {
+                let parts: Vec<&str> = value.split_whitespace().collect();
+                let [priority, weight, port, target] = parts[..] else {
+                    return Err(invalid(
+                        "expected '<priority> <weight> <port> <target>'".to_string(),
+                    ));
+                };
+                Self::Srv {
+                    priority: number(priority, "priority").map_err(invalid)?,
+                    weight: number(weight, "weight").map_err(invalid)?,
+                    port: number(port, "port").map_err(invalid)?,
+                    target: hostname(target).map_err(invalid)?,
+                }
+            }
+            RecordType::Caa => {
+                let mut parts = value.splitn(3, ' ');
+                let (Some(flags), Some(tag), Some(caa_value)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid("expected '<flags> <tag> <value>'".to_string()));
+                };
+                if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(invalid("CAA tag must be alphanumeric".to_string()));
+                }
+                let caa_value = caa_value.trim();
+                Self::Caa {
+                    flags: number(flags, "flags").map_err(invalid)?,
+                    tag: tag.to_ascii_lowercase(),
+                    value: caa_value
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .unwrap_or(caa_value)
+                        .to_string(),
+                }
+            }
+            RecordType::Ant => {
+                if value.len() != ADDRESS_HEX_LENGTH
+                    || !value.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    return Err(invalid(format!(
+                        "expected a {}-character hex address",
+                        ADDRESS_HEX_LENGTH
+                    )));
+                }
+                Self::Ant(value.to_ascii_lowercase())
+            }
+            RecordType::Delegate => Self::Delegate(Delegation::from_value(value).map_err(invalid)?),
+        };
+
+        Ok(data)
+    }
+
+    /// Type of this record
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            Self::A(_) => RecordType::A,
+            Self::Aaaa(_) => RecordType::Aaaa,
+            Self::Cname(_) => RecordType::Cname,
+            Self::Txt(_) => RecordType::Txt,
+            Self::Mx { .. } => RecordType::Mx,
+            Self::Srv { .. } => RecordType::Srv,
+            Self::Caa { .. } => RecordType::Caa,
+            Self::Ant(_) => RecordType::Ant,
+            Self::Delegate(_) => RecordType::Delegate,
+        }
+    }
+
+    /// Value in its stored string form
+    pub fn value(&self) -> String {
+        match self {
+            Self::A(ip) => ip.to_string(),
+            Self::Aaaa(ip) => ip.to_string(),
+            Self::Cname(name) => name.clone(),
+            Self::Txt(text) => text.clone(),
+            Self::Mx {
+                preference,
+                exchange,
+            } => format!("{} {}", preference, exchange),
+            Self::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => format!("{} {} {} {}", priority, weight, port, target),
+            Self::Caa { flags, tag, value } => format!("{} {} \"{}\"", flags, tag, value),
+            Self::Ant(address) => address.clone(),
+            Self::Delegate(delegation) => delegation.to_value(),
+        }
+    }
+}
+
+/// Validate a host name used as a record target, without the trailing dot
+fn hostname(value: &str) -> std::result::Result<String, String> {
+    let name = value
+        .strip_suffix('.')
+        .unwrap_or(value)
+        .to_ascii_lowercase();
+    if name.is_empty() || name.len() > 253 {
+        return Err("host name must be 1 to 253 characters".to_string());
+    }
+    for label in name.split('.') {
+        validate_label(label, true).map_err(|reason| format!("invalid host name: {}", reason))?;
+    }
+    Ok(name)
+}
+
+fn number<T: std::str::FromStr>(value: &str, what: &str) -> std::result::Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", what, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register::DnsRecord;
+
+    #[test]
+    fn test_parse_record_data() {
+        assert_eq!(
+            RecordData::parse("a", "192.0.2.1").unwrap(),
+            RecordData::A(Ipv4Addr::new(192, 0, 2, 1))
+        );
+        assert_eq!(
+            RecordData::parse("AAAA", "2001:db8::1").unwrap().value(),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            RecordData::parse("CNAME", "Web.Example.com.").unwrap(),
+            RecordData::Cname("web.example.com".to_string())
+        );
+        assert_eq!(
+            RecordData::parse("TEXT", "hello world")
+                .unwrap()
+                .record_type(),
+            RecordType::Txt
+        );
+        assert_eq!(
+            RecordData::parse("MX", "10 mail.example.com").unwrap(),
+            RecordData::Mx {
+                preference: 10,
+                exchange: "mail.example.com".to_string()
+            }
+        );
+        assert_eq!(
+            RecordData::parse("SRV", "0 5 5060 _sip.example.com")
+                .unwrap()
+                .value(),
+            "0 5 5060 _sip.example.com"
+        );
+        assert_eq!(
+            RecordData::parse("CAA", "0 issue \"letsencrypt.org\"").unwrap(),
+            RecordData::Caa {
+                flags: 0,
+                tag: "issue".to_string(),
+                value: "letsencrypt.org".to_string()
+            }
+        );
+        assert!(RecordData::parse("ANT", &"Ab".repeat(32)).is_ok());
+
+        let invalid = [
+            ("A", "256.0.0.1"),
+            ("A", "::1"),
+            ("AAAA", "192.0.2.1"),
+            ("CNAME", "bad..name"),
+            ("MX", "mail.example.com"),
+            ("MX", "70000 mail.example.com"),
+            ("SRV", "0 5 99999 sip.example.com"),
+            ("SRV", "0 5 sip.example.com"),
+            ("CAA", "0 is-sue x"),
+            ("ANT", "abc123"),
+            ("ANT", &"zz".repeat(32)),
+            ("DELEGATE", "abcd"),
+            ("NS", "ns.example.com"),
+        ];
+        for (record_type, value) in invalid {
+            assert!(
+                matches!(
+                    RecordData::parse(record_type, value),
+                    Err(AntnsError::InvalidRecord { .. })
+                ),
+                "{} {}",
+                record_type,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_wire_format() {
+        // Records without a TTL serialize exactly like before TTLs existed
+        let legacy = r#"{"type":"ant","name":".","value":"abc123"}"#;
+        let record: DnsRecord = serde_json::from_str(legacy).unwrap();
+        assert_eq!(record.ttl, None);
+        assert!(record.is_type(RecordType::Ant));
+        assert_eq!(serde_json::to_string(&record).unwrap(), legacy);
+
+        let data = RecordData::parse("mx", "10 mail.example.com").unwrap();
+        let record = DnsRecord::new("www", data.clone()).with_ttl(60);
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"type":"MX","name":"www","value":"10 mail.example.com","ttl":60}"#
+        );
+        assert_eq!(record.data().unwrap(), data);
+    }
+}
@@ -0,0 +1,691 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Verified domain resolution
+//!
+//! [`Resolver`] is the one place where a register history is walked and
+//! turned into verified state. Lookups, record listings, history and
+//! inspection are all views of the same [`ResolvedDomain`].
+//!
+//! # Resolution policy
+//!
+//! 1. The first register entry is the owner document; every later entry must
+//!    be signed by the owner's key for this domain, otherwise it is spam.
+//! 2. Among validly signed documents the current one is chosen with the
+//!    sequence rules of [`LatestRecords`] (replays never roll a domain back).
+//! 3. Records, including the root target, are read from the current document
+//!    only. If the owner removed the root `ant` record, the domain has no
+//!    target; an older document is never used as a fallback.
+//! 4. A `DELEGATE` record hands a label to another key (see
+//!    [`crate::register::delegation`]). Names at or below a delegated label
+//!    are resolved from the child zone, whose entries must all be signed by
+//!    the delegated key for the child name. At most
+//!    [`LookupOptions::max_delegation_depth`] delegations are followed.
+//!    Delegated zones are always walked in full; checkpoints only cover
+//!    registered domains.
+//! 5. Legacy signatures are only accepted with
+//!    [`VerifyMode::AllowLegacy`](crate::crypto::VerifyMode::AllowLegacy),
+//!    and such lookups bypass checkpoints.
+
+use crate::constants::{CHECKPOINT_FORMAT_VERSION, ROOT_RECORD_NAME};
+use crate::crypto::VerifyMode;
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
+use crate::register::delegation::{find_zone_cut, Delegation};
+use crate::register::lookup::{
+    domain_register, download_in_order, fetch_owner_document, parse_owner_key, LatestRecords,
+    LookupOptions, LookupStats,
+};
+use crate::register::name::{registered_domain, DomainName};
+use crate::register::record::RecordType;
+use crate::register::{DnsRecord, DomainOwner, DomainRecordsDocument, HistoryEntry};
+use crate::storage::checkpoint::Checkpoint;
+use autonomi::register::{RegisterAddress, RegisterValue};
+use ed25519_dalek::VerifyingKey;
+use futures::StreamExt;
+use std::time::Instant;
+
+/// Resolves domains by walking and verifying their register history
+pub struct Resolver<'a, N: NameNetwork> {
+    client: &'a N,
+    options: LookupOptions,
+}
+
+/// Key that signs a zone's records
+#[derive(Debug, Clone)]
+pub enum ZoneAuthority {
+    /// Registered domain, signed by the key in its owner document
+    Owner(DomainOwner),
+    /// Delegated zone, signed by the key named in the parent's `DELEGATE` record
+    Delegated {
+        /// Zone holding the `DELEGATE` record
+        parent: String,
+        delegation: Box<Delegation>,
+    },
+}
+
+/// Verified state of a zone: a registered domain or a delegated subdomain
+#[derive(Debug, Clone)]
+pub struct ResolvedDomain {
+    pub domain: String,
+    pub authority: ZoneAuthority,
+    current: Option<DomainRecordsDocument>,
+    history: Option<Vec<HistoryEntry>>,
+    /// Register this state was read from
+    register: RegisterAddress,
+    /// Last register entry this state was read from
+    head: Option<RegisterValue>,
+    /// Entry counts and timings for the walk that produced this state
+    pub stats: LookupStats,
+}
+
+impl ResolvedDomain {
+    /// Ed25519 public key (hex) that signs this zone's records
+    pub fn owner_public_key(&self) -> String {
+        match &self.authority {
+            ZoneAuthority::Owner(owner) => owner.document.public_key.clone(),
+            ZoneAuthority::Delegated { delegation, .. } => delegation.public_key_hex(),
+        }
+    }
+
+    /// Chunk address of the owner document; delegated zones have none
+    pub fn owner_chunk_address(&self) -> Option<[u8; 32]> {
+        match &self.authority {
+            ZoneAuthority::Owner(owner) => Some(owner.chunk_address),
+            ZoneAuthority::Delegated { .. } => None,
+        }
+    }
+
+    /// Current records document, if the owner published any valid one
+    pub fn document(&self) -> Option<&DomainRecordsDocument> {
+        self.current.as_ref()
+    }
+
+    /// Consume the state and return the current records document
+    pub fn into_document(self) -> Option<DomainRecordsDocument> {
+        self.current
+    }
+
+    /// All current records
+    pub fn records(&self) -> &[DnsRecord] {
+        self.current
+            .as_ref()
+            .map(|doc| doc.records.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Current records with the given name and type
+    pub fn records_for<'b>(
+        &'b self,
+        name: &'b str,
+        record_type: RecordType,
+    ) -> impl Iterator<Item = &'b DnsRecord> + 'b {
+        self.records()
+            .iter()
+            .filter(move |r| r.name == name && r.is_type(record_type))
+    }
+
+    /// Target of the root `ant` record in the current document
+    pub fn root_target(&self) -> Option<&str> {
+        self.current.as_ref().and_then(|doc| doc.root_target())
+    }
+
+    /// Target of the `ant` record with the given name (`.` for the root)
+    pub fn target_for(&self, record_name: &str) -> Option<&str> {
+        self.current
+            .as_ref()
+            .and_then(|doc| doc.target_for(record_name))
+    }
+
+    /// Every history entry with its validation status
+    ///
+    /// Only available from [`Resolver::resolve_with_history`].
+    pub fn history(&self) -> Option<&[HistoryEntry]> {
+        self.history.as_deref()
+    }
+
+    /// Register this state was read from
+    ///
+    /// Usually derived from the zone's name; see [`crate::register::name`]
+    /// for domains registered before names were lowercased.
+    pub fn register(&self) -> RegisterAddress {
+        self.register
+    }
+
+    /// Register head this state was read from; `None` for an empty register
+    pub fn head(&self) -> Option<RegisterValue> {
+        self.head
+    }
+}
+
+/// A full name resolved through the delegations on its path
+#[derive(Debug, Clone)]
+pub struct ResolvedName {
+    pub name: DomainName,
+    /// Zone whose records hold the name
+    pub zone: ResolvedDomain,
+    /// Name of the selected records within `zone` (`.` for the zone itself)
+    pub record_name: String,
+    /// Number of delegations followed
+    pub depth: usize,
+    /// Entry counts and timings summed over every zone walked
+    pub stats: LookupStats,
+    /// Registers the resolution was read from
+    registers: Vec<RegisterAddress>,
+    /// Heads of [`ResolvedName::registers`] at the time of resolution
+    heads: Vec<Option<RegisterValue>>,
+}
+
+impl ResolvedName {
+    /// Target of the name's `ant` record, if the zone has one
+    pub fn target(&self) -> Option<&str> {
+        self.zone.target_for(&self.record_name)
+    }
+
+    /// Current records of the zone named after this name
+    pub fn records(&self) -> impl Iterator<Item = &DnsRecord> {
+        self.zone
+            .records()
+            .iter()
+            .filter(|r| r.name == self.record_name)
+    }
+
+    /// Whether the name exists in its zone
+    ///
+    /// The zone itself always exists. Any other name exists if it has
+    /// records, or if a name below it has records (an empty non-terminal,
+    /// see RFC 8020).
+    pub fn exists(&self) -> bool {
+        let suffix = format!(".{}", self.record_name);
+        self.record_name == ROOT_RECORD_NAME
+            || self
+                .zone
+                .records()
+                .iter()
+                .any(|r| r.name == self.record_name || r.name.ends_with(&suffix))
+    }
+
+    /// Registers whose entries this resolution was read from
+    ///
+    /// The registered domain's register, plus the delegated zone's register
+    /// if the name was resolved through a delegation. A new entry in any of
+    /// them can change the resolution.
+    pub fn registers(&self) -> &[RegisterAddress] {
+        &self.registers
+    }
+
+    /// Heads of [`ResolvedName::registers`] this resolution was read from
+    ///
+    /// Comparing them with the current heads tells whether the resolution
+    /// is out of date. An empty delegated register has no head.
+    pub fn heads(&self) -> &[Option<RegisterValue>] {
+        &self.heads
+    }
+}
+
+/// Result of verifying a run of register entries in order
+struct VerifiedEntries {
+    latest: LatestRecords,
+    history: Option<Vec<HistoryEntry>>,
+    /// Entries verified before the first failed download and the current document at that point
+    resume_point: Option<(usize, Option<DomainRecordsDocument>)>,
+}
+
+impl<'a, N: NameNetwork> Resolver<'a, N> {
+    /// Resolver with default lookup options
+    pub fn new(client: &'a N) -> Self {
+        Self::with_options(client, LookupOptions::default())
+    }
+
+    /// Resolver with explicit lookup options
+    pub fn with_options(client: &'a N, options: LookupOptions) -> Self {
+        Self { client, options }
+    }
+
+    /// Resolve the current verified state of a registered domain
+    ///
+    /// `domain` must be a registered name such as `mydomain.ant`; use
+    /// [`Resolver::resolve_name`] for subdomains. Resumes from a checkpoint
+    /// when the options provide a cache.
+    pub async fn resolve(&self, domain: &str) -> Result<ResolvedDomain> {
+        self.walk(domain, false)
+            .await?
+            .ok_or_else(|| AntnsError::RegisterNotFound(domain.to_string()))
+    }
+
+    /// Resolve a domain and keep every history entry
+    ///
+    /// Always walks the full history; checkpoints are only written, not read.
+    pub async fn resolve_with_history(&self, domain: &str) -> Result<ResolvedDomain> {
+        self.walk(domain, true)
+            .await?
+            .ok_or_else(|| AntnsError::RegisterNotFound(domain.to_string()))
+    }
+
+    /// Resolve a full name to the zone holding its records
+    ///
+    /// Starts at the registered domain and follows `DELEGATE` records
+    /// towards the name, see [`crate::register::name`] for how records are
+    /// selected within the final zone.
+    pub async fn resolve_name(&self, name: &DomainName) -> Result<ResolvedName> {
+        let mut zone = self.resolve(name.typed_base()).await?;
+        let mut registers = vec![zone.register];
+        let mut stats = zone.stats.clone();
+        let mut heads = vec![zone.head];
+        let mut record_name = name.record_name();
+        let mut depth = 0;
+
+        while let Some((label, remaining, record)) = find_zone_cut(zone.records(), &record_name) {
+            let child = format!("{}.{}", label, zone.domain);
+            if depth >= self.options.max_delegation_depth {
+                return Err(AntnsError::Delegation {
+                    name: child,
+                    reason: format!(
+                        "more than {} nested delegations",
+                        self.options.max_delegation_depth
+                    ),
+                });
+            }
+
+            let delegation = Delegation::parse(&child, &record.value)?;
+            tracing::debug!(
+                "'{}' is delegated to key {}",
+                child,
+                delegation.public_key_hex()
+            );
+
+            zone = self
+                .walk_delegated(&child, &zone.domain, delegation)
+                .await?;
+            stats.add(&zone.stats);
+            record_name = remaining;
+            depth += 1;
+        }
+
+        if depth > 0 {
+            registers.push(zone.register);
+            heads.push(zone.head);
+        }
+
+        Ok(ResolvedName {
+            name: name.clone(),
+            zone,
+            record_name,
+            depth,
+            stats,
+            registers,
+            heads,
+        })
+    }
+
+    /// Walk a domain's register history and compute its verified state
+    ///
+    /// Entries are downloaded concurrently but verified strictly in register
+    /// order, so the result is identical to a sequential walk. Returns `None`
+    /// if the register has no entries (domain not registered).
+    pub(crate) async fn walk(
+        &self,
+        domain: &str,
+        keep_history: bool,
+    ) -> Result<Option<ResolvedDomain>> {
+        let typed = domain;
+        let domain = registered_domain(domain)?;
+        let domain = domain.as_str();
+        let client = self.client;
+        let options = &self.options;
+        let started = Instant::now();
+        let mut stats = LookupStats {
+            concurrency: options.concurrency.max(1),
+            ..Default::default()
+        };
+
+        // Step 1: Find the register (derived from the domain name) and
+        // fetch its history (all chunk addresses)
+        let register = domain_register(client, typed).await?;
+        let (register_addr, history) = (register.address, register.history);
+        let register_hex = register_addr.to_hex();
+
+        tracing::debug!(
+            "Resolving domain '{}' at register: {}",
+            domain,
+            register_addr
+        );
+
+        // Step 2: Resolve the owner document (first entry), from a checkpoint if possible
+        let owner_chunk_addr = match history.first() {
+            Some(addr) => *addr,
+            None => return Ok(None),
+        };
+
+        // Documents accepted in legacy mode must never reach strict lookups
+        let checkpoints = options
+            .checkpoints
+            .as_ref()
+            .filter(|_| options.verify_mode == VerifyMode::Strict);
+
+        let checkpoint = checkpoints
+            .filter(|_| !keep_history)
+            .and_then(|cache| cache.load(domain, &register_hex))
+            .filter(|checkpoint| {
+                // Histories only grow and never change their first entry
+                checkpoint.owner_chunk_address == hex::encode(owner_chunk_addr)
+                    && checkpoint.processed_entries >= 1
+                    && checkpoint.processed_entries <= history.len()
+            });
+
+        let (owner_doc, latest, start) = match checkpoint {
+            Some(checkpoint) => {
+                tracing::debug!(
+                    "Resuming '{}' from checkpoint at entry {}",
+                    domain,
+                    checkpoint.processed_entries
+                );
+                stats.cached_entries = checkpoint.processed_entries - 1;
+                (
+                    checkpoint.owner,
+                    LatestRecords::resume(checkpoint.current),
+                    checkpoint.processed_entries,
+                )
+            }
+            None => (
+                fetch_owner_document(client, domain, owner_chunk_addr).await?,
+                LatestRecords::default(),
+                1,
+            ),
+        };
+
+        tracing::debug!("Owner public key: {}", owner_doc.public_key);
+
+        // Parse owner's Ed25519 public key
+        let owner_pubkey = parse_owner_key(domain, &owner_doc)?;
+
+        let entries = keep_history.then(|| {
+            let mut entries = Vec::with_capacity(history.len());
+            entries.push(HistoryEntry::Owner {
+                public_key: owner_doc.public_key.clone(),
+                chunk_address: hex::encode(owner_chunk_addr),
+            });
+            entries
+        });
+
+        stats.history_time = started.elapsed();
+        stats.entries = history.len() - start;
+
+        // Step 3: Download new entries concurrently, verify them in order
+        let VerifiedEntries {
+            latest,
+            history: entries,
+            resume_point,
+        } = self
+            .verify_entries(
+                domain,
+                &owner_pubkey,
+                &history[start..],
+                latest,
+                entries,
+                &mut stats,
+            )
+            .await;
+
+        let current = latest.into_current();
+
+        // Step 4: Save the new checkpoint for the next lookup
+        if let Some(cache) = checkpoints {
+            let (processed_entries, checkpoint_current) = match resume_point {
+                Some((verified, current)) => (start + verified, current),
+                None => (history.len(), current.clone()),
+            };
+
+            if processed_entries > start || stats.cached_entries == 0 {
+                let checkpoint = Checkpoint {
+                    format_version: CHECKPOINT_FORMAT_VERSION,
+                    domain: domain.to_string(),
+                    register_address: register_hex,
+                    owner_chunk_address: hex::encode(owner_chunk_addr),
+                    owner: owner_doc.clone(),
+                    processed_entries,
+                    current: checkpoint_current,
+                };
+                if let Err(e) = cache.store(&checkpoint) {
+                    tracing::warn!("Failed to save checkpoint for '{}': {:#}", domain, e);
+                }
+            }
+        }
+
+        stats.total_time = started.elapsed();
+
+        tracing::info!("Domain resolution complete for '{}': {}", domain, stats);
+
+        Ok(Some(ResolvedDomain {
+            domain: domain.to_string(),
+            authority: ZoneAuthority::Owner(DomainOwner {
+                chunk_address: owner_chunk_addr,
+                document: owner_doc,
+            }),
+            current,
+            history: entries,
+            register: register_addr,
+            head: history.last().copied(),
+            stats,
+        }))
+    }
+
+    /// Walk a delegated zone's register and compute its verified state
+    ///
+    /// There is no owner document: every entry must be signed by the
+    /// delegated key for the child name. An empty register is a delegated
+    /// zone without records.
+    async fn walk_delegated(
+        &self,
+        zone: &str,
+        parent: &str,
+        delegation: Delegation,
+    ) -> Result<ResolvedDomain> {
+        let started = Instant::now();
+        let mut stats = LookupStats {
+            concurrency: self.options.concurrency.max(1),
+            ..Default::default()
+        };
+
+        let register_addr = delegation.register_for(zone)?;
+        tracing::debug!(
+            "Resolving delegated zone '{}' at register: {}",
+            zone,
+            register_addr
+        );
+
+        let history = self.client.register_history(&register_addr).await?;
+        stats.history_time = started.elapsed();
+        stats.entries = history.len();
+
+        let verified = self
+            .verify_entries(
+                zone,
+                &delegation.public_key,
+                &history,
+                LatestRecords::default(),
+                None,
+                &mut stats,
+            )
+            .await;
+
+        stats.total_time = started.elapsed();
+
+        tracing::info!(
+            "Delegated zone resolution complete for '{}': {}",
+            zone,
+            stats
+        );
+
+        Ok(ResolvedDomain {
+            domain: zone.to_string(),
+            authority: ZoneAuthority::Delegated {
+                parent: parent.to_string(),
+                delegation: Box::new(delegation),
+            },
+            current: verified.latest.into_current(),
+            history: None,
+            register: register_addr,
+            head: history.last().copied(),
+            stats,
+        })
+    }
+
+    /// Download register entries concurrently and verify them in register order
+    ///
+    /// Every entry must be a records document signed by `key` for `zone`;
+    /// anything else is counted as invalid and skipped.
+    async fn verify_entries(
+        &self,
+        zone: &str,
+        key: &VerifyingKey,
+        addresses: &[RegisterValue],
+        mut latest: LatestRecords,
+        mut entries: Option<Vec<HistoryEntry>>,
+        stats: &mut LookupStats,
+    ) -> VerifiedEntries {
+        let client = self.client;
+        let entries_started = Instant::now();
+        let mut downloads = std::pin::pin!(download_in_order(
+            addresses.to_vec(),
+            self.options.concurrency,
+            |addr| client.data_get_public(addr)
+        ));
+
+        // A failed download may be transient, so the checkpoint must not move past it
+        let mut resume_point = None;
+        let mut index = 0;
+
+        while let Some((chunk_addr, download)) = downloads.next().await {
+            index += 1;
+
+            let document = match download {
+                Ok(data) => match serde_json::from_slice::<DomainRecordsDocument>(&data) {
+                    Ok(doc) => Some(doc),
+                    Err(e) => {
+                        tracing::warn!("Failed to parse data as records document: {}", e);
+                        None // Skip invalid JSON
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Failed to download data {}: {}", hex::encode(chunk_addr), e);
+                    resume_point.get_or_insert_with(|| (index - 1, latest.current().cloned()));
+                    None // Skip unreachable entries
+                }
+            };
+
+            // Verify signature
+            let verify_started = Instant::now();
+            let is_valid = document
+                .as_ref()
+                .is_some_and(|doc| doc.verify_with(zone, key, self.options.verify_mode));
+            stats.verify_time += verify_started.elapsed();
+
+            if is_valid {
+                stats.valid_entries += 1;
+            } else {
+                // Invalid signature - spam entry, ignore
+                tracing::debug!("Invalid entry {}, ignoring", hex::encode(chunk_addr));
+                stats.invalid_entries += 1;
+            }
+
+            if let Some(entries) = entries.as_mut() {
+                entries.push(HistoryEntry::Records {
+                    chunk_address: hex::encode(chunk_addr),
+                    records: document.as_ref().map(|doc| doc.records.clone()),
+                    sequence: document.as_ref().and_then(|doc| doc.sequence),
+                    signature: document.as_ref().map(|doc| doc.signature.clone()),
+                    is_valid,
+                });
+            }
+
+            if let Some(doc) = document.filter(|_| is_valid) {
+                if !latest.offer(doc) {
+                    tracing::debug!(
+                        "Stale sequence on chunk {}, ignoring rollback",
+                        hex::encode(chunk_addr)
+                    );
+                }
+            }
+        }
+
+        stats.entries_time = entries_started.elapsed();
+
+        VerifiedEntries {
+            latest,
+            history: entries,
+            resume_point,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(record_type: &str, name: &str, value: &str) -> DnsRecord {
+        DnsRecord {
+            record_type: record_type.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            ttl: None,
+        }
+    }
+
+    fn resolved(records: Option<Vec<DnsRecord>>) -> ResolvedDomain {
+        ResolvedDomain {
+            domain: "test.ant".to_string(),
+            authority: ZoneAuthority::Owner(DomainOwner {
+                chunk_address: [0; 32],
+                document: crate::register::DomainOwnerDocument {
+                    public_key: "00".to_string(),
+                },
+            }),
+            current: records.map(|records| DomainRecordsDocument {
+                version: Some(1),
+                records,
+                sequence: Some(1),
+                timestamp: Some(0),
+                signature: String::new(),
+            }),
+            history: None,
+            register: crate::register::get_register_address_for_domain("test.ant").unwrap(),
+            head: None,
+            stats: LookupStats::default(),
+        }
+    }
+
+    #[test]
+    fn test_accessors_read_current_document() {
+        let domain = resolved(Some(vec![
+            record("ant", ".", "root"),
+            record("ANT", "api.v2", "nested"),
+            record("A", "www", "1.2.3.4"),
+            record("a", "www", "5.6.7.8"),
+            record("TXT", "www", "hello"),
+        ]));
+
+        assert_eq!(domain.root_target(), Some("root"));
+        assert_eq!(domain.target_for("www"), None);
+        assert_eq!(domain.target_for("api.v2"), Some("nested"));
+        assert_eq!(domain.owner_chunk_address(), Some([0; 32]));
+        assert_eq!(domain.records().len(), 5);
+        let values: Vec<_> = domain
+            .records_for("www", RecordType::A)
+            .map(|r| r.value.as_str())
+            .collect();
+        assert_eq!(values, ["1.2.3.4", "5.6.7.8"]);
+        assert!(domain.history().is_none());
+    }
+
+    #[test]
+    fn test_no_root_record_means_no_target() {
+        let domain = resolved(Some(vec![record("TXT", ".", "root removed")]));
+        assert_eq!(domain.root_target(), None);
+        assert_eq!(domain.records().len(), 1);
+
+        let domain = resolved(None);
+        assert_eq!(domain.root_target(), None);
+        assert!(domain.records().is_empty());
+    }
+}
@@ -3,19 +3,95 @@
 
 //! Domain update operations
 
-use crate::constants::DNS_REGISTER_KEY_HEX;
-use crate::crypto::sign_records;
-use crate::register::{DnsRecord, DomainRecordsDocument};
+use crate::constants::ROOT_RECORD_NAME;
+use crate::crypto::VerifyMode;
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
+use crate::register::lookup::{domain_register, LookupOptions};
+use crate::register::name::{registered_domain, DomainName};
+use crate::register::resolver::{Resolver, ZoneAuthority};
+use crate::register::{
+    domain_register_key, get_register_address_for_domain, DnsRecord, DomainRecordsDocument,
+};
 use crate::storage::chunks::upload_document_as_chunk;
-use anyhow::{Context, Result};
-use autonomi::client::payment::PaymentOption;
+use anyhow::Context;
 use autonomi::{AttoTokens, Client, SecretKey};
 use ed25519_dalek::SigningKey;
 
+/// Zone and register that records for a name are published to
+struct PublishTarget {
+    zone: String,
+    register_key: SecretKey,
+    /// The register has no entries yet and must be created
+    create: bool,
+}
+
+/// Find where records for `domain` are published with `signing_key`
+///
+/// A registered domain publishes to its own register. A subdomain can only
+/// publish if it is exactly a delegated zone and `signing_key` is the
+/// delegated key; its register is created on first use.
+async fn publish_target<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    signing_key: &SigningKey,
+) -> Result<PublishTarget> {
+    let name = DomainName::parse(domain)?;
+    if name.is_root() {
+        // Records are signed for the canonical (lowercase) registered name,
+        // even in a register derived from the name as typed
+        let register = domain_register(client, domain).await?;
+        return Ok(PublishTarget {
+            register_key: domain_register_key(&register.name)?,
+            zone: registered_domain(domain)?,
+            create: false,
+        });
+    }
+
+    let zone = name.to_string();
+    let invalid = |reason: String| AntnsError::Delegation {
+        name: zone.clone(),
+        reason,
+    };
+
+    let resolved = Resolver::new(client).resolve_name(&name).await?;
+    let delegation = match &resolved.zone.authority {
+        ZoneAuthority::Delegated { delegation, .. } if resolved.record_name == ROOT_RECORD_NAME => {
+            delegation
+        }
+        _ => {
+            return Err(invalid(format!(
+                "not a delegated zone; its records belong to '{}'",
+                resolved.zone.domain
+            )))
+        }
+    };
+
+    if delegation.public_key != signing_key.verifying_key() {
+        return Err(invalid("signing key is not the delegated key".to_string()));
+    }
+
+    let register_addr =
+        get_register_address_for_domain(&zone).context("Failed to derive register address")?;
+    if delegation.register_for(&zone)? != register_addr {
+        return Err(invalid(
+            "records in a custom register must be published by the register's owner".to_string(),
+        ));
+    }
+
+    let create = client.register_history(&register_addr).await?.is_empty();
+
+    Ok(PublishTarget {
+        register_key: domain_register_key(&zone)?,
+        zone,
+        create,
+    })
+}
+
 /// Update a domain's target address
 ///
 /// # Arguments
-/// * `client` - Autonomi client instance
+/// * `client` - Network backend (e.g. an Autonomi client)
 /// * `domain` - Domain name to update
 /// * `new_target` - New target address (hex)
 /// * `owner_key` - Domain owner's Ed25519 signing key
@@ -23,108 +99,86 @@ use ed25519_dalek::SigningKey;
 ///
 /// # Returns
 /// Total cost of the update operation
-pub async fn update_domain(
-    client: &Client,
+pub async fn update_domain<N: NameNetwork>(
+    client: &N,
     domain: &str,
     new_target: &str,
     owner_key: &SigningKey,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<AttoTokens> {
     tracing::info!("Updating domain '{}' to target: {}", domain, new_target);
 
-    // Step 1: Create new records
     let records = vec![DnsRecord {
         record_type: "ant".to_string(),
         name: ".".to_string(),
         value: new_target.to_string(),
+        ttl: None,
     }];
 
-    // Step 2: Sign records with owner key
-    let signature = sign_records(&records, owner_key).context("Failed to sign records")?;
-
-    let records_doc = DomainRecordsDocument { records, signature };
-
-    // Step 3: Upload new records as chunk
-    let (chunk_cost, records_chunk_addr) =
-        upload_document_as_chunk(client, &records_doc, payment.clone())
-            .await
-            .context("Failed to upload new records document")?;
-
-    tracing::debug!(
-        "New records uploaded to chunk: {}",
-        hex::encode(records_chunk_addr)
-    );
-
-    // Step 4: Append to register
-    let records_value = Client::register_value_from_bytes(&records_chunk_addr)
-        .context("Failed to create register value from chunk address")?;
-
-    // Derive register key from shared DNS key and domain name
-    let shared_key_bytes = hex::decode(DNS_REGISTER_KEY_HEX)?;
-    let shared_key_array: [u8; 32] = shared_key_bytes
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("Invalid DNS register key length"))?;
-    let shared_key = SecretKey::from_bytes(shared_key_array)
-        .map_err(|e| anyhow::anyhow!("Invalid DNS register key: {:?}", e))?;
-
-    let register_key = Client::register_key_from_name(&shared_key, domain);
+    // Sign records with owner key, continuing the current sequence
+    let sequence = next_sequence(client, domain).await?;
 
-    let update_cost = client
-        .register_update(&register_key, records_value, payment)
-        .await
-        .context("Failed to update register")?;
-
-    tracing::info!("Domain '{}' updated successfully", domain);
+    publish_records(client, domain, records, sequence, owner_key, payment).await
+}
 
-    // Total cost
-    let total_cost = chunk_cost
-        .checked_add(update_cost)
-        .context("Cost overflow")?;
+/// Update domain with multiple record types
+pub async fn update_domain_records<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    records: Vec<DnsRecord>,
+    owner_key: &SigningKey,
+    payment: N::Payment,
+) -> Result<AttoTokens> {
+    let sequence = next_sequence(client, domain).await?;
 
-    Ok(total_cost)
+    publish_records(client, domain, records, sequence, owner_key, payment).await
 }
 
-/// Update domain with multiple record types
-pub async fn update_domain_records(
-    client: &Client,
+/// Sign records with the given sequence number and append them to the register
+///
+/// `domain` is a registered domain or a delegated zone, see [`publish_target`].
+async fn publish_records<N: NameNetwork>(
+    client: &N,
     domain: &str,
     records: Vec<DnsRecord>,
+    sequence: u64,
     owner_key: &SigningKey,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<AttoTokens> {
+    let target = publish_target(client, domain, owner_key).await?;
+    let domain = target.zone.as_str();
+
     tracing::info!(
-        "Updating domain '{}' with {} records",
+        "Updating domain '{}' with {} records (sequence {})",
         domain,
-        records.len()
+        records.len(),
+        sequence
     );
 
     // Sign all records
-    let signature = sign_records(&records, owner_key).context("Failed to sign records")?;
-
-    let records_doc = DomainRecordsDocument { records, signature };
+    let records_doc = DomainRecordsDocument::sign(domain, records, sequence, owner_key)
+        .context("Failed to sign records")?;
 
     // Upload records document
     let (chunk_cost, records_chunk_addr) =
-        upload_document_as_chunk(client, &records_doc, payment.clone())
-            .await
-            .context("Failed to upload records document")?;
+        upload_document_as_chunk(client, &records_doc, payment.clone()).await?;
 
     // Update register
-    let records_value = Client::register_value_from_bytes(&records_chunk_addr)?;
-
-    let shared_key_bytes = hex::decode(DNS_REGISTER_KEY_HEX)?;
-    let shared_key_array: [u8; 32] = shared_key_bytes
-        .try_into()
-        .map_err(|_| anyhow::anyhow!("Invalid DNS register key length"))?;
-    let shared_key = SecretKey::from_bytes(shared_key_array)
-        .map_err(|e| anyhow::anyhow!("Invalid DNS register key: {:?}", e))?;
+    let records_value = Client::register_value_from_bytes(&records_chunk_addr)
+        .context("Failed to create register value from chunk address")?;
 
-    let register_key = Client::register_key_from_name(&shared_key, domain);
+    let update_cost = if target.create {
+        let (cost, _) = client
+            .register_create(&target.register_key, records_value, payment)
+            .await?;
+        cost
+    } else {
+        client
+            .register_update(&target.register_key, records_value, payment)
+            .await?
+    };
 
-    let update_cost = client
-        .register_update(&register_key, records_value, payment)
-        .await
-        .context("Failed to update register")?;
+    tracing::info!("Domain '{}' updated successfully", domain);
 
     let total_cost = chunk_cost
         .checked_add(update_cost)
@@ -133,93 +187,163 @@ pub async fn update_domain_records(
     Ok(total_cost)
 }
 
+/// Sequence number for the next records document of a domain
+async fn next_sequence<N: NameNetwork>(client: &N, domain: &str) -> Result<u64> {
+    let current = crate::register::lookup::lookup_domain_document(client, domain).await?;
+
+    Ok(current.map_or(1, |doc| doc.next_sequence()))
+}
+
+/// Fetch the current records and the sequence number to publish them under
+async fn current_records<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+) -> Result<(Vec<DnsRecord>, u64)> {
+    let current = crate::register::lookup::lookup_domain_document(client, domain).await?;
+
+    Ok(match current {
+        Some(doc) => {
+            let sequence = doc.next_sequence();
+            (doc.records, sequence)
+        }
+        None => (Vec::new(), 1),
+    })
+}
+
+/// Re-publish a domain's current legacy records in the current signed format
+///
+/// Domains whose latest document predates sequence numbers and domain-bound
+/// signatures stay vulnerable to rollback and cross-domain reuse until a
+/// current document is published. The lookup accepts legacy signatures so the
+/// legacy document can be found. Returns `None` if the current document is
+/// already up to date.
+pub async fn migrate_domain_records<N: NameNetwork>(
+    client: &N,
+    domain: &str,
+    owner_key: &SigningKey,
+    payment: N::Payment,
+) -> Result<Option<AttoTokens>> {
+    let options = LookupOptions {
+        verify_mode: VerifyMode::AllowLegacy,
+        ..Default::default()
+    };
+    let (current, _) =
+        crate::register::lookup::lookup_domain_document_with(client, domain, &options).await?;
+
+    match current {
+        Some(doc) if doc.is_legacy() => {
+            let sequence = doc.next_sequence();
+            let cost =
+                publish_records(client, domain, doc.records, sequence, owner_key, payment).await?;
+            Ok(Some(cost))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// Add a new record to a domain
 ///
 /// Fetches current records, adds the new one, and updates the register
-pub async fn add_domain_record(
-    client: &Client,
+pub async fn add_domain_record<N: NameNetwork>(
+    client: &N,
     domain: &str,
     new_record: DnsRecord,
     owner_key: &SigningKey,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<AttoTokens> {
     tracing::info!("Adding record to domain '{}'", domain);
 
-    // Fetch current records
-    let mut current_records = crate::lookup_domain_records(client, domain)
-        .await
-        .unwrap_or_else(|_| Vec::new()); // If no records exist, start with empty
+    // Fetch current records (empty if none exist yet)
+    let (mut current_records, sequence) = current_records(client, domain).await?;
 
     // Add new record
     current_records.push(new_record);
 
     // Update with all records
-    update_domain_records(client, domain, current_records, owner_key, payment).await
+    publish_records(
+        client,
+        domain,
+        current_records,
+        sequence,
+        owner_key,
+        payment,
+    )
+    .await
 }
 
 /// Delete a record by index
 ///
 /// Fetches current records, removes the specified one, and updates the register
-pub async fn delete_domain_record(
-    client: &Client,
+pub async fn delete_domain_record<N: NameNetwork>(
+    client: &N,
     domain: &str,
     index: usize,
     owner_key: &SigningKey,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<AttoTokens> {
     tracing::info!("Deleting record {} from domain '{}'", index, domain);
 
     // Fetch current records
-    let mut current_records = crate::lookup_domain_records(client, domain)
-        .await
-        .context("Failed to fetch current records")?;
+    let (mut current_records, sequence) = current_records(client, domain).await?;
 
     // Validate index
     if index >= current_records.len() {
-        anyhow::bail!(
-            "Record index {} out of bounds (total records: {})",
+        return Err(AntnsError::RecordIndexOutOfBounds {
             index,
-            current_records.len()
-        );
+            total: current_records.len(),
+        });
     }
 
     // Remove record
     current_records.remove(index);
 
     // Update with remaining records
-    update_domain_records(client, domain, current_records, owner_key, payment).await
+    publish_records(
+        client,
+        domain,
+        current_records,
+        sequence,
+        owner_key,
+        payment,
+    )
+    .await
 }
 
 /// Update a record by index
 ///
 /// Fetches current records, replaces the specified one, and updates the register
-pub async fn update_domain_record(
-    client: &Client,
+pub async fn update_domain_record<N: NameNetwork>(
+    client: &N,
     domain: &str,
     index: usize,
     new_record: DnsRecord,
     owner_key: &SigningKey,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<AttoTokens> {
     tracing::info!("Updating record {} for domain '{}'", index, domain);
 
     // Fetch current records
-    let mut current_records = crate::lookup_domain_records(client, domain)
-        .await
-        .context("Failed to fetch current records")?;
+    let (mut current_records, sequence) = current_records(client, domain).await?;
 
     // Validate index
     if index >= current_records.len() {
-        anyhow::bail!(
-            "Record index {} out of bounds (total records: {})",
+        return Err(AntnsError::RecordIndexOutOfBounds {
             index,
-            current_records.len()
-        );
+            total: current_records.len(),
+        });
     }
 
     // Replace record
     current_records[index] = new_record;
 
     // Update with modified records
-    update_domain_records(client, domain, current_records, owner_key, payment).await
+    publish_records(
+        client,
+        domain,
+        current_records,
+        sequence,
+        owner_key,
+        payment,
+    )
+    .await
 }
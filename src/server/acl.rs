@@ -0,0 +1,180 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Client access control for the DNS and HTTP servers
+//!
+//! An open resolver or proxy is quickly abused, so both servers only answer
+//! clients whose address is inside one of the allowed networks. By default
+//! that is the loopback networks (`127.0.0.0/8` and `::1/128`); binding to a
+//! LAN or Docker address also requires allowing that network explicitly.
+//!
+//! IPv4 clients connecting to an IPv6 socket (`::ffff:a.b.c.d`) are matched
+//! as IPv4.
+
+use anyhow::{bail, Context, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// A network in CIDR notation, e.g. `192.168.1.0/24` or `fd00::/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Network {
+    /// Network of `addr` with the given prefix length
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max {
+            bail!("Prefix length /{} is too long for {}", prefix_len, addr);
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// Whether `ip` is inside this network
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = anyhow::Error;
+
+    /// Parse `addr/prefix`; a bare address is a single host
+    fn from_str(value: &str) -> Result<Self> {
+        let (addr, prefix_len) = match value.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (value, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid network address '{}'", value))?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .with_context(|| format!("Invalid prefix length in '{}'", value))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Networks whose clients may use the servers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessList {
+    networks: Vec<Network>,
+}
+
+impl AccessList {
+    /// Access list allowing the given networks
+    pub fn new(networks: Vec<Network>) -> Self {
+        Self { networks }
+    }
+
+    /// Parse a list of CIDR networks
+    pub fn parse<S: AsRef<str>>(networks: &[S]) -> Result<Self> {
+        let networks = networks
+            .iter()
+            .map(|network| network.as_ref().parse())
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(networks))
+    }
+
+    /// Loopback clients only
+    pub fn loopback() -> Self {
+        Self::new(vec![
+            Network {
+                addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 0)),
+                prefix_len: 8,
+            },
+            Network {
+                addr: IpAddr::V6(Ipv6Addr::LOCALHOST),
+                prefix_len: 128,
+            },
+        ])
+    }
+
+    /// Whether a client at `ip` may use the servers
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// Allowed networks
+    pub fn networks(&self) -> &[Network] {
+        &self.networks
+    }
+}
+
+impl Default for AccessList {
+    fn default() -> Self {
+        Self::loopback()
+    }
+}
+
+impl std::fmt::Display for AccessList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let networks: Vec<String> = self.networks.iter().map(ToString::to_string).collect();
+        f.write_str(&networks.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_access_list() {
+        let loopback = AccessList::default();
+        assert!(loopback.allows(ip("127.0.0.1")));
+        assert!(loopback.allows(ip("127.1.2.3")));
+        assert!(loopback.allows(ip("::1")));
+        assert!(loopback.allows(ip("::ffff:127.0.0.1")));
+        assert!(!loopback.allows(ip("192.168.1.10")));
+        assert!(!loopback.allows(ip("::2")));
+
+        let office = AccessList::parse(&["192.168.1.0/24", "fd00::/8", "10.0.0.7"]).unwrap();
+        assert!(office.allows(ip("192.168.1.200")));
+        assert!(office.allows(ip("::ffff:192.168.1.1")));
+        assert!(!office.allows(ip("192.168.2.1")));
+        assert!(office.allows(ip("fd12:3456::1")));
+        assert!(!office.allows(ip("fe80::1")));
+        assert!(office.allows(ip("10.0.0.7")));
+        assert!(!office.allows(ip("10.0.0.8")));
+        assert!(!office.allows(ip("127.0.0.1")));
+        assert_eq!(office.to_string(), "192.168.1.0/24, fd00::/8, 10.0.0.7/32");
+
+        let everyone = AccessList::parse(&["0.0.0.0/0", "::/0"]).unwrap();
+        assert!(everyone.allows(ip("203.0.113.5")));
+        assert!(everyone.allows(ip("2001:db8::1")));
+
+        for invalid in ["192.168.1.0/33", "::/129", "example.com/8", "10.0.0.0/x"] {
+            assert!(invalid.parse::<Network>().is_err(), "{}", invalid);
+        }
+    }
+}
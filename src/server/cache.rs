@@ -0,0 +1,565 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Cache of verified name resolutions shared by the DNS and HTTP servers
+//!
+//! Both servers resolve names through one [`ResolutionCache`], so a name
+//! looked up by the DNS server is already verified when the browser's HTTP
+//! request for it arrives.
+//!
+//! - The cache holds at most a fixed number of names and evicts the least
+//!   recently used one when full
+//! - Concurrent misses for the same name wait for a single lookup and share
+//!   its outcome, failures included
+//! - A resolution older than the TTL is still served for one more TTL while
+//!   it is refreshed in the background (stale-while-revalidate)
+//! - Unregistered names are cached for a separate, shorter negative TTL;
+//!   other failures are not cached
+
+use crate::constants::{DEFAULT_NEGATIVE_TTL_SECS, RESOLUTION_CACHE_MAX_ENTRIES};
+use crate::error::{AntnsError, Result};
+use crate::network::NameNetwork;
+use crate::register::lookup::LookupOptions;
+use crate::register::name::DomainName;
+use crate::register::resolver::{ResolvedName, Resolver};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Result of a lookup shared by every caller waiting for it
+pub type SharedResult<T> = std::result::Result<T, Arc<AntnsError>>;
+
+/// A lookup in flight that every caller waiting for the name polls
+type PendingLookup = Shared<BoxFuture<'static, SharedResult<Arc<ResolvedName>>>>;
+
+/// Result of a lookup worth caching
+#[derive(Clone)]
+enum Outcome {
+    Resolved(Arc<ResolvedName>),
+    /// The domain's register does not exist
+    NotFound(String),
+}
+
+impl Outcome {
+    fn into_result(self) -> SharedResult<Arc<ResolvedName>> {
+        match self {
+            Outcome::Resolved(resolved) => Ok(resolved),
+            Outcome::NotFound(domain) => Err(Arc::new(AntnsError::RegisterNotFound(domain))),
+        }
+    }
+}
+
+/// A lookup outcome and when it was made
+struct CachedResolution {
+    outcome: Outcome,
+    resolved_at: Instant,
+    last_used: u64,
+}
+
+/// Cached resolutions in least-recently-used order
+#[derive(Default)]
+struct Entries {
+    resolutions: HashMap<String, CachedResolution>,
+    /// Names by the tick of their last use
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Entries {
+    /// Look up a resolution and mark it as used
+    fn get(&mut self, key: &str) -> Option<&CachedResolution> {
+        self.tick += 1;
+        let cached = self.resolutions.get_mut(key)?;
+        self.recency.remove(&cached.last_used);
+        self.recency.insert(self.tick, key.to_string());
+        cached.last_used = self.tick;
+        Some(cached)
+    }
+
+    /// Store a resolution, evicting the least recently used ones beyond `capacity`
+    fn insert(&mut self, key: String, outcome: Outcome, capacity: usize) {
+        self.tick += 1;
+        if let Some(previous) = self.resolutions.remove(&key) {
+            self.recency.remove(&previous.last_used);
+        }
+        while self.resolutions.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.resolutions.remove(&oldest);
+        }
+
+        self.recency.insert(self.tick, key.clone());
+        self.resolutions.insert(
+            key,
+            CachedResolution {
+                outcome,
+                resolved_at: Instant::now(),
+                last_used: self.tick,
+            },
+        );
+    }
+}
+
+/// What the cache knows about a name
+enum Cached {
+    Fresh(Outcome),
+    /// Past the TTL but within the stale window; refresh it
+    Stale(Arc<ResolvedName>),
+}
+
+/// Counters of a [`ResolutionCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Requests answered from the cache, including stale and negative
+    /// entries, or by joining a lookup already in flight
+    pub hits: u64,
+    /// Hits on stale entries that triggered a background refresh
+    pub stale_hits: u64,
+    /// Requests that waited for a network lookup
+    pub misses: u64,
+    /// Names currently cached
+    pub entries: usize,
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits ({} stale), {} misses, {} cached names",
+            self.hits, self.stale_hits, self.misses, self.entries
+        )
+    }
+}
+
+/// Verified resolutions kept for a fixed time
+pub struct ResolutionCache<N: NameNetwork> {
+    client: N,
+    options: LookupOptions,
+    ttl: Duration,
+    negative_ttl: Duration,
+    capacity: usize,
+    cache: Mutex<Entries>,
+    /// Lookups in flight by name
+    pending: Mutex<HashMap<String, PendingLookup>>,
+    hits: AtomicU64,
+    stale_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<N: NameNetwork> ResolutionCache<N> {
+    /// Cache resolving through `client`; a zero `ttl` disables caching
+    pub fn new(client: N, options: LookupOptions, ttl: Duration) -> Self {
+        Self {
+            client,
+            options,
+            ttl,
+            negative_ttl: Duration::from_secs(DEFAULT_NEGATIVE_TTL_SECS).min(ttl),
+            capacity: RESOLUTION_CACHE_MAX_ENTRIES,
+            cache: Mutex::new(Entries::default()),
+            pending: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            stale_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Keep at most `capacity` names
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Cache unregistered names for `negative_ttl`
+    pub fn with_negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+
+    /// Network backend used for resolutions
+    pub fn client(&self) -> &N {
+        &self.client
+    }
+
+    /// How long resolutions are kept
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// How long unregistered names are remembered
+    pub fn negative_ttl(&self) -> Duration {
+        self.negative_ttl
+    }
+
+    /// Hit and miss counters
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.lock_cache().resolutions.len(),
+        }
+    }
+
+    /// Most recently used names with a cached resolution, newest first
+    pub(crate) fn hot_names(&self, limit: usize) -> Vec<String> {
+        let cache = self.lock_cache();
+        cache
+            .recency
+            .values()
+            .rev()
+            .filter(|key| {
+                matches!(
+                    cache.resolutions.get(*key).map(|cached| &cached.outcome),
+                    Some(Outcome::Resolved(_))
+                )
+            })
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Cached resolution younger than the TTL, without counting a hit
+    pub(crate) fn peek(&self, key: &str) -> Option<Arc<ResolvedName>> {
+        let cache = self.lock_cache();
+        let cached = cache.resolutions.get(key)?;
+        match &cached.outcome {
+            Outcome::Resolved(resolved) if cached.resolved_at.elapsed() < self.ttl => {
+                Some(resolved.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Drop all cached resolutions
+    pub async fn clear(&self) {
+        *self.lock_cache() = Entries::default();
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Stale resolutions are refreshed on a spawned task
+impl<N: NameNetwork + 'static> ResolutionCache<N> {
+    /// Resolve a name, reusing a cached resolution younger than the TTL
+    ///
+    /// A stale resolution is returned at once and refreshed in the
+    /// background.
+    pub async fn resolve(self: &Arc<Self>, name: &DomainName) -> SharedResult<Arc<ResolvedName>> {
+        if self.ttl.is_zero() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return self.lookup(name).await.map(Arc::new).map_err(Arc::new);
+        }
+
+        let key = name.to_string();
+        if let Some(result) = self.from_cache(name, &key) {
+            return result;
+        }
+
+        // Concurrent misses join the first one's lookup and share its outcome
+        let lookup = {
+            let mut pending = self.lock_pending();
+            match pending.get(&key) {
+                Some(lookup) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    lookup.clone()
+                }
+                None => {
+                    // A lookup may have finished since the cache was checked
+                    if let Some(Cached::Fresh(outcome)) = self.cached(&key) {
+                        self.hits.fetch_add(1, Ordering::Relaxed);
+                        return outcome.into_result();
+                    }
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    self.start_lookup(&mut pending, name, &key)
+                }
+            }
+        };
+        lookup.await
+    }
+
+    /// Look a name up again and replace its cached resolution
+    ///
+    /// The cached resolution is only replaced once the new lookup has been
+    /// verified; if it fails, the old one stays in place.
+    pub async fn refresh(self: &Arc<Self>, name: &DomainName) -> SharedResult<Arc<ResolvedName>> {
+        if self.ttl.is_zero() {
+            return self.lookup(name).await.map(Arc::new).map_err(Arc::new);
+        }
+
+        // A lookup already in flight may have read the registers before
+        // they changed; wait for it, then join or start a new one
+        let key = name.to_string();
+        let running = self.lock_pending().get(&key).cloned();
+        if let Some(running) = running {
+            let _ = running.await;
+        }
+
+        let lookup = {
+            let mut pending = self.lock_pending();
+            match pending.get(&key) {
+                Some(lookup) => lookup.clone(),
+                None => self.start_lookup(&mut pending, name, &key),
+            }
+        };
+        lookup.await
+    }
+
+    /// Answer from the cache, refreshing stale resolutions in the background
+    fn from_cache(
+        self: &Arc<Self>,
+        name: &DomainName,
+        key: &str,
+    ) -> Option<SharedResult<Arc<ResolvedName>>> {
+        let cached = self.cached(key)?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+
+        match cached {
+            Cached::Fresh(outcome) => {
+                tracing::debug!("Cache hit for '{}'", key);
+                Some(outcome.into_result())
+            }
+            Cached::Stale(resolved) => {
+                tracing::debug!("Stale cache hit for '{}', refreshing", key);
+                self.stale_hits.fetch_add(1, Ordering::Relaxed);
+                self.revalidate(name.clone(), key.to_string());
+                Some(Ok(resolved))
+            }
+        }
+    }
+
+    /// Cached outcome for a name, unless it has expired
+    fn cached(&self, key: &str) -> Option<Cached> {
+        let mut cache = self.lock_cache();
+        let cached = cache.get(key)?;
+        let age = cached.resolved_at.elapsed();
+
+        match &cached.outcome {
+            Outcome::NotFound(_) if age < self.negative_ttl => {
+                Some(Cached::Fresh(cached.outcome.clone()))
+            }
+            Outcome::Resolved(_) if age < self.ttl => Some(Cached::Fresh(cached.outcome.clone())),
+            Outcome::Resolved(resolved) if age < self.ttl * 2 => {
+                Some(Cached::Stale(resolved.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Refresh a name in the background unless a lookup is already running
+    fn revalidate(self: &Arc<Self>, name: DomainName, key: String) {
+        let lookup = {
+            let mut pending = self.lock_pending();
+            if pending.contains_key(&key) {
+                return;
+            }
+            self.start_lookup(&mut pending, &name, &key)
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = lookup.await {
+                // Keep serving the stale resolution until it runs out
+                tracing::warn!("Failed to refresh '{}': {}", key, e);
+            }
+        });
+    }
+
+    /// Register a lookup for a name that callers can join until it finishes
+    ///
+    /// The outcome is cached before the lookup leaves `pending`, so a caller
+    /// that finds neither sees the new cache entry.
+    fn start_lookup(
+        self: &Arc<Self>,
+        pending: &mut HashMap<String, PendingLookup>,
+        name: &DomainName,
+        key: &str,
+    ) -> PendingLookup {
+        let cache = self.clone();
+        let (name, owned_key) = (name.clone(), key.to_string());
+        let lookup = async move {
+            let result = cache.fetch(&name, &owned_key).await;
+            cache.lock_pending().remove(&owned_key);
+            result
+        }
+        .boxed()
+        .shared();
+
+        pending.insert(key.to_string(), lookup.clone());
+        lookup
+    }
+
+    /// Look a name up on the network and cache the outcome
+    async fn fetch(&self, name: &DomainName, key: &str) -> SharedResult<Arc<ResolvedName>> {
+        let outcome = match self.lookup(name).await {
+            Ok(resolved) => Outcome::Resolved(Arc::new(resolved)),
+            Err(AntnsError::RegisterNotFound(domain)) if !self.negative_ttl.is_zero() => {
+                Outcome::NotFound(domain)
+            }
+            Err(e) => return Err(Arc::new(e)),
+        };
+
+        self.lock_cache()
+            .insert(key.to_string(), outcome.clone(), self.capacity);
+        outcome.into_result()
+    }
+
+    async fn lookup(&self, name: &DomainName) -> Result<ResolvedName> {
+        Resolver::with_options(&self.client, self.options.clone())
+            .resolve_name(name)
+            .await
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<String, PendingLookup>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{MemoryNetwork, MemoryPayment};
+    use crate::register::domain_register_key;
+    use crate::testing::{register, set_target};
+
+    fn name(name: &str) -> DomainName {
+        DomainName::parse(name).unwrap()
+    }
+
+    fn cache(network: MemoryNetwork, ttl: Duration) -> Arc<ResolutionCache<MemoryNetwork>> {
+        Arc::new(ResolutionCache::new(network, Default::default(), ttl))
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let network = MemoryNetwork::new();
+        for domain in ["one.ant", "two.ant", "three.ant"] {
+            register(&network, domain, &"ab".repeat(32)).await;
+        }
+        let cache = Arc::new(
+            ResolutionCache::new(network, Default::default(), Duration::from_secs(60))
+                .with_capacity(2),
+        );
+
+        cache.resolve(&name("one.ant")).await.unwrap();
+        cache.resolve(&name("two.ant")).await.unwrap();
+        // Using "one" makes "two" the least recently used
+        cache.resolve(&name("one.ant")).await.unwrap();
+        cache.resolve(&name("three.ant")).await.unwrap();
+        assert_eq!(cache.stats().entries, 2);
+
+        cache.resolve(&name("one.ant")).await.unwrap();
+        cache.resolve(&name("two.ant")).await.unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 4));
+    }
+
+    #[tokio::test]
+    async fn test_caches_unregistered_names_briefly() {
+        let network = MemoryNetwork::new();
+        let cache = Arc::new(
+            ResolutionCache::new(network, Default::default(), Duration::from_secs(60))
+                .with_negative_ttl(Duration::from_millis(100)),
+        );
+
+        for _ in 0..2 {
+            let result = cache.resolve(&name("later.ant")).await;
+            assert!(result.unwrap_err().is_not_found());
+        }
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+
+        // Registered after the negative entry expires
+        register(cache.client(), "later.ant", &"ab".repeat(32)).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let resolved = cache.resolve(&name("later.ant")).await.unwrap();
+        assert_eq!(resolved.target(), Some("ab".repeat(32).as_str()));
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalesces_concurrent_misses() {
+        let network = MemoryNetwork::new();
+        register(&network, "busy.ant", &"ab".repeat(32)).await;
+        let cache = cache(network, Duration::from_secs(60));
+
+        let domain = name("busy.ant");
+        let results = futures::future::join_all((0..8).map(|_| cache.resolve(&domain))).await;
+        assert!(results.iter().all(Result::is_ok));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (7, 1));
+    }
+
+    #[tokio::test]
+    async fn test_coalesces_concurrent_failures() {
+        let network = MemoryNetwork::new().with_latency(Duration::from_millis(20));
+        // An owner document nobody can fetch fails every lookup
+        let key = domain_register_key("broken.ant").unwrap();
+        network
+            .register_create(&key, [9u8; 32], MemoryPayment::unlimited())
+            .await
+            .unwrap();
+        let cache = cache(network, Duration::from_secs(60));
+
+        let domain = name("broken.ant");
+        let results = futures::future::join_all((0..8).map(|_| cache.resolve(&domain))).await;
+        assert!(results.iter().all(|result| matches!(
+            result.as_ref().map_err(Arc::as_ref),
+            Err(AntnsError::OwnerDocumentMissing { .. })
+        )));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (7, 1));
+
+        // Failures are shared, not cached
+        assert!(cache.resolve(&domain).await.is_err());
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_serves_stale_while_revalidating() {
+        let network = MemoryNetwork::new();
+        let registration = register(&network, "moving.ant", &"ab".repeat(32)).await;
+        let cache = cache(network, Duration::from_millis(200));
+        let domain = name("moving.ant");
+
+        cache.resolve(&domain).await.unwrap();
+        set_target(
+            cache.client(),
+            &registration,
+            "moving.ant",
+            &"cd".repeat(32),
+        )
+        .await;
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        // The stale target is served at once and refreshed in the background
+        let stale = cache.resolve(&domain).await.unwrap();
+        assert_eq!(stale.target(), Some("ab".repeat(32).as_str()));
+        assert_eq!(cache.stats().stale_hits, 1);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let fresh = cache.resolve(&domain).await.unwrap();
+        assert_eq!(fresh.target(), Some("cd".repeat(32).as_str()));
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_caching() {
+        let network = MemoryNetwork::new();
+        register(&network, "live.ant", &"ab".repeat(32)).await;
+        let cache = cache(network, Duration::ZERO);
+
+        for _ in 0..2 {
+            cache.resolve(&name("live.ant")).await.unwrap();
+        }
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                misses: 2,
+                ..Default::default()
+            }
+        );
+    }
+}
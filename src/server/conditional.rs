@@ -0,0 +1,566 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Conditional and range requests (RFC 9110 §13, §14)
+//!
+//! [`respond`] applies a request's `If-None-Match`, `If-Modified-Since`,
+//! `Range` and `If-Range` headers to a complete `200 OK` response, whether
+//! it came from the content cache, the Autonomi network or an upstream that
+//! ignored them. Ranges are cut out of the streamed body as it passes
+//! through, so seeking in a large file does not buffer it.
+
+use crate::server::http::{full, BoxError, ProxyBody};
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, SizeHint};
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_ENCODING,
+    CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use hyper::http::request::Parts;
+use hyper::{Method, Response, StatusCode};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::SystemTime;
+
+/// Most ranges served in one response; requests for more get the full content
+const MAX_RANGES: usize = 32;
+
+/// Answer to a `Range` header for content of a known length
+#[derive(Debug, PartialEq, Eq)]
+enum Ranges {
+    /// No usable range: serve the full content
+    Full,
+    /// None of the ranges overlaps the content
+    Unsatisfiable,
+    /// Sorted, non-overlapping byte ranges
+    Satisfiable(Vec<Range<u64>>),
+}
+
+/// Apply the request's conditional and range headers to a `200 OK` response
+///
+/// Returns `304 Not Modified` if the client's copy is current, `206 Partial
+/// Content` for satisfiable ranges and `416 Range Not Satisfiable` for
+/// others. Any other response is returned unchanged.
+pub(crate) fn respond(parts: &Parts, response: Response<ProxyBody>) -> Response<ProxyBody> {
+    if response.status() != StatusCode::OK
+        || (parts.method != Method::GET && parts.method != Method::HEAD)
+    {
+        return response;
+    }
+
+    if not_modified(&parts.headers, response.headers()) {
+        return without_content(response, StatusCode::NOT_MODIFIED);
+    }
+
+    let Some(length) = content_length(response.headers()) else {
+        return response;
+    };
+    let mut response = response;
+    response
+        .headers_mut()
+        .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+    let range = parts
+        .headers
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok());
+    let Some(range) = range.filter(|_| parts.method == Method::GET) else {
+        return response;
+    };
+    if !if_range_holds(&parts.headers, response.headers()) {
+        return response;
+    }
+
+    match parse_ranges(range, length) {
+        Ranges::Full => response,
+        Ranges::Unsatisfiable => {
+            let mut response = without_content(response, StatusCode::RANGE_NOT_SATISFIABLE);
+            let headers = response.headers_mut();
+            headers.insert(CONTENT_RANGE, header(format!("bytes */{}", length)));
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(0));
+            response
+        }
+        Ranges::Satisfiable(ranges) => partial(response, ranges, length),
+    }
+}
+
+/// Whether the client's cached copy, described by its validators, is current
+///
+/// `If-Modified-Since` only counts without `If-None-Match` (RFC 9110 §13.2.2).
+fn not_modified(request: &HeaderMap, response: &HeaderMap) -> bool {
+    if request.contains_key(IF_NONE_MATCH) {
+        let etag = response.get(ETAG).and_then(|value| value.to_str().ok());
+        return request
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .any(|tag| tag == "*" || etag.is_some_and(|etag| weak_eq(tag, etag)));
+    }
+
+    match (
+        date(request, IF_MODIFIED_SINCE),
+        date(response, LAST_MODIFIED),
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Whether `If-Range` allows serving a range: it must name the current
+/// content by strong entity tag or exact modification date
+fn if_range_holds(request: &HeaderMap, response: &HeaderMap) -> bool {
+    let Some(condition) = request.get(IF_RANGE) else {
+        return true;
+    };
+    let Ok(condition) = condition.to_str() else {
+        return false;
+    };
+
+    if condition.starts_with('"') || condition.starts_with("W/") {
+        let etag = response.get(ETAG).and_then(|value| value.to_str().ok());
+        return !condition.starts_with("W/")
+            && etag.is_some_and(|etag| !etag.starts_with("W/") && etag == condition);
+    }
+    match (
+        httpdate::parse_http_date(condition),
+        date(response, LAST_MODIFIED),
+    ) {
+        (Ok(since), Some(modified)) => modified == since,
+        _ => false,
+    }
+}
+
+/// Weak entity tag comparison (RFC 9110 §8.8.3.2)
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+/// Date in an HTTP-date header
+fn date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    let value = headers.get(name)?.to_str().ok()?;
+    httpdate::parse_http_date(value).ok()
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+fn header(value: String) -> HeaderValue {
+    HeaderValue::try_from(value).expect("generated header values are valid")
+}
+
+/// The response's status and validators without its content
+fn without_content(response: Response<ProxyBody>, status: StatusCode) -> Response<ProxyBody> {
+    let (mut head, _) = response.into_parts();
+    head.status = status;
+    for name in [
+        CONTENT_TYPE,
+        CONTENT_LENGTH,
+        CONTENT_ENCODING,
+        CONTENT_LANGUAGE,
+        CONTENT_DISPOSITION,
+        CONTENT_RANGE,
+    ] {
+        head.headers.remove(name);
+    }
+    Response::from_parts(head, full(Bytes::new()))
+}
+
+/// `206 Partial Content` with one range, or several as `multipart/byteranges`
+fn partial(
+    response: Response<ProxyBody>,
+    ranges: Vec<Range<u64>>,
+    length: u64,
+) -> Response<ProxyBody> {
+    let (mut head, body) = response.into_parts();
+    head.status = StatusCode::PARTIAL_CONTENT;
+    let content_range =
+        |range: &Range<u64>| format!("bytes {}-{}/{}", range.start, range.end - 1, length);
+
+    if let [range] = &ranges[..] {
+        head.headers
+            .insert(CONTENT_RANGE, header(content_range(range)));
+        head.headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(range.end - range.start));
+        let body = RangeBody::new(body, vec![(range.clone(), None)], None);
+        return Response::from_parts(head, body.boxed_unsync());
+    }
+
+    let boundary = format!(
+        "{:016x}{:016x}",
+        rand::random::<u64>(),
+        rand::random::<u64>()
+    );
+    let content_type = head.headers.remove(CONTENT_TYPE);
+    let parts: Vec<(Range<u64>, Option<Bytes>)> = ranges
+        .into_iter()
+        .map(|range| {
+            let mut part = format!("\r\n--{}\r\n", boundary);
+            if let Some(content_type) = content_type.as_ref().and_then(|v| v.to_str().ok()) {
+                part.push_str(&format!("Content-Type: {}\r\n", content_type));
+            }
+            part.push_str(&format!("Content-Range: {}\r\n\r\n", content_range(&range)));
+            (range, Some(Bytes::from(part)))
+        })
+        .collect();
+    let end = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+
+    let size = parts
+        .iter()
+        .map(|(range, part)| range.end - range.start + part.as_ref().map_or(0, |p| p.len() as u64))
+        .sum::<u64>()
+        + end.len() as u64;
+    head.headers.insert(
+        CONTENT_TYPE,
+        header(format!("multipart/byteranges; boundary={}", boundary)),
+    );
+    head.headers.insert(CONTENT_LENGTH, HeaderValue::from(size));
+    Response::from_parts(head, RangeBody::new(body, parts, Some(end)).boxed_unsync())
+}
+
+/// Parse a `Range` header (RFC 9110 §14.1.2) for content of `length` bytes
+///
+/// Invalid headers and other units are ignored, as the RFC allows.
+/// Overlapping and adjacent ranges are merged.
+fn parse_ranges(header: &str, length: u64) -> Ranges {
+    let Some((unit, specs)) = header.split_once('=') else {
+        return Ranges::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Ranges::Full;
+    }
+
+    let number = |s: &str| {
+        let s = s.trim();
+        (!s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| s.parse::<u64>().ok())
+            .flatten()
+    };
+
+    let mut ranges = Vec::new();
+    let mut specs_seen = 0;
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        specs_seen += 1;
+        if specs_seen > MAX_RANGES {
+            return Ranges::Full;
+        }
+        let Some((first, last)) = spec.split_once('-') else {
+            return Ranges::Full;
+        };
+
+        let range = if first.trim().is_empty() {
+            // Suffix: the last `n` bytes
+            let Some(n) = number(last) else {
+                return Ranges::Full;
+            };
+            length.saturating_sub(n)..length
+        } else {
+            let Some(start) = number(first) else {
+                return Ranges::Full;
+            };
+            let end = if last.trim().is_empty() {
+                length
+            } else {
+                match number(last) {
+                    Some(last) if last >= start => last.saturating_add(1).min(length),
+                    _ => return Ranges::Full,
+                }
+            };
+            start..end.max(start)
+        };
+        if range.start < length && !range.is_empty() {
+            ranges.push(range);
+        }
+    }
+
+    if specs_seen == 0 {
+        return Ranges::Full;
+    }
+    if ranges.is_empty() {
+        return Ranges::Unsatisfiable;
+    }
+
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    Ranges::Satisfiable(merged)
+}
+
+/// Body yielding only the given byte ranges of the inner body, each after
+/// an optional part header, followed by an optional trailer
+///
+/// Bytes before a range are read and dropped; the inner body is not read
+/// past the last range.
+struct RangeBody {
+    inner: ProxyBody,
+    /// Offset in the inner body of the next byte read
+    position: u64,
+    /// Data read past the end of the previous range
+    leftover: Option<Bytes>,
+    parts: VecDeque<(Range<u64>, Option<Bytes>)>,
+    trailer: Option<Bytes>,
+    remaining: u64,
+}
+
+impl RangeBody {
+    fn new(
+        inner: ProxyBody,
+        parts: Vec<(Range<u64>, Option<Bytes>)>,
+        trailer: Option<Bytes>,
+    ) -> Self {
+        let remaining = parts
+            .iter()
+            .map(|(range, header)| {
+                range.end - range.start + header.as_ref().map_or(0, |h| h.len() as u64)
+            })
+            .sum::<u64>()
+            + trailer.as_ref().map_or(0, |t| t.len() as u64);
+        Self {
+            inner,
+            position: 0,
+            leftover: None,
+            parts: parts.into(),
+            trailer,
+            remaining,
+        }
+    }
+
+    fn emit(&mut self, data: Bytes) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        self.remaining -= data.len() as u64;
+        Poll::Ready(Some(Ok(Frame::data(data))))
+    }
+}
+
+impl Body for RangeBody {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        let this = &mut *self;
+        loop {
+            let Some((range, part_header)) = this.parts.front_mut() else {
+                return match this.trailer.take() {
+                    Some(trailer) => this.emit(trailer),
+                    None => Poll::Ready(None),
+                };
+            };
+            if let Some(part_header) = part_header.take() {
+                return this.emit(part_header);
+            }
+            let range = range.clone();
+
+            let chunk = match this.leftover.take() {
+                Some(chunk) => chunk,
+                None => match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                    Some(Ok(frame)) => match frame.into_data() {
+                        Ok(data) => data,
+                        // Trailers of the full content do not apply to a range
+                        Err(_) => continue,
+                    },
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    None => {
+                        return Poll::Ready(Some(Err(
+                            "content ended before the requested range".into()
+                        )))
+                    }
+                },
+            };
+
+            let chunk_start = this.position;
+            let chunk_end = chunk_start + chunk.len() as u64;
+            if chunk_end <= range.start {
+                this.position = chunk_end;
+                continue;
+            }
+
+            let from = (range.start.saturating_sub(chunk_start)) as usize;
+            let to = (range.end.min(chunk_end) - chunk_start) as usize;
+            if range.end <= chunk_end {
+                this.parts.pop_front();
+                if range.end < chunk_end {
+                    // The rest may belong to the next range
+                    this.leftover = Some(chunk.slice(to..));
+                }
+                this.position = range.end;
+            } else {
+                this.position = chunk_end;
+            }
+            return this.emit(chunk.slice(from..to));
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Request;
+
+    fn satisfiable(ranges: &[(u64, u64)]) -> Ranges {
+        Ranges::Satisfiable(ranges.iter().map(|&(start, end)| start..end).collect())
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges("bytes=0-9", 100), satisfiable(&[(0, 10)]));
+        assert_eq!(parse_ranges("bytes=90-", 100), satisfiable(&[(90, 100)]));
+        assert_eq!(parse_ranges("bytes=-10", 100), satisfiable(&[(90, 100)]));
+        assert_eq!(parse_ranges("bytes=-500", 100), satisfiable(&[(0, 100)]));
+        assert_eq!(parse_ranges("bytes=95-200", 100), satisfiable(&[(95, 100)]));
+        assert_eq!(
+            parse_ranges("bytes=50-59, 0-9,5-14 ,60-60", 100),
+            satisfiable(&[(0, 15), (50, 61)])
+        );
+        assert_eq!(parse_ranges("bytes=100-", 100), Ranges::Unsatisfiable);
+        assert_eq!(parse_ranges("bytes=-0", 100), Ranges::Unsatisfiable);
+        assert_eq!(parse_ranges("bytes=0-", 0), Ranges::Unsatisfiable);
+        for ignored in [
+            "items=0-9",
+            "bytes=",
+            "bytes=9-0",
+            "bytes=a-b",
+            "bytes=+1-2",
+            "0-9",
+        ] {
+            assert_eq!(parse_ranges(ignored, 100), Ranges::Full, "{}", ignored);
+        }
+        let many = format!("bytes={}", vec!["0-0"; MAX_RANGES + 1].join(","));
+        assert_eq!(parse_ranges(&many, 100), Ranges::Full);
+    }
+
+    /// `respond` for a request with `headers` to 26 bytes of content,
+    /// streamed in chunks of 4
+    async fn respond_to(method: Method, headers: &[(&str, &str)]) -> Response<Bytes> {
+        let mut request = Request::builder().method(method);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let (parts, _) = request.body(()).unwrap().into_parts();
+
+        let chunks: Vec<Result<Frame<Bytes>, BoxError>> = b"abcdefghijklmnopqrstuvwxyz"
+            .chunks(4)
+            .map(|chunk| Ok(Frame::data(Bytes::copy_from_slice(chunk))))
+            .collect();
+        let body = http_body_util::StreamBody::new(futures::stream::iter(chunks));
+        let response = Response::builder()
+            .header(CONTENT_TYPE, "text/plain")
+            .header(CONTENT_LENGTH, 26)
+            .header(ETAG, "\"v1\"")
+            .header(LAST_MODIFIED, "Sun, 06 Nov 1994 08:49:37 GMT")
+            .body(body.boxed_unsync())
+            .unwrap();
+
+        let response = respond(&parts, response);
+        let (head, body) = response.into_parts();
+        let body = body.collect().await.unwrap().to_bytes();
+        Response::from_parts(head, body)
+    }
+
+    #[tokio::test]
+    async fn test_respond_to_ranges() {
+        let response = respond_to(Method::GET, &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
+        assert_eq!(response.body().len(), 26);
+
+        let response = respond_to(Method::GET, &[("range", "bytes=2-9")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 2-9/26");
+        assert_eq!(response.headers()[CONTENT_LENGTH], "8");
+        assert_eq!(response.body(), "cdefghij");
+
+        let response = respond_to(Method::GET, &[("range", "bytes=-3,0-1")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response.headers()[CONTENT_TYPE].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let expected = format!(
+            "\r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/26\r\n\r\nab\
+             \r\n--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 23-25/26\r\n\r\nxyz\
+             \r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(response.body(), expected.as_str());
+        assert_eq!(
+            response.headers()[CONTENT_LENGTH],
+            expected.len().to_string().as_str()
+        );
+
+        let response = respond_to(Method::GET, &[("range", "bytes=26-")]).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */26");
+        assert!(response.body().is_empty());
+
+        // Ranges only apply while the client's copy is current
+        let response = respond_to(
+            Method::GET,
+            &[("range", "bytes=0-1"), ("if-range", "\"v1\"")],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        for stale in ["\"v0\"", "W/\"v1\"", "Mon, 07 Nov 1994 08:49:37 GMT"] {
+            let response =
+                respond_to(Method::GET, &[("range", "bytes=0-1"), ("if-range", stale)]).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", stale);
+        }
+
+        let response = respond_to(Method::HEAD, &[("range", "bytes=0-1")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_respond_to_conditionals() {
+        for (name, value) in [
+            ("if-none-match", "\"v1\""),
+            ("if-none-match", "\"v0\", W/\"v1\""),
+            ("if-none-match", "*"),
+            ("if-modified-since", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ("if-modified-since", "Mon, 07 Nov 1994 08:49:37 GMT"),
+        ] {
+            let response = respond_to(Method::GET, &[(name, value)]).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", value);
+            assert_eq!(response.headers()[ETAG], "\"v1\"");
+            assert!(!response.headers().contains_key(CONTENT_LENGTH));
+            assert!(response.body().is_empty());
+        }
+
+        for headers in [
+            &[("if-none-match", "\"v0\"")][..],
+            &[("if-modified-since", "Sat, 05 Nov 1994 08:49:37 GMT")],
+            &[("if-modified-since", "not a date")],
+            // If-None-Match takes precedence
+            &[
+                ("if-none-match", "\"v0\""),
+                ("if-modified-since", "Mon, 07 Nov 1994 08:49:37 GMT"),
+            ],
+        ] {
+            let response = respond_to(Method::GET, headers).await;
+            assert_eq!(response.status(), StatusCode::OK, "{:?}", headers);
+        }
+
+        let response = respond_to(Method::POST, &[("if-none-match", "*")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Native content serving from the Autonomi network
+//!
+//! Instead of forwarding to an external gateway, the proxy can fetch a
+//! name's `ANT` target itself. A target that is a public archive is served
+//! like a directory: URL paths map to archive entries and directories serve
+//! their `index.html`. Any other target is public data served at `/`.
+//!
+//! Uploading a directory usually stores its files under the directory's own
+//! name (`site/index.html`); when every entry shares one top-level directory
+//! it is treated as the root.
+//!
+//! A target is a content address, so what it holds never changes: parsed
+//! archive listings are kept in a [`TargetCache`] and every later request
+//! only downloads the file it asks for.
+//!
+//! Files are downloaded whole before they are sent, so files larger than
+//! [`NATIVE_CONTENT_MAX_FILE_BYTES`] are refused; archive listings record
+//! each file's size, so those are refused without downloading them.
+
+use crate::constants::{ARCHIVE_CACHE_MAX_ENTRIES, NATIVE_CONTENT_MAX_FILE_BYTES};
+use crate::network::{ArchiveEntry, NameNetwork, PublicData};
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// File served for directory paths
+const INDEX_FILE: &str = "index.html";
+
+/// Content found at a path of a target
+#[derive(Debug)]
+pub(crate) enum Content {
+    /// File contents and their media type
+    File {
+        data: Bytes,
+        content_type: &'static str,
+    },
+    /// Directory requested without a trailing slash
+    Directory,
+    /// File of `size` bytes, more than the proxy holds in memory
+    TooLarge { size: u64 },
+    /// Nothing at this path
+    NotFound,
+}
+
+/// What a target holds
+#[derive(Clone)]
+enum Target {
+    /// Archive files by path, see [`archive_files`]
+    Archive(Arc<HashMap<String, ArchiveEntry>>),
+    /// A single file of `size` bytes
+    File { size: u64 },
+}
+
+/// What targets were found to hold, by address
+pub(crate) struct TargetCache {
+    targets: Mutex<HashMap<[u8; 32], Target>>,
+    capacity: usize,
+    /// Largest file served, in bytes
+    max_file_bytes: u64,
+}
+
+impl Default for TargetCache {
+    fn default() -> Self {
+        Self::new(ARCHIVE_CACHE_MAX_ENTRIES)
+    }
+}
+
+impl TargetCache {
+    /// Remember at most `capacity` targets
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            targets: Mutex::new(HashMap::new()),
+            capacity: capacity.max(1),
+            max_file_bytes: NATIVE_CONTENT_MAX_FILE_BYTES,
+        }
+    }
+
+    /// Refuse files larger than `bytes`
+    pub(crate) fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    fn get(&self, address: &[u8; 32]) -> Option<Target> {
+        self.lock().get(address).cloned()
+    }
+
+    fn insert(&self, address: [u8; 32], target: Target) {
+        let mut targets = self.lock();
+        // Any entry can be fetched again; drop one to make room
+        if targets.len() >= self.capacity && !targets.contains_key(&address) {
+            if let Some(evicted) = targets.keys().next().copied() {
+                targets.remove(&evicted);
+            }
+        }
+        targets.insert(address, target);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<[u8; 32], Target>> {
+        self.targets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Fetch the content at URL `path` of the target with address `target`
+///
+/// The target itself is downloaded once to learn what it holds; later
+/// requests find that in `targets`.
+pub(crate) async fn fetch<N: NameNetwork>(
+    network: &N,
+    targets: &TargetCache,
+    target: &str,
+    path: &str,
+) -> Result<Content> {
+    let address: [u8; 32] = hex::decode(target)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .with_context(|| format!("Target '{}' is not a data address", target))?;
+
+    let Some(path) = decode_path(path) else {
+        return Ok(Content::NotFound);
+    };
+
+    let max_bytes = targets.max_file_bytes;
+    let files = match targets.get(&address) {
+        Some(Target::Archive(files)) => files,
+        Some(Target::File { size }) if size > max_bytes && path.is_empty() => {
+            return Ok(Content::TooLarge { size })
+        }
+        Some(Target::File { .. }) => {
+            return single_file(network, address, &path, None, max_bytes).await
+        }
+        None => match network.archive_get_public(address).await? {
+            PublicData::Archive(entries) => {
+                let files = Arc::new(archive_files(entries));
+                targets.insert(address, Target::Archive(files.clone()));
+                files
+            }
+            PublicData::File(data) => {
+                let size = data.len() as u64;
+                targets.insert(address, Target::File { size });
+                return single_file(network, address, &path, Some(data), max_bytes).await;
+            }
+        },
+    };
+
+    let file = if path.is_empty() || path.ends_with('/') {
+        format!("{}{}", path, INDEX_FILE)
+    } else if files.contains_key(&path) {
+        path
+    } else if files.contains_key(&format!("{}/{}", path, INDEX_FILE)) {
+        return Ok(Content::Directory);
+    } else {
+        return Ok(Content::NotFound);
+    };
+
+    let Some(entry) = files.get(&file) else {
+        return Ok(Content::NotFound);
+    };
+    if entry.size > max_bytes {
+        return Ok(Content::TooLarge { size: entry.size });
+    }
+
+    // The listed size is the uploader's claim; check what arrived
+    let data = network.data_get_public(entry.address).await?;
+    if data.len() as u64 > max_bytes {
+        return Ok(Content::TooLarge {
+            size: data.len() as u64,
+        });
+    }
+    Ok(Content::File {
+        data,
+        content_type: content_type(&file),
+    })
+}
+
+/// Content at `path` of a target holding a single file
+///
+/// `data` is the file if it was just downloaded.
+async fn single_file<N: NameNetwork>(
+    network: &N,
+    address: [u8; 32],
+    path: &str,
+    data: Option<Bytes>,
+    max_bytes: u64,
+) -> Result<Content> {
+    // A single file only exists at the root
+    if !path.is_empty() {
+        return Ok(Content::NotFound);
+    }
+    let data = match data {
+        Some(data) => data,
+        None => network.data_get_public(address).await?,
+    };
+    if data.len() as u64 > max_bytes {
+        return Ok(Content::TooLarge {
+            size: data.len() as u64,
+        });
+    }
+    Ok(Content::File {
+        content_type: sniff_content_type(&data),
+        data,
+    })
+}
+
+/// Archive entries by path, relative to the archive's root directory
+fn archive_files(entries: Vec<ArchiveEntry>) -> HashMap<String, ArchiveEntry> {
+    let paths: Vec<String> = entries
+        .iter()
+        .map(|entry| entry.path.trim_start_matches('/').to_string())
+        .collect();
+
+    // Strip a top-level directory shared by every entry
+    let root = paths
+        .first()
+        .and_then(|path| path.split_once('/'))
+        .map(|(root, _)| format!("{}/", root))
+        .filter(|root| paths.iter().all(|path| path.starts_with(root.as_str())));
+
+    paths
+        .into_iter()
+        .zip(entries)
+        .map(|(path, entry)| {
+            let path = match &root {
+                Some(root) => path[root.len()..].to_string(),
+                None => path,
+            };
+            (path, entry)
+        })
+        .collect()
+}
+
+/// Percent-decode a URL path into an archive path without the leading `/`
+///
+/// Returns `None` for paths that are not valid UTF-8 or try to leave the
+/// archive with `.` or `..` segments.
+fn decode_path(path: &str) -> Option<String> {
+    let raw = path.trim_start_matches('/').as_bytes();
+    let mut decoded = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'%' {
+            let hex = std::str::from_utf8(raw.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(raw[i]);
+            i += 1;
+        }
+    }
+
+    let decoded = String::from_utf8(decoded).ok()?;
+    if decoded
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return None;
+    }
+    Some(decoded)
+}
+
+/// Media type of a file, inferred from its extension
+pub(crate) fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Media type of a file without a name: HTML documents are recognised so a
+/// single uploaded page renders, anything else is downloaded
+fn sniff_content_type(data: &[u8]) -> &'static str {
+    let start = data.trim_ascii_start();
+    let html = [&b"<!doctype html"[..], b"<html"].iter().any(|prefix| {
+        start
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    });
+
+    if html {
+        "text/html; charset=utf-8"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MemoryNetwork;
+
+    async fn get(network: &MemoryNetwork, target: &str, path: &str) -> Content {
+        fetch(network, &TargetCache::default(), target, path)
+            .await
+            .unwrap()
+    }
+
+    fn assert_file(content: Content, expected: &str, expected_type: &str) {
+        match content {
+            Content::File { data, content_type } => {
+                assert_eq!(data, expected);
+                assert_eq!(content_type, expected_type);
+            }
+            other => panic!("expected {}, got {:?}", expected, other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_archives_and_data() {
+        let network = MemoryNetwork::new();
+        let site = hex::encode(network.insert_archive(&[
+            ("site/index.html", b"home"),
+            ("site/css/style.css", b"body {}"),
+            ("site/docs/index.html", b"docs"),
+            ("site/my file.txt", b"spaces"),
+        ]));
+
+        let html = "text/html; charset=utf-8";
+        assert_file(get(&network, &site, "/").await, "home", html);
+        assert_file(get(&network, &site, "/docs/").await, "docs", html);
+        assert_file(
+            get(&network, &site, "/css/style.css").await,
+            "body {}",
+            "text/css; charset=utf-8",
+        );
+        assert_file(
+            get(&network, &site, "/my%20file.txt").await,
+            "spaces",
+            "text/plain; charset=utf-8",
+        );
+        assert!(matches!(
+            get(&network, &site, "/docs").await,
+            Content::Directory
+        ));
+        for missing in ["/missing.html", "/css/", "/../site/index.html", "/%ff"] {
+            assert!(
+                matches!(get(&network, &site, missing).await, Content::NotFound),
+                "{}",
+                missing
+            );
+        }
+
+        // Plain data is served at the root only
+        let page = hex::encode(network.insert_data(Bytes::from_static(b"  <!DOCTYPE html>")));
+        assert_file(get(&network, &page, "/").await, "  <!DOCTYPE html>", html);
+        assert!(matches!(
+            get(&network, &page, "/index.html").await,
+            Content::NotFound
+        ));
+        let blob = hex::encode(network.insert_data(Bytes::from_static(b"\x00\x01")));
+        assert_file(
+            get(&network, &blob, "/").await,
+            "\x00\x01",
+            "application/octet-stream",
+        );
+
+        let targets = TargetCache::default();
+        assert!(fetch(&network, &targets, &"00".repeat(32), "/")
+            .await
+            .is_err());
+        assert!(fetch(&network, &targets, "not-hex", "/").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_files_too_large_to_buffer() {
+        let network = MemoryNetwork::new();
+        let site = network.insert_archive(&[("index.html", b"home"), ("big.bin", b"0123456789")]);
+        let page = network.insert_data(Bytes::from_static(b"0123456789"));
+        let targets = TargetCache::default().with_max_file_bytes(4);
+
+        let content = fetch(&network, &targets, &hex::encode(site), "/");
+        assert_file(content.await.unwrap(), "home", "text/html; charset=utf-8");
+
+        // The second request for the page knows its size without downloading it
+        for (target, path) in [(site, "/big.bin"), (page, "/"), (page, "/")] {
+            let content = fetch(&network, &targets, &hex::encode(target), path);
+            assert!(
+                matches!(content.await.unwrap(), Content::TooLarge { size: 10 }),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remembers_what_targets_hold() {
+        let network = MemoryNetwork::new();
+        let site = network.insert_archive(&[("index.html", b"home"), ("a.css", b"a {}")]);
+        let page = network.insert_data(Bytes::from_static(b"page"));
+        let targets = TargetCache::new(2);
+
+        let content = fetch(&network, &targets, &hex::encode(site), "/a.css");
+        assert_file(content.await.unwrap(), "a {}", "text/css; charset=utf-8");
+        assert!(matches!(targets.get(&site), Some(Target::Archive(_))));
+        let content = fetch(&network, &targets, &hex::encode(site), "/");
+        assert_file(content.await.unwrap(), "home", "text/html; charset=utf-8");
+
+        let content = fetch(&network, &targets, &hex::encode(page), "/");
+        assert_file(content.await.unwrap(), "page", "application/octet-stream");
+        assert!(matches!(targets.get(&page), Some(Target::File { size: 4 })));
+        let content = fetch(&network, &targets, &hex::encode(page), "/page.txt");
+        assert!(matches!(content.await.unwrap(), Content::NotFound));
+
+        // Beyond capacity, an older target makes room
+        let other = network.insert_data(Bytes::from_static(b"other"));
+        fetch(&network, &targets, &hex::encode(other), "/")
+            .await
+            .unwrap();
+        assert_eq!(targets.lock().len(), 2);
+        assert!(targets.get(&other).is_some());
+    }
+}
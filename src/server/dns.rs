@@ -2,22 +2,371 @@
 // Licensed under GPL-3.0
 
 //! DNS resolver server for .ant and .autonomi domains
+//!
+//! Queries are answered from the verified records of the name (see
+//! [`crate::register::resolver`]):
+//!
+//! - A, AAAA, TXT, CNAME, MX and SRV queries return the matching records
+//! - a name with a CNAME record answers other types with the CNAME
+//! - an unregistered domain, or a subdomain that does not exist, is NXDOMAIN
+//! - an existing name without records of the queried type is NODATA; names
+//!   with records below them exist even without records of their own
+//!   (empty non-terminals, RFC 8020)
+//! - NXDOMAIN and NODATA answers carry a synthesized SOA record of the zone
+//!   in the authority section, so resolvers can cache them (RFC 2308)
+//! - a name with an `ANT` record but no address records answers A queries
+//!   with 127.0.0.1, so that browsers reach the local HTTP proxy
+//!
+//! Other names are NXDOMAIN, unless forwarding is enabled, in which case
+//! they are relayed to upstream resolvers (see [`crate::server::forward`]).
+//!
+//! Besides plain UDP/TCP the same handler can serve DNS-over-HTTPS
+//! ([`crate::server::doh`]) and DNS-over-TLS ([`crate::server::dot`]).
+//! Clients outside the access list ([`crate::server::acl`]) are refused on
+//! every transport.
 
+use crate::constants::DEFAULT_RECORD_TTL;
+use crate::network::NameNetwork;
+use crate::register::name::is_ant_name;
+use crate::register::record::{RecordData, RecordType as AntRecordType};
+use crate::server::acl::AccessList;
+use crate::server::cache::ResolutionCache;
+use crate::server::forward::Forwarder;
+use crate::server::tls::{load_server_config, DOH_ALPN, DOT_ALPN};
 use anyhow::{Context, Result};
-use hickory_proto::op::{Header, ResponseCode};
-use hickory_proto::rr::rdata::A;
-use hickory_proto::rr::{Name, RData, Record};
+use hickory_proto::op::{Header, Message, MessageType, OpCode, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA, CNAME, MX, SOA, SRV, TXT};
+use hickory_proto::rr::{LowerName, Name, RData, Record, RecordType};
 use hickory_server::authority::MessageResponseBuilder;
 use hickory_server::server::{Request, RequestHandler, ResponseHandler, ResponseInfo};
 use hickory_server::ServerFuture;
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Maximum length of a single TXT character-string
+const TXT_CHUNK_LENGTH: usize = 255;
+
+/// Refresh, retry and expire intervals (seconds) of synthesized SOA records
+///
+/// AntNS zones have no secondaries, so these only need to be plausible.
+const SOA_REFRESH: i32 = 3600;
+const SOA_RETRY: i32 = 600;
+const SOA_EXPIRE: i32 = 604_800;
+
+/// Response to a single query
+#[derive(Debug, Clone)]
+pub struct QueryResponse {
+    pub response_code: ResponseCode,
+    /// Whether the response comes from AntNS records rather than an upstream
+    pub authoritative: bool,
+    pub answers: Vec<Record>,
+    pub name_servers: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl QueryResponse {
+    fn authoritative(response_code: ResponseCode, answers: Vec<Record>) -> Self {
+        Self {
+            response_code,
+            authoritative: true,
+            answers,
+            name_servers: Vec::new(),
+            additionals: Vec::new(),
+        }
+    }
+
+    /// Authoritative NXDOMAIN or NODATA answer, with the zone's SOA if known
+    fn negative(response_code: ResponseCode, soa: Option<Record>) -> Self {
+        Self {
+            name_servers: soa.into_iter().collect(),
+            ..Self::authoritative(response_code, Vec::new())
+        }
+    }
+}
 
 /// DNS request handler for .ant and .autonomi domains
-#[derive(Clone)]
-struct AntDnsHandler;
+///
+/// Cloning is cheap; clones share the resolution cache, forwarder and
+/// access list.
+pub struct AntDnsHandler<N: NameNetwork> {
+    cache: Arc<ResolutionCache<N>>,
+    forwarder: Option<Arc<Forwarder>>,
+    access_list: Arc<AccessList>,
+}
+
+impl<N: NameNetwork> Clone for AntDnsHandler<N> {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+            forwarder: self.forwarder.clone(),
+            access_list: self.access_list.clone(),
+        }
+    }
+}
+
+impl<N: NameNetwork + 'static> AntDnsHandler<N> {
+    /// Handler answering loopback clients from names resolved through `cache`
+    pub fn new(cache: Arc<ResolutionCache<N>>) -> Self {
+        Self {
+            cache,
+            forwarder: None,
+            access_list: Arc::new(AccessList::loopback()),
+        }
+    }
+
+    /// Only answer clients in `access_list`
+    pub fn with_access_list(mut self, access_list: Arc<AccessList>) -> Self {
+        self.access_list = access_list;
+        self
+    }
+
+    /// Whether a client at `ip` may query this handler
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.access_list.allows(ip)
+    }
+
+    /// Relay queries for non-AntNS names through `forwarder`
+    pub fn with_forwarder(mut self, forwarder: Forwarder) -> Self {
+        self.forwarder = Some(Arc::new(forwarder));
+        self
+    }
+
+    /// Respond to a query, forwarding non-AntNS names if enabled
+    pub async fn respond(&self, name: &LowerName, query_type: RecordType) -> QueryResponse {
+        let forwarder = match &self.forwarder {
+            Some(forwarder) if !is_ant_name(&name.to_string()) => forwarder,
+            _ => return self.answer(name, query_type).await,
+        };
+
+        match forwarder.forward(name, query_type).await {
+            Ok(response) => {
+                println!(
+                    "  → {} from upstream ({} answer(s))",
+                    response.response_code,
+                    response.answers.len()
+                );
+                QueryResponse {
+                    response_code: response.response_code,
+                    authoritative: false,
+                    answers: response.answers,
+                    name_servers: response.name_servers,
+                    additionals: response.additionals,
+                }
+            }
+            Err(e) => {
+                println!("  ✗ Forwarding failed: {:#}", e);
+                QueryResponse {
+                    authoritative: false,
+                    ..QueryResponse::authoritative(ResponseCode::ServFail, Vec::new())
+                }
+            }
+        }
+    }
+
+    /// Respond to an encoded-and-decoded DNS message (DoH and DoT)
+    ///
+    /// Only standard queries with a single question are answered; anything
+    /// else gets FORMERR or NOTIMP.
+    pub async fn respond_to_message(&self, request: &Message) -> Message {
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_recursion_desired(request.recursion_desired())
+            .set_recursion_available(self.forwarder.is_some());
+
+        if request.op_code() != OpCode::Query {
+            response.set_response_code(ResponseCode::NotImp);
+            return response;
+        }
+        let query = match request.queries() {
+            [query] if request.message_type() == MessageType::Query => query.clone(),
+            _ => {
+                response.set_response_code(ResponseCode::FormErr);
+                return response;
+            }
+        };
+
+        println!("DNS query: {} {:?}", query.name(), query.query_type());
+
+        let result = self
+            .respond(&LowerName::from(query.name()), query.query_type())
+            .await;
+        response
+            .add_query(query)
+            .set_authoritative(result.authoritative)
+            .set_response_code(result.response_code)
+            .add_answers(result.answers)
+            .add_name_servers(result.name_servers)
+            .add_additionals(result.additionals);
+        response
+    }
+
+    /// Authoritative answer to a query for an AntNS name
+    pub async fn answer(&self, name: &LowerName, query_type: RecordType) -> QueryResponse {
+        // DNS names in the protocol have a trailing dot (e.g., "www.mark2.ant.")
+        let domain_name = match crate::DomainName::parse(&name.to_string()) {
+            Ok(domain_name) => domain_name,
+            Err(_) => {
+                println!("  → NXDOMAIN (not a .ant/.autonomi domain)");
+                return QueryResponse::authoritative(ResponseCode::NXDomain, Vec::new());
+            }
+        };
+
+        let resolved = match self.cache.resolve(&domain_name).await {
+            Ok(resolved) => resolved,
+            Err(e) if e.is_not_found() => {
+                println!("  → NXDOMAIN (not registered)");
+                // The name does not exist in its top-level domain
+                let tld = domain_name.base().rsplit('.').next().unwrap_or_default();
+                let soa = self.soa(tld, 0);
+                return QueryResponse::negative(ResponseCode::NXDomain, soa);
+            }
+            Err(e) => {
+                println!("  ✗ Lookup failed: {}", e);
+                return QueryResponse::authoritative(ResponseCode::ServFail, Vec::new());
+            }
+        };
+
+        let zone = &resolved.zone;
+        let serial = zone
+            .document()
+            .and_then(|doc| doc.sequence)
+            .map_or(0, |sequence| u32::try_from(sequence).unwrap_or(u32::MAX));
+        let soa = self.soa(&zone.domain, serial);
+
+        if !resolved.exists() {
+            println!("  → NXDOMAIN (no records named '{}')", resolved.record_name);
+            return QueryResponse::negative(ResponseCode::NXDomain, soa);
+        }
+
+        let records: Vec<_> = resolved.records().collect();
+
+        let owner = Name::from(name.clone());
+        let typed: Vec<(u32, RecordData)> = records
+            .iter()
+            .filter_map(|record| match record.data() {
+                Ok(data) => Some((record.ttl.unwrap_or(DEFAULT_RECORD_TTL), data)),
+                Err(e) => {
+                    tracing::debug!("Skipping record: {}", e);
+                    None
+                }
+            })
+            .collect();
+        let rdatas: Vec<(u32, RData)> = typed
+            .iter()
+            .filter_map(|(ttl, data)| to_rdata(data).map(|rdata| (*ttl, rdata)))
+            .collect();
+
+        let mut answers: Vec<Record> = rdatas
+            .iter()
+            .filter(|(_, rdata)| rdata.record_type() == query_type)
+            .map(|(ttl, rdata)| Record::from_rdata(owner.clone(), *ttl, rdata.clone()))
+            .collect();
+
+        if answers.is_empty() {
+            // A CNAME stands in for every other type at the same name
+            answers.extend(
+                rdatas
+                    .iter()
+                    .filter(|(_, rdata)| rdata.record_type() == RecordType::CNAME)
+                    .map(|(ttl, rdata)| Record::from_rdata(owner.clone(), *ttl, rdata.clone())),
+            );
+        }
+
+        let has_address = typed.iter().any(|(_, data)| {
+            matches!(
+                data.record_type(),
+                AntRecordType::A | AntRecordType::Aaaa | AntRecordType::Cname
+            )
+        });
+        let has_content = typed
+            .iter()
+            .any(|(_, data)| data.record_type() == AntRecordType::Ant);
+
+        if answers.is_empty() && query_type == RecordType::A && has_content && !has_address {
+            // Content-only names are served by the local HTTP proxy
+            println!("  → 127.0.0.1 (content address, served by the HTTP proxy)");
+            let rdata = RData::A(A(Ipv4Addr::LOCALHOST));
+            answers.push(Record::from_rdata(owner, DEFAULT_RECORD_TTL, rdata));
+        } else if answers.is_empty() {
+            println!("  → NODATA (no {} records)", query_type);
+            return QueryResponse::negative(ResponseCode::NoError, soa);
+        } else {
+            println!("  → {} answer(s)", answers.len());
+        }
+
+        QueryResponse::authoritative(ResponseCode::NoError, answers)
+    }
+
+    /// Synthesized SOA record for negative answers from `zone`
+    ///
+    /// Both its TTL and its minimum field are the resolution cache's
+    /// negative TTL, which bounds how long resolvers cache the answer.
+    fn soa(&self, zone: &str, serial: u32) -> Option<Record> {
+        let negative_ttl = u32::try_from(self.cache.negative_ttl().as_secs()).unwrap_or(u32::MAX);
+        let soa = SOA::new(
+            fqdn(&format!("ns.{}", zone))?,
+            fqdn(&format!("hostmaster.{}", zone))?,
+            serial,
+            SOA_REFRESH,
+            SOA_RETRY,
+            SOA_EXPIRE,
+            negative_ttl,
+        );
+        Some(Record::from_rdata(
+            fqdn(zone)?,
+            negative_ttl,
+            RData::SOA(soa),
+        ))
+    }
+}
+
+/// DNS form of a record, for the types the DNS server answers
+fn to_rdata(data: &RecordData) -> Option<RData> {
+    let rdata = match data {
+        RecordData::A(ip) => RData::A(A(*ip)),
+        RecordData::Aaaa(ip) => RData::AAAA(AAAA(*ip)),
+        RecordData::Cname(target) => RData::CNAME(CNAME(fqdn(target)?)),
+        RecordData::Txt(text) => RData::TXT(TXT::new(txt_chunks(text))),
+        RecordData::Mx {
+            preference,
+            exchange,
+        } => RData::MX(MX::new(*preference, fqdn(exchange)?)),
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            target,
+        } => RData::SRV(SRV::new(*priority, *weight, *port, fqdn(target)?)),
+        RecordData::Caa { .. } | RecordData::Ant(_) | RecordData::Delegate(_) => return None,
+    };
+    Some(rdata)
+}
+
+/// Fully qualified DNS name for a record target
+fn fqdn(name: &str) -> Option<Name> {
+    Name::from_ascii(format!("{}.", name)).ok()
+}
+
+/// Split text into DNS character-strings of at most 255 bytes
+fn txt_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > TXT_CHUNK_LENGTH {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    chunks.push(chunk);
+    chunks
+}
 
 #[async_trait::async_trait]
-impl RequestHandler for AntDnsHandler {
+impl<N: NameNetwork + 'static> RequestHandler for AntDnsHandler<N> {
     async fn handle_request<R: ResponseHandler>(
         &self,
         request: &Request,
@@ -27,89 +376,302 @@ impl RequestHandler for AntDnsHandler {
         let name = query.name();
         let query_type = query.query_type();
 
-        println!("DNS query: {} {:?}", name, query_type);
-
-        // Check if this is a .ant or .autonomi domain
-        // Note: DNS names in the protocol have a trailing dot (e.g., "mark2.ant.")
-        let name_str = name.to_string();
-        let is_ant_domain = name_str.ends_with(".ant.") || name_str.ends_with(".autonomi.");
-
         let mut header = Header::response_from_request(request.header());
-        header.set_authoritative(true);
-
-        if is_ant_domain {
-            // Respond with 127.0.0.1 for .ant/.autonomi domains
-            println!("  → Resolving to 127.0.0.1");
-            let mut records = Vec::new();
+        header.set_recursion_available(self.forwarder.is_some());
 
-            if query_type == hickory_proto::rr::RecordType::A {
-                let rdata = RData::A(A(Ipv4Addr::new(127, 0, 0, 1)));
-                let record = Record::from_rdata(Name::from(name.clone()), 300, rdata);
-                records.push(record);
+        let result = if self.allows(request.src().ip()) {
+            println!("DNS query: {} {:?}", name, query_type);
+            self.respond(name, query_type).await
+        } else {
+            tracing::warn!("Refused DNS query from {}", request.src());
+            QueryResponse {
+                authoritative: false,
+                ..QueryResponse::authoritative(ResponseCode::Refused, Vec::new())
             }
+        };
+        header.set_authoritative(result.authoritative);
+        header.set_response_code(result.response_code);
 
-            header.set_response_code(ResponseCode::NoError);
-            let response = MessageResponseBuilder::from_message_request(request).build(
-                header,
-                records.iter(),
-                &[],
-                &[],
-                &[],
-            );
+        let response = MessageResponseBuilder::from_message_request(request).build(
+            header,
+            result.answers.iter(),
+            result.name_servers.iter(),
+            &[],
+            result.additionals.iter(),
+        );
 
-            match response_handler.send_response(response).await {
-                Ok(info) => return info,
-                Err(e) => {
-                    println!("  ✗ Failed to send DNS response: {}", e);
-                    return ResponseInfo::from(header);
-                }
-            }
-        } else {
-            // Return NXDOMAIN for non-.ant domains
-            println!("  → NXDOMAIN (not a .ant/.autonomi domain)");
-            header.set_response_code(ResponseCode::NXDomain);
-            let response =
-                MessageResponseBuilder::from_message_request(request).build_no_records(header);
-
-            match response_handler.send_response(response).await {
-                Ok(info) => return info,
-                Err(e) => {
-                    println!("  ✗ Failed to send DNS response: {}", e);
-                    return ResponseInfo::from(header);
-                }
+        match response_handler.send_response(response).await {
+            Ok(info) => info,
+            Err(e) => {
+                println!("  ✗ Failed to send DNS response: {}", e);
+                ResponseInfo::from(header)
             }
         }
     }
 }
 
-/// Start the DNS server on the specified port
-pub async fn run(port: u16) -> Result<()> {
-    let addr = format!("127.0.0.1:{}", port);
+/// DNS-over-HTTPS and DNS-over-TLS listeners, sharing one certificate
+#[derive(Debug, Clone)]
+pub struct TlsListeners {
+    /// PEM certificate chain
+    pub cert_path: PathBuf,
+    /// PEM private key
+    pub key_path: PathBuf,
+    /// Port for DNS-over-HTTPS (RFC 8484), if enabled
+    pub doh_port: Option<u16>,
+    /// Port for DNS-over-TLS (RFC 7858), if enabled
+    pub dot_port: Option<u16>,
+}
+
+/// Start the DNS server on `port` of every address in `addresses`
+///
+/// With `tls`, DoH and DoT listeners are started next to plain UDP/TCP on
+/// the same addresses and answer through the same handler.
+pub async fn run<N: NameNetwork + 'static>(
+    addresses: &[IpAddr],
+    port: u16,
+    handler: AntDnsHandler<N>,
+    tls: Option<TlsListeners>,
+) -> Result<()> {
+    let addrs: Vec<SocketAddr> = addresses
+        .iter()
+        .map(|ip| SocketAddr::new(*ip, port))
+        .collect();
+
+    println!("DNS server starting on {}", join(&addrs));
+    println!("Allowed clients: {}", handler.access_list);
+
+    // Load certificates before binding anything so mistakes fail fast
+    let secure_listener = |port: Option<u16>, alpn: &[&[u8]]| -> Result<_> {
+        match (&tls, port) {
+            (Some(tls), Some(port)) => Ok(Some((
+                port,
+                load_server_config(&tls.cert_path, &tls.key_path, alpn)?,
+            ))),
+            _ => Ok(None),
+        }
+    };
+    let doh = secure_listener(tls.as_ref().and_then(|tls| tls.doh_port), DOH_ALPN)?;
+    let dot = secure_listener(tls.as_ref().and_then(|tls| tls.dot_port), DOT_ALPN)?;
+
+    if let Some(forwarder) = &handler.forwarder {
+        println!("Forwarding other names to: {}", join(forwarder.upstreams()));
+    }
+
+    let mut doh_listeners = Vec::new();
+    let mut dot_listeners = Vec::new();
+    for ip in addresses {
+        if let Some((port, config)) = &doh {
+            let addr = SocketAddr::new(*ip, *port);
+            doh_listeners.push(crate::server::doh::run(
+                addr,
+                config.clone(),
+                handler.clone(),
+            ));
+        }
+        if let Some((port, config)) = &dot {
+            let addr = SocketAddr::new(*ip, *port);
+            dot_listeners.push(crate::server::dot::run(
+                addr,
+                config.clone(),
+                handler.clone(),
+            ));
+        }
+    }
 
-    println!("DNS server starting on {}", addr);
+    let mut server = ServerFuture::new(handler.clone());
 
-    let handler = AntDnsHandler;
-    let mut server = ServerFuture::new(handler);
+    for addr in &addrs {
+        server.register_socket(
+            tokio::net::UdpSocket::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind DNS UDP socket on {}", addr))?,
+        );
 
-    server.register_socket(
-        tokio::net::UdpSocket::bind(&addr)
-            .await
-            .context("Failed to bind DNS UDP socket")?,
-    );
+        server.register_listener(
+            tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind DNS TCP socket on {}", addr))?,
+            std::time::Duration::from_secs(5),
+        );
+    }
 
-    server.register_listener(
-        tokio::net::TcpListener::bind(&addr)
-            .await
-            .context("Failed to bind DNS TCP socket")?,
-        std::time::Duration::from_secs(5),
-    );
+    println!("✓ DNS server listening on {}\n", join(&addrs));
 
-    println!("✓ DNS server listening on {}\n", addr);
+    let plain = async { server.block_until_done().await.context("DNS server error") };
 
-    server
-        .block_until_done()
-        .await
-        .context("DNS server error")?;
+    tokio::try_join!(
+        plain,
+        futures::future::try_join_all(doh_listeners),
+        futures::future::try_join_all(dot_listeners)
+    )?;
 
     Ok(())
 }
+
+fn join(addrs: &[SocketAddr]) -> String {
+    addrs
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MemoryNetwork;
+    use crate::register::DnsRecord;
+    use crate::testing::register_with_records;
+    use std::str::FromStr;
+
+    fn record(record_type: &str, name: &str, value: &str) -> DnsRecord {
+        DnsRecord::new(name, RecordData::parse(record_type, value).unwrap())
+    }
+
+    async fn query(
+        handler: &AntDnsHandler<MemoryNetwork>,
+        name: &str,
+        query_type: RecordType,
+    ) -> (ResponseCode, Vec<RData>) {
+        let name = LowerName::from(Name::from_str(name).unwrap());
+        let response = handler.answer(&name, query_type).await;
+        let rdatas = response
+            .answers
+            .into_iter()
+            .filter_map(|record| record.data().cloned())
+            .collect();
+        (response.response_code, rdatas)
+    }
+
+    /// Owner name and serial of the SOA record in a negative answer
+    async fn negative_soa(
+        handler: &AntDnsHandler<MemoryNetwork>,
+        name: &str,
+        query_type: RecordType,
+    ) -> (ResponseCode, String, u32) {
+        let name = LowerName::from(Name::from_str(name).unwrap());
+        let response = handler.answer(&name, query_type).await;
+        assert!(response.answers.is_empty());
+        match &response.name_servers[..] {
+            [record] => match record.data() {
+                Some(RData::SOA(soa)) => {
+                    assert_eq!(record.ttl(), soa.minimum());
+                    (
+                        response.response_code,
+                        record.name().to_string(),
+                        soa.serial(),
+                    )
+                }
+                other => panic!("expected an SOA record, got {:?}", other),
+            },
+            other => panic!("expected one authority record, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_answers_from_verified_records() {
+        let network = MemoryNetwork::new();
+        let content = "ab".repeat(32);
+        let records = vec![
+            record("ANT", ".", &content),
+            record("A", "www", "192.0.2.1").with_ttl(60),
+            record("AAAA", "www", "2001:db8::1"),
+            record("TXT", "www", &"x".repeat(300)),
+            record("MX", ".", "10 mail.example.com"),
+            record("SRV", "_sip._udp", "0 5 5060 sip.example.com"),
+            record("CNAME", "alias", "www.test.ant"),
+        ];
+        register_with_records(&network, "test.ant", records).await;
+
+        let cache = ResolutionCache::new(
+            network,
+            Default::default(),
+            std::time::Duration::from_secs(60),
+        );
+        let handler = AntDnsHandler::new(Arc::new(cache));
+
+        let (code, answers) = query(&handler, "www.test.ant.", RecordType::A).await;
+        assert_eq!(code, ResponseCode::NoError);
+        assert_eq!(answers, [RData::A(A(Ipv4Addr::new(192, 0, 2, 1)))]);
+
+        let (_, answers) = query(&handler, "WWW.test.ant.", RecordType::AAAA).await;
+        assert_eq!(answers.len(), 1);
+
+        let (_, answers) = query(&handler, "www.test.ant.", RecordType::TXT).await;
+        match &answers[..] {
+            [RData::TXT(txt)] => assert_eq!(txt.txt_data().len(), 2),
+            other => panic!("expected one TXT record, got {:?}", other),
+        }
+
+        let (_, answers) = query(&handler, "test.ant.", RecordType::MX).await;
+        assert_eq!(answers.len(), 1);
+        let (_, answers) = query(&handler, "_sip._udp.test.ant.", RecordType::SRV).await;
+        assert_eq!(answers.len(), 1);
+
+        // CNAME answers queries for other types
+        let (_, answers) = query(&handler, "alias.test.ant.", RecordType::A).await;
+        assert!(matches!(&answers[..], [RData::CNAME(_)]));
+
+        // Content-only root falls back to the local proxy
+        let (code, answers) = query(&handler, "test.ant.", RecordType::A).await;
+        assert_eq!(code, ResponseCode::NoError);
+        assert_eq!(answers, [RData::A(A(Ipv4Addr::LOCALHOST))]);
+
+        // NODATA: the name exists, the type does not
+        let (code, answers) = query(&handler, "www.test.ant.", RecordType::MX).await;
+        assert_eq!((code, answers.len()), (ResponseCode::NoError, 0));
+        let (code, answers) = query(&handler, "test.ant.", RecordType::AAAA).await;
+        assert_eq!((code, answers.len()), (ResponseCode::NoError, 0));
+
+        // NXDOMAIN: no such subdomain, unregistered domain, not an ant name
+        for name in ["missing.test.ant.", "other.ant.", "example.com."] {
+            let (code, _) = query(&handler, name, RecordType::A).await;
+            assert_eq!(code, ResponseCode::NXDomain, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negative_answers() {
+        let network = MemoryNetwork::new();
+        let records = vec![
+            record("A", "a.b", "192.0.2.1"),
+            record("SRV", "_sip._udp", "0 5 5060 sip.example.com"),
+        ];
+        register_with_records(&network, "test.ant", records).await;
+
+        let cache = ResolutionCache::new(
+            network,
+            Default::default(),
+            std::time::Duration::from_secs(60),
+        );
+        let handler = AntDnsHandler::new(Arc::new(cache));
+
+        // Empty non-terminals exist, so they are NODATA rather than NXDOMAIN
+        for name in ["b.test.ant.", "_udp.test.ant."] {
+            let (code, answers) = query(&handler, name, RecordType::A).await;
+            assert_eq!(
+                (code, answers.len()),
+                (ResponseCode::NoError, 0),
+                "{}",
+                name
+            );
+        }
+        let (code, _) = query(&handler, "c.b.test.ant.", RecordType::A).await;
+        assert_eq!(code, ResponseCode::NXDomain);
+
+        // Negative answers carry the SOA of the zone, serial from the document
+        let (code, zone, serial) = negative_soa(&handler, "b.test.ant.", RecordType::A).await;
+        assert_eq!(
+            (code, zone.as_str(), serial),
+            (ResponseCode::NoError, "test.ant.", 1)
+        );
+        let (code, zone, _) = negative_soa(&handler, "missing.test.ant.", RecordType::A).await;
+        assert_eq!((code, zone.as_str()), (ResponseCode::NXDomain, "test.ant."));
+        let (code, zone, serial) = negative_soa(&handler, "other.ant.", RecordType::A).await;
+        assert_eq!(
+            (code, zone.as_str(), serial),
+            (ResponseCode::NXDomain, "ant.", 0)
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! DNS-over-HTTPS listener (RFC 8484)
+//!
+//! Queries are served at `/dns-query`, either as the base64url-encoded `dns`
+//! parameter of a GET request or as the `application/dns-message` body of a
+//! POST request. HTTP/2 and HTTP/1.1 are both accepted.
+
+use crate::network::NameNetwork;
+use crate::server::dns::AntDnsHandler;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bytes::Bytes;
+use hickory_proto::op::Message;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Body;
+use hyper::header::{ALLOW, CACHE_CONTROL, CONTENT_TYPE};
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+/// Path queries are served at
+const DOH_PATH: &str = "/dns-query";
+
+/// Media type of DNS messages
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Largest accepted DNS message
+const MAX_MESSAGE_SIZE: usize = 65535;
+
+/// How long the TLS handshake may take
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start the DNS-over-HTTPS listener on `addr`
+///
+/// Connections from clients the handler does not allow are closed at once.
+pub async fn run<N: NameNetwork + 'static>(
+    addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    handler: AntDnsHandler<N>,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind DNS-over-HTTPS socket on {}", addr))?;
+    let acceptor = TlsAcceptor::from(config);
+
+    println!("✓ DNS-over-HTTPS listening on https://{}{}", addr, DOH_PATH);
+
+    loop {
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::error!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        if !handler.allows(remote_addr.ip()) {
+            tracing::warn!("Refused DNS-over-HTTPS connection from {}", remote_addr);
+            continue;
+        }
+
+        let acceptor = acceptor.clone();
+        let handler = handler.clone();
+
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!("TLS handshake with {} timed out", remote_addr);
+                        return;
+                    }
+                };
+
+            let service = service_fn(move |req| {
+                let handler = handler.clone();
+                async move { Ok::<_, hyper::Error>(handle_request(&handler, req).await) }
+            });
+
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::error!("Connection error from {}: {}", remote_addr, e);
+            }
+        });
+    }
+}
+
+/// Handle a DNS-over-HTTPS request
+pub(crate) async fn handle_request<N, B>(
+    handler: &AntDnsHandler<N>,
+    req: Request<B>,
+) -> Response<Full<Bytes>>
+where
+    N: NameNetwork + 'static,
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    if req.uri().path() != DOH_PATH {
+        return error_response(StatusCode::NOT_FOUND, "Not found");
+    }
+
+    let message = match *req.method() {
+        Method::GET => {
+            let encoded = req.uri().query().and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix("dns="))
+            });
+            match encoded.map(|encoded| URL_SAFE_NO_PAD.decode(encoded)) {
+                Some(Ok(message)) => Bytes::from(message),
+                Some(Err(_)) => {
+                    return error_response(StatusCode::BAD_REQUEST, "Invalid base64url 'dns'")
+                }
+                None => return error_response(StatusCode::BAD_REQUEST, "Missing 'dns' parameter"),
+            }
+        }
+        Method::POST => {
+            let content_type = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok());
+            if content_type != Some(DNS_MESSAGE) {
+                return error_response(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "Expected application/dns-message",
+                );
+            }
+            match Limited::new(req.into_body(), MAX_MESSAGE_SIZE)
+                .collect()
+                .await
+            {
+                Ok(collected) => collected.to_bytes(),
+                Err(e) if e.is::<http_body_util::LengthLimitError>() => {
+                    return error_response(StatusCode::PAYLOAD_TOO_LARGE, "Message too large")
+                }
+                Err(_) => return error_response(StatusCode::BAD_REQUEST, "Failed to read body"),
+            }
+        }
+        _ => {
+            let mut response = error_response(StatusCode::METHOD_NOT_ALLOWED, "Use GET or POST");
+            response
+                .headers_mut()
+                .insert(ALLOW, "GET, POST".parse().unwrap());
+            return response;
+        }
+    };
+
+    let request = match Message::from_vec(&message) {
+        Ok(request) => request,
+        Err(_) => return error_response(StatusCode::BAD_REQUEST, "Invalid DNS message"),
+    };
+
+    let response = handler.respond_to_message(&request).await;
+    // RFC 8484 §5.1: cache for no longer than the smallest TTL in the answer
+    let max_age = response
+        .answers()
+        .iter()
+        .chain(response.name_servers())
+        .map(|record| record.ttl())
+        .min()
+        .unwrap_or(0);
+
+    match response.to_vec() {
+        Ok(bytes) => Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(CACHE_CONTROL, format!("max-age={}", max_age))
+            .body(Full::new(Bytes::from(bytes)))
+            .unwrap(),
+        Err(e) => {
+            tracing::error!("Failed to encode DNS response: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to encode response",
+            )
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: &'static str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::from(message)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MemoryNetwork;
+    use crate::server::cache::ResolutionCache;
+    use hickory_proto::op::{MessageType, OpCode, Query, ResponseCode};
+    use hickory_proto::rr::{Name, RecordType};
+    use std::str::FromStr;
+
+    fn request(
+        method: Method,
+        uri: &str,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, content_type)
+            .body(Full::new(Bytes::from(body)))
+            .unwrap()
+    }
+
+    async fn body(response: Response<Full<Bytes>>) -> Bytes {
+        response.into_body().collect().await.unwrap().to_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_doh_requests() {
+        let cache = ResolutionCache::new(
+            MemoryNetwork::new(),
+            Default::default(),
+            Duration::from_secs(60),
+        );
+        let handler = AntDnsHandler::new(Arc::new(cache));
+
+        let mut query = Message::new();
+        query
+            .set_id(0)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .add_query(Query::query(
+                Name::from_str("missing.ant.").unwrap(),
+                RecordType::A,
+            ));
+        let query = query.to_vec().unwrap();
+
+        let get = format!("/dns-query?ct&dns={}", URL_SAFE_NO_PAD.encode(&query));
+        let post = request(Method::POST, "/dns-query", DNS_MESSAGE, query.clone());
+        for req in [request(Method::GET, &get, "", Vec::new()), post] {
+            let response = handle_request(&handler, req).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[CONTENT_TYPE], DNS_MESSAGE);
+            let message = Message::from_vec(&body(response).await).unwrap();
+            assert_eq!(message.response_code(), ResponseCode::NXDomain);
+            assert_eq!(message.queries().len(), 1);
+        }
+
+        let rejected = [
+            (
+                request(Method::GET, "/dns-query", "", Vec::new()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                request(Method::GET, "/dns-query?dns=%%%", "", Vec::new()),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                request(Method::POST, "/dns-query", "text/plain", query.clone()),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ),
+            (
+                request(Method::POST, "/dns-query", DNS_MESSAGE, vec![0; 3]),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                request(
+                    Method::POST,
+                    "/dns-query",
+                    DNS_MESSAGE,
+                    vec![0; MAX_MESSAGE_SIZE + 1],
+                ),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                request(Method::PUT, "/dns-query", DNS_MESSAGE, query.clone()),
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            (
+                request(Method::GET, "/resolve", "", Vec::new()),
+                StatusCode::NOT_FOUND,
+            ),
+        ];
+        for (req, status) in rejected {
+            let uri = req.uri().clone();
+            assert_eq!(
+                handle_request(&handler, req).await.status(),
+                status,
+                "{}",
+                uri
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! DNS-over-TLS listener (RFC 7858)
+//!
+//! Messages are framed like DNS over TCP: a two-byte length followed by the
+//! message. A connection may carry any number of queries and is closed after
+//! it has been idle for [`IDLE_TIMEOUT`].
+
+use crate::network::NameNetwork;
+use crate::server::dns::AntDnsHandler;
+use anyhow::{Context, Result};
+use hickory_proto::op::Message;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+/// How long a connection may stay idle (including the TLS handshake)
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start the DNS-over-TLS listener on `addr`
+///
+/// Connections from clients the handler does not allow are closed at once.
+pub async fn run<N: NameNetwork + 'static>(
+    addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    handler: AntDnsHandler<N>,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind DNS-over-TLS socket on {}", addr))?;
+    let acceptor = TlsAcceptor::from(config);
+
+    println!("✓ DNS-over-TLS listening on {}", addr);
+
+    loop {
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::error!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        if !handler.allows(remote_addr.ip()) {
+            tracing::warn!("Refused DNS-over-TLS connection from {}", remote_addr);
+            continue;
+        }
+
+        let acceptor = acceptor.clone();
+        let handler = handler.clone();
+
+        tokio::spawn(async move {
+            let stream = match tokio::time::timeout(IDLE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                    return;
+                }
+                Err(_) => {
+                    tracing::debug!("TLS handshake with {} timed out", remote_addr);
+                    return;
+                }
+            };
+
+            if let Err(e) = serve_connection(stream, &handler).await {
+                tracing::debug!("DNS-over-TLS connection from {}: {:#}", remote_addr, e);
+            }
+        });
+    }
+}
+
+/// Answer length-prefixed queries until the client closes or goes idle
+pub(crate) async fn serve_connection<S, N>(mut stream: S, handler: &AntDnsHandler<N>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    N: NameNetwork + 'static,
+{
+    loop {
+        let len = match tokio::time::timeout(IDLE_TIMEOUT, stream.read_u16()).await {
+            Ok(Ok(len)) => len,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Ok(Err(e)) => return Err(e).context("Failed to read query"),
+            Err(_) => return Ok(()),
+        };
+
+        let mut buffer = vec![0; len.into()];
+        tokio::time::timeout(IDLE_TIMEOUT, stream.read_exact(&mut buffer))
+            .await
+            .context("Timed out reading query")?
+            .context("Failed to read query")?;
+
+        let request = Message::from_vec(&buffer).context("Invalid DNS message")?;
+        let response = handler
+            .respond_to_message(&request)
+            .await
+            .to_vec()
+            .context("Failed to encode response")?;
+
+        let len = u16::try_from(response.len()).context("Response is too large")?;
+        stream.write_all(&len.to_be_bytes()).await?;
+        stream.write_all(&response).await?;
+        stream.flush().await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MemoryNetwork;
+    use crate::server::cache::ResolutionCache;
+    use hickory_proto::op::{MessageType, OpCode, Query, ResponseCode};
+    use hickory_proto::rr::{Name, RecordType};
+    use std::str::FromStr;
+
+    fn query(id: u16, name: &str) -> Vec<u8> {
+        let mut message = Message::new();
+        message
+            .set_id(id)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .add_query(Query::query(Name::from_str(name).unwrap(), RecordType::A));
+        let bytes = message.to_vec().unwrap();
+        [&(bytes.len() as u16).to_be_bytes()[..], &bytes].concat()
+    }
+
+    async fn read_response(stream: &mut tokio::io::DuplexStream) -> Message {
+        let len = stream.read_u16().await.unwrap();
+        let mut buffer = vec![0; len.into()];
+        stream.read_exact(&mut buffer).await.unwrap();
+        Message::from_vec(&buffer).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_queries() {
+        let cache = ResolutionCache::new(
+            MemoryNetwork::new(),
+            Default::default(),
+            Duration::from_secs(60),
+        );
+        let handler = AntDnsHandler::new(Arc::new(cache));
+        let (mut client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move { serve_connection(server, &handler).await });
+
+        // Both queries are sent before either answer is read
+        client.write_all(&query(1, "missing.ant.")).await.unwrap();
+        client.write_all(&query(2, "example.com.")).await.unwrap();
+
+        for id in [1, 2] {
+            let response = read_response(&mut client).await;
+            assert_eq!(response.id(), id);
+            assert_eq!(response.message_type(), MessageType::Response);
+            assert_eq!(response.response_code(), ResponseCode::NXDomain);
+            assert_eq!(response.queries().len(), 1);
+        }
+
+        drop(client);
+        server.await.unwrap().unwrap();
+    }
+}
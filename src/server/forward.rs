@@ -0,0 +1,559 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Forwarding of non-AntNS queries to upstream resolvers
+//!
+//! With forwarding enabled the DNS server can be a machine's only resolver:
+//! `.ant`/`.autonomi` names are answered from verified records and every
+//! other query is relayed to the configured upstreams.
+//!
+//! - Upstreams are tried in order; one that times out, fails, or answers
+//!   SERVFAIL or REFUSED is skipped and the next one is asked. If none
+//!   answers, the last SERVFAIL or REFUSED is relayed.
+//! - Queries go over UDP and are retried over TCP when the answer is
+//!   truncated. Responses that do not echo the query's ID and question are
+//!   not accepted.
+//! - Responses are cached for the lowest TTL of their records (capped at
+//!   [`FORWARD_CACHE_MAX_TTL`]); responses without records are cached for
+//!   [`FORWARD_NEGATIVE_TTL`]. Failures are never cached. When the cache is
+//!   full, the entry closest to expiry makes room.
+
+use crate::constants::{FORWARD_CACHE_MAX_ENTRIES, FORWARD_CACHE_MAX_TTL, FORWARD_NEGATIVE_TTL};
+use anyhow::{anyhow, bail, Context, Result};
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{LowerName, Name, Record, RecordType};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+
+/// Port used for upstreams given without one
+const DNS_PORT: u16 = 53;
+
+/// Largest DNS message over UDP or TCP
+const MAX_MESSAGE_SIZE: usize = 65535;
+
+/// Response relayed from an upstream resolver
+#[derive(Debug, Clone)]
+pub struct ForwardedResponse {
+    pub response_code: ResponseCode,
+    pub answers: Vec<Record>,
+    pub name_servers: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl ForwardedResponse {
+    /// How long the response may be cached
+    fn cache_ttl(&self) -> u32 {
+        self.records()
+            .map(Record::ttl)
+            .min()
+            .unwrap_or(FORWARD_NEGATIVE_TTL)
+            .min(FORWARD_CACHE_MAX_TTL)
+    }
+
+    /// Copy of the response with TTLs reduced by the time spent in the cache
+    fn aged(&self, age: u32) -> Self {
+        let mut response = self.clone();
+        for record in response
+            .answers
+            .iter_mut()
+            .chain(&mut response.name_servers)
+            .chain(&mut response.additionals)
+        {
+            record.set_ttl(record.ttl().saturating_sub(age));
+        }
+        response
+    }
+
+    fn records(&self) -> impl Iterator<Item = &Record> {
+        self.answers
+            .iter()
+            .chain(&self.name_servers)
+            .chain(&self.additionals)
+    }
+}
+
+/// A cached response and when it expires
+struct CachedResponse {
+    response: ForwardedResponse,
+    stored_at: Instant,
+    expires_at: Instant,
+}
+
+/// Relays queries to upstream resolvers
+pub struct Forwarder {
+    upstreams: Vec<SocketAddr>,
+    timeout: Duration,
+    capacity: usize,
+    cache: Mutex<HashMap<(LowerName, RecordType), CachedResponse>>,
+}
+
+impl Forwarder {
+    /// Forwarder asking `upstreams` in order, waiting `timeout` for each
+    pub fn new(upstreams: Vec<SocketAddr>, timeout: Duration) -> Self {
+        Self {
+            upstreams,
+            timeout,
+            capacity: FORWARD_CACHE_MAX_ENTRIES,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keep at most `capacity` cached responses
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Configured upstream resolvers
+    pub fn upstreams(&self) -> &[SocketAddr] {
+        &self.upstreams
+    }
+
+    /// Resolve a query through the cache or the first upstream that answers
+    pub async fn forward(
+        &self,
+        name: &LowerName,
+        query_type: RecordType,
+    ) -> Result<ForwardedResponse> {
+        let key = (name.clone(), query_type);
+
+        if let Some(cached) = self.cache.lock().await.get(&key) {
+            let now = Instant::now();
+            if now < cached.expires_at {
+                let age = now.duration_since(cached.stored_at).as_secs() as u32;
+                return Ok(cached.response.aged(age));
+            }
+        }
+
+        let query = Query::query(Name::from(name.clone()), query_type);
+        let mut last = Err(anyhow!("no upstream resolvers configured"));
+        for upstream in &self.upstreams {
+            match self.exchange(*upstream, &query).await {
+                Ok(response)
+                    if matches!(
+                        response.response_code,
+                        ResponseCode::ServFail | ResponseCode::Refused
+                    ) =>
+                {
+                    // Another upstream may still be able to answer
+                    tracing::warn!("Upstream {} answered {}", upstream, response.response_code);
+                    last = Ok(response);
+                }
+                Ok(response) => {
+                    self.store(key, &response).await;
+                    return Ok(response);
+                }
+                Err(e) => {
+                    tracing::warn!("Upstream {} failed: {:#}", upstream, e);
+                    if last.is_err() {
+                        last = Err(e);
+                    }
+                }
+            }
+        }
+
+        last
+    }
+
+    /// Drop all cached responses
+    pub async fn clear(&self) {
+        self.cache.lock().await.clear();
+    }
+
+    async fn store(&self, key: (LowerName, RecordType), response: &ForwardedResponse) {
+        if !matches!(
+            response.response_code,
+            ResponseCode::NoError | ResponseCode::NXDomain
+        ) {
+            return;
+        }
+
+        let mut cache = self.cache.lock().await;
+        if cache.len() >= self.capacity && !cache.contains_key(&key) {
+            let now = Instant::now();
+            cache.retain(|_, cached| cached.expires_at > now);
+
+            // Still full: give up the response that would expire first
+            if cache.len() >= self.capacity {
+                let soonest = cache
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.expires_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(soonest) = soonest {
+                    cache.remove(&soonest);
+                }
+            }
+        }
+
+        let stored_at = Instant::now();
+        let ttl = Duration::from_secs(response.cache_ttl().into());
+        cache.insert(
+            key,
+            CachedResponse {
+                response: response.clone(),
+                stored_at,
+                expires_at: stored_at + ttl,
+            },
+        );
+    }
+
+    /// Ask one upstream, over UDP and then TCP if the answer was truncated
+    async fn exchange(&self, upstream: SocketAddr, query: &Query) -> Result<ForwardedResponse> {
+        let mut request = Message::new();
+        request
+            .set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(query.clone());
+        let request_bytes = request.to_vec().context("Failed to encode query")?;
+
+        let mut response = tokio::time::timeout(
+            self.timeout,
+            exchange_udp(upstream, &request, &request_bytes),
+        )
+        .await
+        .map_err(|_| anyhow!("timed out over UDP"))??;
+
+        if response.truncated() {
+            response = tokio::time::timeout(
+                self.timeout,
+                exchange_tcp(upstream, &request, &request_bytes),
+            )
+            .await
+            .map_err(|_| anyhow!("timed out over TCP"))??;
+        }
+
+        Ok(ForwardedResponse {
+            response_code: response.response_code(),
+            answers: response.take_answers(),
+            name_servers: response.take_name_servers(),
+            additionals: response.take_additionals(),
+        })
+    }
+}
+
+/// Whether `response` answers `request`: same ID and the same single question
+///
+/// Name comparison is case-insensitive, so upstreams that randomise the case
+/// of the question (DNS 0x20) are still accepted.
+fn answers(request: &Message, response: &Message) -> bool {
+    response.id() == request.id()
+        && response.message_type() == MessageType::Response
+        && response.queries() == request.queries()
+}
+
+async fn exchange_udp(upstream: SocketAddr, request: &Message, bytes: &[u8]) -> Result<Message> {
+    let local: SocketAddr = if upstream.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(local)
+        .await
+        .context("Failed to bind UDP socket")?;
+    socket
+        .connect(upstream)
+        .await
+        .context("Failed to connect UDP socket")?;
+    socket.send(bytes).await.context("Failed to send query")?;
+
+    let mut buffer = vec![0; MAX_MESSAGE_SIZE];
+    loop {
+        let len = socket
+            .recv(&mut buffer)
+            .await
+            .context("Failed to receive response")?;
+        // Ignore stray or spoofed datagrams that do not answer this query
+        match Message::from_vec(&buffer[..len]) {
+            Ok(response) if answers(request, &response) => return Ok(response),
+            _ => continue,
+        }
+    }
+}
+
+async fn exchange_tcp(upstream: SocketAddr, request: &Message, bytes: &[u8]) -> Result<Message> {
+    let mut stream = TcpStream::connect(upstream)
+        .await
+        .context("Failed to connect over TCP")?;
+
+    let len = u16::try_from(bytes.len()).context("Query is too large")?;
+    stream.write_all(&len.to_be_bytes()).await?;
+    stream.write_all(bytes).await?;
+
+    let len = stream.read_u16().await.context("Failed to read response")?;
+    let mut buffer = vec![0; len.into()];
+    stream
+        .read_exact(&mut buffer)
+        .await
+        .context("Failed to read response")?;
+
+    let response = Message::from_vec(&buffer).context("Invalid response")?;
+    if !answers(request, &response) {
+        bail!("response does not match the query");
+    }
+    Ok(response)
+}
+
+/// Parse an upstream resolver address, `IP` or `IP:port` (default port 53)
+pub fn parse_upstream(value: &str) -> Result<SocketAddr> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let ip: IpAddr = value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .with_context(|| format!("Invalid upstream resolver '{}'", value))?;
+    Ok(SocketAddr::new(ip, DNS_PORT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::RData;
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    /// Answer of the stand-in resolver; `big.example.com` only fits over TCP
+    fn stand_in_response(request: &Message, tcp: bool) -> Message {
+        let query = request.queries()[0].clone();
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .add_query(query.clone());
+
+        match query.name().to_ascii().as_str() {
+            "missing.example.com." => {
+                response.set_response_code(ResponseCode::NXDomain);
+            }
+            "big.example.com." if !tcp => {
+                response.set_truncated(true);
+            }
+            _ => {
+                let rdata = RData::A(A(Ipv4Addr::new(192, 0, 2, 53)));
+                response.add_answer(Record::from_rdata(query.name().clone(), 120, rdata));
+            }
+        }
+        response
+    }
+
+    /// Response of an upstream that has no answer for anything
+    fn failing_response(request: &Message, _tcp: bool) -> Message {
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_response_code(ResponseCode::ServFail)
+            .add_queries(request.queries().to_vec());
+        response
+    }
+
+    /// Response with the right ID that answers a different question
+    fn mismatched_response(request: &Message, tcp: bool) -> Message {
+        let mut other = Message::new();
+        other.set_id(request.id()).add_query(Query::query(
+            name("other.example.com.").into(),
+            RecordType::A,
+        ));
+        stand_in_response(&other, tcp)
+    }
+
+    /// Local resolver on UDP and TCP counting the queries it receives
+    async fn stand_in_resolver() -> (SocketAddr, Arc<AtomicUsize>) {
+        serve(stand_in_response).await
+    }
+
+    /// Local resolver answering with `respond(request, over_tcp)`
+    async fn serve(respond: fn(&Message, bool) -> Message) -> (SocketAddr, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+
+        let counter = queries.clone();
+        tokio::spawn(async move {
+            let mut buffer = vec![0; MAX_MESSAGE_SIZE];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let request = Message::from_vec(&buffer[..len]).unwrap();
+                let response = respond(&request, false).to_vec().unwrap();
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+
+        let counter = queries.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let len = stream.read_u16().await.unwrap();
+                let mut buffer = vec![0; len.into()];
+                stream.read_exact(&mut buffer).await.unwrap();
+                let request = Message::from_vec(&buffer).unwrap();
+                let response = respond(&request, true).to_vec().unwrap();
+                stream
+                    .write_all(&(response.len() as u16).to_be_bytes())
+                    .await
+                    .unwrap();
+                stream.write_all(&response).await.unwrap();
+            }
+        });
+
+        (addr, queries)
+    }
+
+    fn name(name: &str) -> LowerName {
+        LowerName::from(Name::from_str(name).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_forwarding() {
+        // An upstream that never answers, followed by the stand-in
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (addr, queries) = stand_in_resolver().await;
+        let forwarder = Forwarder::new(
+            vec![silent.local_addr().unwrap(), addr],
+            Duration::from_millis(200),
+        );
+
+        let response = forwarder
+            .forward(&name("www.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(response.response_code, ResponseCode::NoError);
+        assert_eq!(
+            response.answers[0].data(),
+            Some(&RData::A(A(Ipv4Addr::new(192, 0, 2, 53))))
+        );
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+
+        // Served from the cache
+        let cached = forwarder
+            .forward(&name("www.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(cached.answers.len(), 1);
+        assert!(cached.answers[0].ttl() <= 120);
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+
+        // Truncated over UDP, retried over TCP
+        let big = forwarder
+            .forward(&name("big.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(big.answers.len(), 1);
+        assert_eq!(queries.load(Ordering::SeqCst), 3);
+
+        // Negative answers are relayed and cached too
+        for _ in 0..2 {
+            let missing = forwarder
+                .forward(&name("missing.example.com."), RecordType::A)
+                .await
+                .unwrap();
+            assert_eq!(missing.response_code, ResponseCode::NXDomain);
+        }
+        assert_eq!(queries.load(Ordering::SeqCst), 4);
+
+        // No upstream answers
+        let unreachable = Forwarder::new(
+            vec![silent.local_addr().unwrap()],
+            Duration::from_millis(100),
+        );
+        assert!(unreachable
+            .forward(&name("www.example.com."), RecordType::A)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let (failing, failing_queries) = serve(failing_response).await;
+        let (mismatched, _) = serve(mismatched_response).await;
+        let (addr, queries) = stand_in_resolver().await;
+
+        // SERVFAIL and answers to another question move on to the next upstream
+        let forwarder = Forwarder::new(vec![failing, mismatched, addr], Duration::from_millis(200));
+        let response = forwarder
+            .forward(&name("www.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(response.response_code, ResponseCode::NoError);
+        assert_eq!(
+            response.answers[0].name(),
+            &Name::from(name("www.example.com."))
+        );
+        assert_eq!(
+            (
+                failing_queries.load(Ordering::SeqCst),
+                queries.load(Ordering::SeqCst)
+            ),
+            (1, 1)
+        );
+
+        // Without a better answer SERVFAIL is relayed, but never cached
+        let forwarder = Forwarder::new(vec![mismatched, failing], Duration::from_millis(200));
+        for _ in 0..2 {
+            let response = forwarder
+                .forward(&name("www.example.com."), RecordType::A)
+                .await
+                .unwrap();
+            assert_eq!(response.response_code, ResponseCode::ServFail);
+        }
+        assert_eq!(failing_queries.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_full_cache_evicts_soonest_expiry() {
+        let (addr, queries) = stand_in_resolver().await;
+        let forwarder = Forwarder::new(vec![addr], Duration::from_millis(200)).with_capacity(2);
+
+        // NXDOMAIN is cached for less time than the answers
+        for query in [
+            "www.example.com.",
+            "missing.example.com.",
+            "mail.example.com.",
+        ] {
+            forwarder
+                .forward(&name(query), RecordType::A)
+                .await
+                .unwrap();
+        }
+        assert_eq!(queries.load(Ordering::SeqCst), 3);
+
+        forwarder
+            .forward(&name("www.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 3);
+        forwarder
+            .forward(&name("missing.example.com."), RecordType::A)
+            .await
+            .unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_parse_upstream() {
+        assert_eq!(
+            parse_upstream("192.0.2.1").unwrap(),
+            "192.0.2.1:53".parse().unwrap()
+        );
+        assert_eq!(
+            parse_upstream("[2001:db8::1]:5353").unwrap(),
+            "[2001:db8::1]:5353".parse().unwrap()
+        );
+        assert_eq!(parse_upstream("2001:db8::1").unwrap().port(), 53);
+        assert!(parse_upstream("dns.example.com").is_err());
+    }
+}
@@ -2,112 +2,268 @@
 // Licensed under GPL-3.0
 
 //! HTTP proxy server for .ant and .autonomi domains
+//!
+//! Connections from clients outside the access list
+//! ([`crate::server::acl`]) are closed without a response. The listener
+//! accepts HTTP/1.1 and HTTP/2 (detected from the connection preface), and
+//! all requests share one pooled upstream client.
+//!
+//! An optional HTTPS listener terminates TLS with certificates from the
+//! local CA ([`crate::server::local_ca`]), so `.ant` sites are secure
+//! contexts in the browser.
+//!
+//! Content comes either from an upstream gateway or, with
+//! [`ContentSource::Network`], straight from the Autonomi network
+//! ([`crate::server::content`]).
+//!
+//! Successful `GET` responses can be kept in a [`ContentCache`]. A name's
+//! target is a content address, so cached content is served again without
+//! revalidation, with a strong `ETag` from its checksum and the
+//! `Last-Modified` date of its first fetch.
+//!
+//! Conditional and `Range` requests are forwarded to the upstream. Full
+//! responses to them, whether the upstream ignored the headers or the
+//! content is local, are answered by [`crate::server::conditional`], so
+//! seeking in media and resuming downloads work with every source.
 
+use crate::network::NameNetwork;
+use crate::server::acl::AccessList;
+use crate::server::cache::ResolutionCache;
+use crate::server::conditional;
+use crate::server::content::{self, Content, TargetCache};
+use crate::server::local_ca::LocalCa;
+use crate::server::tls::PROXY_ALPN;
+use crate::storage::content::{CachedContent, ContentCache, CACHED_HEADERS};
 use anyhow::{Context, Result};
-use autonomi::Client;
 use bytes::Bytes;
-use http_body_util::Full;
-use hyper::body::Incoming;
-use hyper::server::conn::http1;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Frame, SizeHint};
+use hyper::header::{
+    HeaderMap, HeaderName, ALLOW, AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH,
+    CONTENT_TYPE, ETAG, HOST, LAST_MODIFIED, LOCATION, RANGE, VARY,
+};
+use hyper::http::request::Parts;
 use hyper::service::service_fn;
-use hyper::{Request, Response, StatusCode};
-use hyper_util::rt::TokioIo;
-use std::collections::HashMap;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::task::{ready, Context as TaskContext, Poll};
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::Mutex;
+use tokio_rustls::TlsAcceptor;
 
-/// Cached domain lookup result
-#[derive(Clone)]
-struct CachedLookup {
-    target: String,
-    timestamp: SystemTime,
+/// How long the TLS handshake of an HTTPS connection may take
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause after a failed accept, e.g. when out of file descriptors
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// Error type of streamed bodies
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Body streamed through the proxy in either direction
+pub(crate) type ProxyBody = UnsyncBoxBody<Bytes, BoxError>;
+
+/// Headers that only apply to a single connection (RFC 9110 §7.6.1) and
+/// must not be forwarded
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Where the proxy gets the content of a name's target from
+#[derive(Debug, Clone)]
+pub enum ContentSource {
+    /// Fetch targets from the Autonomi network with the proxy's own client
+    Network,
+    /// Forward requests to a gateway; `$ADDRESS` in the URL template is
+    /// replaced with the target
+    Upstream(String),
+}
+
+/// HTTPS listener of the proxy
+pub struct HttpsListener {
+    /// Port to listen on, on every bind address
+    pub port: u16,
+    /// CA issuing the certificates presented to browsers
+    pub ca: Arc<LocalCa>,
+}
+
+/// Connection pool limits and timeouts of the upstream client
+#[derive(Debug, Clone)]
+pub struct UpstreamOptions {
+    /// Idle connections kept open per upstream host
+    pub max_idle_per_host: usize,
+    /// How long an idle connection is kept open
+    pub idle_timeout: Duration,
+    /// Timeout for connecting to the upstream
+    pub connect_timeout: Duration,
+    /// Timeout for the upstream's response headers; the body may take longer
+    pub response_timeout: Duration,
+}
+
+impl Default for UpstreamOptions {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: crate::DEFAULT_PROXY_MAX_IDLE_PER_HOST,
+            idle_timeout: Duration::from_secs(crate::DEFAULT_PROXY_IDLE_TIMEOUT_SECS),
+            connect_timeout: Duration::from_secs(crate::DEFAULT_PROXY_CONNECT_TIMEOUT_SECS),
+            response_timeout: Duration::from_secs(crate::DEFAULT_PROXY_RESPONSE_TIMEOUT_SECS),
+        }
+    }
 }
 
 /// HTTP proxy service state
-struct ProxyState {
-    client: Client,
-    upstream_template: String,
-    cache: Mutex<HashMap<String, CachedLookup>>,
-    cache_ttl: Duration,
+struct ProxyState<N: NameNetwork> {
+    cache: Arc<ResolutionCache<N>>,
+    source: ContentSource,
+    client: Client<HttpConnector, ProxyBody>,
+    response_timeout: Duration,
+    content_cache: Option<ContentCache>,
+    /// What native targets hold, so archives are parsed once
+    targets: TargetCache,
+    access_list: Arc<AccessList>,
+}
+
+impl<N: NameNetwork> ProxyState<N> {
+    fn new(
+        cache: Arc<ResolutionCache<N>>,
+        source: ContentSource,
+        options: &UpstreamOptions,
+        content_cache: Option<ContentCache>,
+        access_list: Arc<AccessList>,
+    ) -> Self {
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(Some(options.connect_timeout));
+        connector.set_nodelay(true);
+
+        let client = Client::builder(TokioExecutor::new())
+            .pool_max_idle_per_host(options.max_idle_per_host)
+            .pool_idle_timeout(options.idle_timeout)
+            .pool_timer(TokioTimer::new())
+            .build(connector);
+
+        Self {
+            cache,
+            source,
+            client,
+            response_timeout: options.response_timeout,
+            content_cache,
+            targets: TargetCache::default(),
+            access_list,
+        }
+    }
 }
 
 /// Handle an HTTP request
-async fn handle_request(
-    state: Arc<ProxyState>,
-    req: Request<Incoming>,
-) -> Result<Response<Full<Bytes>>, hyper::Error> {
-    let host = req
-        .headers()
-        .get("host")
+///
+/// The request body is streamed to the upstream and the upstream's response
+/// body is streamed back, so memory use does not depend on body size.
+async fn handle_request<N, B>(
+    state: Arc<ProxyState<N>>,
+    req: Request<B>,
+) -> Result<Response<ProxyBody>, hyper::Error>
+where
+    N: NameNetwork + 'static,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = req.into_parts();
+    // HTTP/2 requests carry the host in the :authority pseudo-header
+    let host = parts
+        .headers
+        .get(HOST)
         .and_then(|h| h.to_str().ok())
+        .or_else(|| parts.uri.authority().map(|authority| authority.as_str()))
         .unwrap_or("");
 
     println!(
         "\nHTTP request: {} {} {}",
-        req.method(),
+        parts.method,
         host,
-        req.uri().path()
+        parts.uri.path()
     );
 
-    // Extract domain from Host header
-    let domain = host.split(':').next().unwrap_or(host);
-
-    // Check if this is a .ant, .autonomi, or .antns.net domain
-    let lookup_domain = if domain.ends_with(".antns.net") {
-        // Normalize .antns.net to .ant for lookup
-        domain.strip_suffix(".antns.net").unwrap().to_string() + ".ant"
-    } else if domain.ends_with(".ant") || domain.ends_with(".autonomi") {
-        domain.to_string()
-    } else {
-        println!("  ✗ Not a .ant, .autonomi, or .antns.net domain");
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Full::new(Bytes::from(
-                "Only .ant, .autonomi, and .antns.net domains are supported",
-            )))
-            .unwrap());
+    // Extract the name from the Host header; subdomains select records of
+    // their registered domain and *.antns.net maps onto .ant
+    let name = match crate::DomainName::from_host(host) {
+        Ok(name) => name,
+        Err(e) => {
+            println!("  ✗ {}", e);
+            return Ok(text_response(
+                StatusCode::BAD_REQUEST,
+                "Only .ant, .autonomi, and .antns.net domains are supported".to_string(),
+            ));
+        }
     };
+    let domain = host.split(':').next().unwrap_or(host).to_string();
 
-    // Check cache first
-    let target = if state.cache_ttl.as_secs() > 0 {
-        let cache = state.cache.lock().await;
-        if let Some(cached) = cache.get(&lookup_domain) {
-            let age = SystemTime::now()
-                .duration_since(cached.timestamp)
-                .unwrap_or(Duration::MAX);
-            if age < state.cache_ttl {
-                println!("  ✓ Cache hit (age: {}s)", age.as_secs());
-                cached.target.clone()
-            } else {
-                println!("  Cache expired (age: {}s)", age.as_secs());
-                drop(cache);
-                match lookup_and_cache(&state, &lookup_domain).await {
-                    Ok(target) => target,
-                    Err(resp) => return Ok(resp),
-                }
+    println!("  Looking up domain: {}", name);
+    let target = match state.cache.resolve(&name).await {
+        Ok(resolved) => match resolved.target() {
+            Some(target) => {
+                println!("  ✓ Resolved to: {}", target);
+                target.to_string()
             }
-        } else {
-            drop(cache);
-            match lookup_and_cache(&state, &lookup_domain).await {
-                Ok(target) => target,
-                Err(resp) => return Ok(resp),
+            None => {
+                println!("  ✗ No content address for '{}'", name);
+                return Ok(not_found(&name));
             }
+        },
+        Err(e) if e.is_not_found() => {
+            println!("  ✗ Not registered: {}", e);
+            return Ok(not_found(&name));
         }
-    } else {
-        // Caching disabled
-        match lookup_domain_no_cache(&state, &lookup_domain).await {
-            Ok(target) => target,
-            Err(resp) => return Ok(resp),
+        Err(e) => {
+            println!("  ✗ Lookup failed: {}", e);
+            let status = if e.is_timeout() {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::BAD_GATEWAY
+            };
+            return Ok(text_response(
+                status,
+                format!("Lookup of {} failed: {}", name, e),
+            ));
         }
     };
 
+    let cache_path = cacheable_path(&parts, &state.source);
+    if let (Some(content_cache), Some(path)) = (&state.content_cache, cache_path) {
+        let (content_cache, key, path) = (content_cache.clone(), target.clone(), path.to_string());
+        let cached = tokio::task::spawn_blocking(move || content_cache.get(&key, &path)).await;
+        if let Ok(Some(content)) = cached {
+            println!(
+                "  ✓ Serving {} bytes from the content cache",
+                content.data.len()
+            );
+            return Ok(content_response(&parts, &domain, &target, &content, "HIT"));
+        }
+    }
+
+    let template = match &state.source {
+        ContentSource::Network => return Ok(serve_native(&state, &parts, &domain, &target).await),
+        ContentSource::Upstream(template) => template,
+    };
+
     // Build upstream URL by replacing $ADDRESS with the target
-    let upstream_url = state.upstream_template.replace("$ADDRESS", &target);
-    let path = req.uri().path();
-    let query = req
-        .uri()
+    let upstream_url = template.replace("$ADDRESS", &target);
+    let path = parts.uri.path();
+    let query = parts
+        .uri
         .query()
         .map(|q| format!("?{}", q))
         .unwrap_or_default();
@@ -120,203 +276,986 @@ async fn handle_request(
         Ok(uri) => uri,
         Err(e) => {
             tracing::error!("Invalid upstream URL: {}", e);
-            return Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Full::new(Bytes::from(format!(
-                    "Invalid upstream URL: {}",
-                    e
-                ))))
-                .unwrap());
+            return Ok(text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Invalid upstream URL: {}", e),
+            ));
         }
     };
 
-    // Create HTTP client
-    use hyper_util::client::legacy::Client;
-    use hyper_util::rt::TokioExecutor;
-    let client = Client::builder(TokioExecutor::new()).build_http();
-
-    // Make request to upstream
+    // Stream the request body to the upstream
     let mut upstream_req = Request::builder()
-        .method(req.method())
+        .method(parts.method.clone())
         .uri(upstream_uri)
-        .body(http_body_util::Empty::<Bytes>::new())
+        .body(body.map_err(Into::into).boxed_unsync())
         .unwrap();
 
-    // Copy headers from original request
-    *upstream_req.headers_mut() = req.headers().clone();
-
-    match client.request(upstream_req).await {
-        Ok(upstream_resp) => {
-            let status = upstream_resp.status();
-            let headers = upstream_resp.headers().clone();
-            println!("  ✓ Upstream responded: {}", status);
-
-            // Collect body
-            use http_body_util::BodyExt;
-            let body_bytes = match upstream_resp.collect().await {
-                Ok(collected) => {
-                    let bytes = collected.to_bytes();
-                    println!("  ✓ Received {} bytes", bytes.len());
-                    bytes
-                }
-                Err(e) => {
-                    println!("  ✗ Failed to read upstream response body: {}", e);
-                    return Ok(Response::builder()
-                        .status(StatusCode::BAD_GATEWAY)
-                        .body(Full::new(Bytes::from("Failed to read upstream response")))
-                        .unwrap());
-                }
-            };
+    // Copy end-to-end headers from original request
+    *upstream_req.headers_mut() = parts.headers.clone();
+    strip_hop_by_hop(upstream_req.headers_mut());
 
-            // Build response
-            let mut response = Response::builder().status(status);
+    let upstream_resp =
+        tokio::time::timeout(state.response_timeout, state.client.request(upstream_req)).await;
+    match upstream_resp {
+        Ok(Ok(upstream_resp)) => {
+            println!("  ✓ Upstream responded: {}", upstream_resp.status());
 
-            // Copy headers from upstream response
-            for (name, value) in headers.iter() {
-                response = response.header(name, value);
+            // Stream the response body back; hyper only polls the upstream
+            // for more data as the client reads it
+            let mut response = upstream_resp.map(|body| body.map_err(Into::into).boxed_unsync());
+            let headers = response.headers_mut();
+            strip_hop_by_hop(headers);
+
+            // Keep a copy of complete, reusable responses; a range of one
+            // is cut from the body, which is then not read to its end
+            if let (Some(content_cache), Some(path), true) = (
+                &state.content_cache,
+                cache_path,
+                parts.method == Method::GET
+                    && !parts.headers.contains_key(RANGE)
+                    && cacheable_response(&response),
+            ) {
+                let headers = with_last_modified(cached_headers(response.headers()));
+                // Send the validators later hits will send, so the client can
+                // revalidate against either
+                if let Some((_, date)) = headers
+                    .iter()
+                    .find(|(name, _)| name.as_str() == LAST_MODIFIED)
+                {
+                    if let Ok(date) = date.parse() {
+                        response.headers_mut().insert(LAST_MODIFIED, date);
+                    }
+                }
+                let store = PendingStore {
+                    cache: content_cache.clone(),
+                    target: target.clone(),
+                    path: path.to_string(),
+                    headers,
+                };
+                response = response.map(|body| CachingBody::new(body, store).boxed_unsync());
+                response
+                    .headers_mut()
+                    .insert("X-AntNS-Cache", "MISS".parse().unwrap());
             }
+            let headers = response.headers_mut();
 
             // Add custom headers
-            response = response
-                .header("X-AntNS-Domain", domain)
-                .header("X-AntNS-Target", &target)
-                .header("X-AntNS-Upstream", &full_upstream_url);
-
-            let resp = response.body(Full::new(body_bytes)).unwrap();
-            println!("  ✓ Response sent to client");
-            Ok(resp)
+            for (name, value) in [
+                ("X-AntNS-Domain", domain.as_str()),
+                ("X-AntNS-Target", target.as_str()),
+                ("X-AntNS-Upstream", full_upstream_url.as_str()),
+            ] {
+                if let Ok(value) = value.parse() {
+                    headers.insert(name, value);
+                }
+            }
+
+            // Answer the request's range and conditions if the upstream
+            // sent the full content instead
+            println!("  ✓ Streaming response to client");
+            Ok(conditional::respond(&parts, response))
         }
-        Err(e) => {
+        Ok(Err(e)) => {
             println!("  ✗ Failed to proxy to upstream: {}", e);
-            Ok(Response::builder()
-                .status(StatusCode::BAD_GATEWAY)
-                .body(Full::new(Bytes::from(format!(
-                    "Failed to proxy to upstream: {}",
-                    e
-                ))))
-                .unwrap())
+            Ok(text_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to proxy to upstream: {}", e),
+            ))
+        }
+        Err(_) => {
+            println!("  ✗ Upstream did not respond in time");
+            Ok(text_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                "Upstream did not respond in time".to_string(),
+            ))
         }
     }
 }
 
-/// Lookup domain and cache the result
-async fn lookup_and_cache(
-    state: &ProxyState,
+/// Serve a target's content from the Autonomi network
+async fn serve_native<N: NameNetwork + 'static>(
+    state: &ProxyState<N>,
+    parts: &Parts,
     domain: &str,
-) -> Result<String, Response<Full<Bytes>>> {
-    println!("  Looking up domain: {}", domain);
-    match crate::lookup_domain(&state.client, domain).await {
-        Ok(resolution) => {
-            println!("  ✓ Resolved to: {}", resolution.target);
-            let target = resolution.target.clone();
-
-            // Store in cache
-            let mut cache = state.cache.lock().await;
-            cache.insert(
-                domain.to_string(),
-                CachedLookup {
-                    target: target.clone(),
-                    timestamp: SystemTime::now(),
-                },
-            );
+    target: &str,
+) -> Response<ProxyBody> {
+    if parts.method != Method::GET && parts.method != Method::HEAD {
+        let mut response = text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Use GET or HEAD".to_string(),
+        );
+        response
+            .headers_mut()
+            .insert(ALLOW, "GET, HEAD".parse().unwrap());
+        return response;
+    }
 
-            Ok(target)
+    let path = parts.uri.path();
+    println!("  Fetching {} from the network", path);
+    match content::fetch(state.cache.client(), &state.targets, target, path).await {
+        Ok(Content::File { data, content_type }) => {
+            println!("  ✓ Serving {} bytes ({})", data.len(), content_type);
+            let headers = vec![(CONTENT_TYPE.to_string(), content_type.to_string())];
+            let content = CachedContent::new(with_last_modified(headers), data);
+            if let Some(content_cache) = state.content_cache.clone() {
+                let (key, path, content) = (target.to_string(), path.to_string(), content.clone());
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = content_cache.store(&key, &path, &content) {
+                        tracing::warn!("Failed to cache content: {:#}", e);
+                    }
+                });
+            }
+            content_response(parts, domain, target, &content, "MISS")
+        }
+        Ok(Content::Directory) => {
+            // Redirect so relative links resolve inside the directory
+            let query = parts
+                .uri
+                .query()
+                .map(|q| format!("?{}", q))
+                .unwrap_or_default();
+            Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(LOCATION, format!("{}/{}", path, query))
+                .body(full(Bytes::new()))
+                .unwrap()
+        }
+        Ok(Content::TooLarge { size }) => {
+            println!("  ✗ {} is too large to serve ({} bytes)", path, size);
+            text_response(
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "{} is too large to serve from the network ({} bytes); use an upstream gateway",
+                    path, size
+                ),
+            )
+        }
+        Ok(Content::NotFound) => {
+            println!("  ✗ Nothing at {}", path);
+            text_response(StatusCode::NOT_FOUND, format!("Not found: {}", path))
         }
         Err(e) => {
-            println!("  ✗ Lookup failed: {}", e);
-            Err(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::from(format!(
-                    "Domain not found: {}",
-                    domain
-                ))))
-                .unwrap())
+            println!("  ✗ Failed to fetch content: {:#}", e);
+            text_response(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to fetch content: {:#}", e),
+            )
         }
     }
 }
 
-/// Lookup domain without caching
-async fn lookup_domain_no_cache(
-    state: &ProxyState,
+/// Path under which the response to a request can be cached, if any
+///
+/// Only `GET` and `HEAD` are cached. Upstream responses may depend on the
+/// query string or on credentials, so those requests are always forwarded.
+fn cacheable_path<'a>(parts: &'a Parts, source: &ContentSource) -> Option<&'a str> {
+    if parts.method != Method::GET && parts.method != Method::HEAD {
+        return None;
+    }
+    let personal = parts.uri.query().is_some() || parts.headers.contains_key(AUTHORIZATION);
+    if matches!(source, ContentSource::Upstream(_)) && personal {
+        return None;
+    }
+    Some(parts.uri.path())
+}
+
+/// Whether an upstream response is the full content and the same for everyone
+fn cacheable_response(response: &Response<ProxyBody>) -> bool {
+    let headers = response.headers();
+    let no_store = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| {
+            let directive = directive.trim();
+            directive.eq_ignore_ascii_case("no-store") || directive.eq_ignore_ascii_case("private")
+        });
+
+    response.status() == StatusCode::OK && !headers.contains_key(VARY) && !no_store
+}
+
+/// Headers of a response worth keeping with its cached content
+fn cached_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    CACHED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(*name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Add a `Last-Modified` date of now unless the headers carry one
+///
+/// Content at a target never changes, so the date it was first fetched
+/// serves as a validator for `If-Modified-Since` and `If-Range`.
+fn with_last_modified(mut headers: Vec<(String, String)>) -> Vec<(String, String)> {
+    if !headers
+        .iter()
+        .any(|(name, _)| name.as_str() == LAST_MODIFIED)
+    {
+        let now = httpdate::fmt_http_date(std::time::SystemTime::now());
+        headers.push((LAST_MODIFIED.to_string(), now));
+    }
+    headers
+}
+
+/// Response with `content`, answering the request's range and conditions
+fn content_response(
+    parts: &Parts,
     domain: &str,
-) -> Result<String, Response<Full<Bytes>>> {
-    println!("  Looking up domain: {}", domain);
-    match crate::lookup_domain(&state.client, domain).await {
-        Ok(resolution) => {
-            println!("  ✓ Resolved to: {}", resolution.target);
-            Ok(resolution.target)
+    target: &str,
+    content: &CachedContent,
+    cache_status: &str,
+) -> Response<ProxyBody> {
+    let mut response = Response::builder()
+        .header(ETAG, content.etag())
+        .header("X-AntNS-Domain", domain)
+        .header("X-AntNS-Target", target)
+        .header("X-AntNS-Cache", cache_status);
+    for (name, value) in &content.headers {
+        if name.as_str() != ETAG {
+            response = response.header(name, value);
         }
-        Err(e) => {
-            println!("  ✗ Lookup failed: {}", e);
-            Err(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::from(format!(
-                    "Domain not found: {}",
-                    domain
-                ))))
-                .unwrap())
+    }
+
+    let body = if parts.method == Method::HEAD {
+        Bytes::new()
+    } else {
+        content.data.clone()
+    };
+    let response = response
+        .status(StatusCode::OK)
+        .header(CONTENT_LENGTH, content.data.len())
+        .body(full(body))
+        .unwrap();
+    conditional::respond(parts, response)
+}
+
+/// Where a [`CachingBody`] stores its content once complete
+struct PendingStore {
+    cache: ContentCache,
+    target: String,
+    path: String,
+    headers: Vec<(String, String)>,
+}
+
+/// Response body that keeps a copy of the streamed content and stores it in
+/// the content cache once the upstream has sent all of it
+///
+/// Bodies that fail, or grow beyond what the cache stores, are not cached.
+struct CachingBody {
+    inner: ProxyBody,
+    buffer: Vec<u8>,
+    store: Option<PendingStore>,
+}
+
+impl CachingBody {
+    fn new(inner: ProxyBody, store: PendingStore) -> Self {
+        let too_large = inner
+            .size_hint()
+            .exact()
+            .is_some_and(|size| size > store.cache.max_entry_bytes());
+        Self {
+            inner,
+            buffer: Vec::new(),
+            store: (!too_large).then_some(store),
         }
     }
+
+    fn finish(&mut self) {
+        let Some(store) = self.store.take() else {
+            return;
+        };
+        let content =
+            CachedContent::new(store.headers, Bytes::from(std::mem::take(&mut self.buffer)));
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = store.cache.store(&store.target, &store.path, &content) {
+                tracing::warn!("Failed to cache content: {:#}", e);
+            }
+        });
+    }
 }
 
-/// Start the HTTP proxy server on the specified port
-pub async fn run(port: u16, upstream_template: String, cache_ttl_minutes: u64) -> Result<()> {
-    let addr = format!("127.0.0.1:{}", port);
+impl Body for CachingBody {
+    type Data = Bytes;
+    type Error = BoxError;
 
-    println!("HTTP proxy starting on {}", addr);
-    println!("Upstream template: {}", upstream_template);
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, BoxError>>> {
+        let this = &mut *self;
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
 
-    let cache_ttl = Duration::from_secs(cache_ttl_minutes * 60);
-    if cache_ttl_minutes > 0 {
-        println!("Cache TTL: {} minutes", cache_ttl_minutes);
-    } else {
-        println!("Cache: disabled");
+        match &frame {
+            Some(Ok(frame)) => {
+                if let (Some(store), Some(data)) = (&this.store, frame.data_ref()) {
+                    if (this.buffer.len() + data.len()) as u64 > store.cache.max_entry_bytes() {
+                        this.store = None;
+                        this.buffer = Vec::new();
+                    } else {
+                        this.buffer.extend_from_slice(data);
+                    }
+                }
+                // hyper stops polling once the body reports its end
+                if this.inner.is_end_stream() {
+                    this.finish();
+                }
+            }
+            Some(Err(_)) => {
+                this.store = None;
+                this.buffer = Vec::new();
+            }
+            None => this.finish(),
+        }
+
+        Poll::Ready(frame)
     }
 
-    println!("Initializing Autonomi client...");
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
 
-    // Initialize Autonomi client
-    let client = Client::init()
-        .await
-        .context("Failed to initialize Autonomi client")?;
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
 
-    println!("✓ Autonomi client initialized");
+/// Remove hop-by-hop headers, including those named in `Connection`
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
 
-    let state = Arc::new(ProxyState {
-        client,
-        upstream_template,
-        cache: Mutex::new(HashMap::new()),
-        cache_ttl,
-    });
+    for name in listed {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(*name);
+    }
+}
 
-    let listener = TcpListener::bind(&addr)
-        .await
-        .context("Failed to bind HTTP proxy socket")?;
+/// Body holding `data` in full
+pub(crate) fn full(data: Bytes) -> ProxyBody {
+    Full::new(data)
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+/// Plain-text response generated by the proxy itself
+fn text_response(status: StatusCode, message: String) -> Response<ProxyBody> {
+    Response::builder()
+        .status(status)
+        .body(full(Bytes::from(message)))
+        .unwrap()
+}
+
+/// Response for a name without a content address
+fn not_found(name: &crate::DomainName) -> Response<ProxyBody> {
+    text_response(StatusCode::NOT_FOUND, format!("Domain not found: {}", name))
+}
+
+/// Start the HTTP proxy server on `port` of every address in `addresses`,
+/// and the HTTPS listener if given
+#[allow(clippy::too_many_arguments)]
+pub async fn run<N: NameNetwork + 'static>(
+    addresses: &[IpAddr],
+    port: u16,
+    https: Option<HttpsListener>,
+    source: ContentSource,
+    upstream_options: UpstreamOptions,
+    content_cache: Option<ContentCache>,
+    cache: Arc<ResolutionCache<N>>,
+    access_list: Arc<AccessList>,
+) -> Result<()> {
+    let addrs: Vec<SocketAddr> = addresses
+        .iter()
+        .map(|ip| SocketAddr::new(*ip, port))
+        .collect();
+
+    for addr in &addrs {
+        println!("HTTP proxy starting on {}", addr);
+    }
+    match &source {
+        ContentSource::Network => println!("Content source: Autonomi network"),
+        ContentSource::Upstream(template) => println!("Upstream template: {}", template),
+    }
+    if let Some(content_cache) = &content_cache {
+        println!("Content cache: {}", content_cache.dir().display());
+    }
+    println!("Allowed clients: {}", access_list);
+
+    let state = Arc::new(ProxyState::new(
+        cache,
+        source,
+        &upstream_options,
+        content_cache,
+        access_list,
+    ));
 
-    println!("✓ HTTP proxy listening on http://{}\n", addr);
+    let mut listeners = Vec::new();
+    for addr in &addrs {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind HTTP proxy socket on {}", addr))?;
+        println!("✓ HTTP proxy listening on http://{}", addr);
+        listeners.push((listener, None));
+    }
+    if let Some(https) = https {
+        let acceptor = TlsAcceptor::from(https.ca.server_config(PROXY_ALPN));
+        for ip in addresses {
+            let addr = SocketAddr::new(*ip, https.port);
+            let listener = TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to bind HTTPS proxy socket on {}", addr))?;
+            println!("✓ HTTPS proxy listening on https://{}", addr);
+            listeners.push((listener, Some(acceptor.clone())));
+        }
+    }
+    println!();
+
+    futures::future::join_all(
+        listeners
+            .into_iter()
+            .map(|(listener, tls)| serve(listener, tls, state.clone())),
+    )
+    .await;
 
+    Ok(())
+}
+
+/// Accept connections on one listener, with TLS if `tls` is given
+async fn serve<N: NameNetwork + 'static>(
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    state: Arc<ProxyState<N>>,
+) {
     loop {
         let (stream, remote_addr) = match listener.accept().await {
             Ok(conn) => conn,
             Err(e) => {
+                // Retrying at once would spin while the error lasts
                 tracing::error!("Failed to accept connection: {}", e);
+                tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
                 continue;
             }
         };
 
+        if !state.access_list.allows(remote_addr.ip()) {
+            tracing::warn!("Refused HTTP connection from {}", remote_addr);
+            continue;
+        }
+
         let state = state.clone();
+        let tls = tls.clone();
 
         tokio::spawn(async move {
-            let service = service_fn(move |req| {
-                let state = state.clone();
-                handle_request(state, req)
-            });
+            let Some(acceptor) = tls else {
+                return serve_connection(TokioIo::new(stream), state, remote_addr).await;
+            };
+
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => serve_connection(TokioIo::new(stream), state, remote_addr).await,
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", remote_addr),
+            }
+        });
+    }
+}
+
+/// Serve HTTP/1.1 or HTTP/2 requests on one connection
+async fn serve_connection<N, I>(io: I, state: Arc<ProxyState<N>>, remote_addr: SocketAddr)
+where
+    N: NameNetwork + 'static,
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    let service = service_fn(move |req| {
+        let state = state.clone();
+        handle_request(state, req)
+    });
 
-            let io = TokioIo::new(stream);
+    if let Err(e) = auto::Builder::new(TokioExecutor::new())
+        .serve_connection(io, service)
+        .await
+    {
+        tracing::error!("Connection error from {}: {}", remote_addr, e);
+    }
+}
 
-            if let Err(e) = http1::Builder::new().serve_connection(io, service).await {
-                tracing::error!("Connection error from {}: {}", remote_addr, e);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MemoryNetwork;
+    use crate::testing::{register, TempDir};
+    use futures::channel::mpsc;
+    use futures::SinkExt;
+    use http_body_util::StreamBody;
+    use hyper::body::{Frame, Incoming};
+    use hyper::header::{ACCEPT_RANGES, CONTENT_RANGE};
+    use hyper::server::conn::http1;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpStream;
+
+    /// Upstream that streams the request body straight back, and the number
+    /// of connections it accepted
+    async fn echo_upstream() -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let service = service_fn(|req: Request<Incoming>| async move {
+                        let mut seen: Vec<&str> =
+                            req.headers().keys().map(HeaderName::as_str).collect();
+                        seen.sort_unstable();
+                        let response = Response::builder()
+                            .header("x-seen", seen.join(","))
+                            .header("proxy-authenticate", "Basic")
+                            .header("x-upstream", "1")
+                            .body(req.into_body())
+                            .unwrap();
+                        Ok::<_, Infallible>(response)
+                    });
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
             }
         });
+        (addr, connections)
+    }
+
+    /// Proxy state for `test.ant` pointing at `target`
+    async fn proxy_state(
+        network: MemoryNetwork,
+        target: &str,
+        source: ContentSource,
+        content_cache: Option<ContentCache>,
+    ) -> Arc<ProxyState<MemoryNetwork>> {
+        register(&network, "test.ant", target).await;
+
+        Arc::new(ProxyState::new(
+            Arc::new(ResolutionCache::new(
+                network,
+                Default::default(),
+                Duration::from_secs(60),
+            )),
+            source,
+            &UpstreamOptions::default(),
+            content_cache,
+            Arc::new(AccessList::loopback()),
+        ))
+    }
+
+    /// Proxy state for `test.ant`, whose content lives at `upstream`
+    async fn upstream_state(upstream: SocketAddr) -> Arc<ProxyState<MemoryNetwork>> {
+        let source = ContentSource::Upstream(format!("http://{}/$ADDRESS", upstream));
+        proxy_state(MemoryNetwork::new(), &"ab".repeat(32), source, None).await
+    }
+
+    #[tokio::test]
+    async fn test_streams_bodies() {
+        let (upstream, _) = echo_upstream().await;
+        let state = upstream_state(upstream).await;
+
+        let (mut sender, receiver) = mpsc::channel::<Result<Frame<Bytes>, Infallible>>(1);
+        let req = Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header("host", "test.ant")
+            .header("connection", "x-hop")
+            .header("x-hop", "1")
+            .header("proxy-authorization", "Basic secret")
+            .header("x-end", "1")
+            .body(StreamBody::new(receiver))
+            .unwrap();
+
+        let response = handle_request(state, req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["x-antns-target"],
+            "ab".repeat(32).as_str()
+        );
+        assert_eq!(response.headers()["x-upstream"], "1");
+        assert!(!response.headers().contains_key("proxy-authenticate"));
+        let seen = response.headers()["x-seen"].to_str().unwrap();
+        assert!(seen.split(',').any(|name| name == "x-end"), "{}", seen);
+        for stripped in ["connection", "x-hop", "proxy-authorization"] {
+            assert!(!seen.split(',').any(|name| name == stripped), "{}", seen);
+        }
+
+        // Each chunk comes back before the next one is sent, so neither body
+        // is buffered in full
+        let mut body = response.into_body();
+        for chunk in ["first", "second", "third"] {
+            sender
+                .send(Ok(Frame::data(Bytes::from(chunk))))
+                .await
+                .unwrap();
+            let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+                .await
+                .expect("chunk was not streamed back")
+                .unwrap()
+                .unwrap();
+            assert_eq!(frame.into_data().unwrap(), chunk);
+        }
+
+        drop(sender);
+        let end = tokio::time::timeout(Duration::from_secs(5), body.frame())
+            .await
+            .unwrap();
+        assert!(end.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http2_and_connection_reuse() {
+        let (upstream, connections) = echo_upstream().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, None, upstream_state(upstream).await));
+
+        // HTTP/2 with prior knowledge; the host is only in :authority
+        let stream = TokioIo::new(TcpStream::connect(proxy).await.unwrap());
+        let (mut h2, conn) = hyper::client::conn::http2::handshake(TokioExecutor::new(), stream)
+            .await
+            .unwrap();
+        tokio::spawn(conn);
+        for path in ["/a", "/b"] {
+            let req = Request::builder()
+                .uri(format!("http://test.ant{}", path))
+                .body(Full::new(Bytes::from(path)))
+                .unwrap();
+            let response = h2.send_request(req).await.unwrap();
+            assert_eq!(response.version(), hyper::Version::HTTP_2);
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, path);
+        }
+
+        // HTTP/1.1 on the same port
+        let stream = TokioIo::new(TcpStream::connect(proxy).await.unwrap());
+        let (mut h1, conn) = hyper::client::conn::http1::handshake(stream).await.unwrap();
+        tokio::spawn(conn);
+        let req = Request::builder()
+            .uri("/c")
+            .header(HOST, "test.ant")
+            .body(Full::new(Bytes::from("c")))
+            .unwrap();
+        let response = h1.send_request(req).await.unwrap();
+        assert_eq!(response.version(), hyper::Version::HTTP_11);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // All three requests went over one pooled upstream connection
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_serves_from_network() {
+        let network = MemoryNetwork::new();
+        let site = network.insert_archive(&[
+            ("index.html", b"<h1>home</h1>"),
+            ("docs/index.html", b"docs"),
+        ]);
+        let state = proxy_state(network, &hex::encode(site), ContentSource::Network, None).await;
+
+        let request = |method: &str, path: &str| {
+            Request::builder()
+                .method(method)
+                .uri(path)
+                .header(HOST, "test.ant")
+                .body(Full::new(Bytes::new()))
+                .unwrap()
+        };
+
+        let response = handle_request(state.clone(), request("GET", "/"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(response.headers()[CONTENT_LENGTH], "13");
+        assert_eq!(response.headers()["x-antns-target"], hex::encode(site));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "<h1>home</h1>");
+
+        let response = handle_request(state.clone(), request("HEAD", "/"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_LENGTH], "13");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+
+        let response = handle_request(state.clone(), request("GET", "/docs?page=2"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[LOCATION], "/docs/?page=2");
+
+        for (method, path, status) in [
+            ("GET", "/docs/", StatusCode::OK),
+            ("GET", "/missing.png", StatusCode::NOT_FOUND),
+            ("POST", "/", StatusCode::METHOD_NOT_ALLOWED),
+        ] {
+            let response = handle_request(state.clone(), request(method, path))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{} {}", method, path);
+        }
+    }
+
+    /// Upstream serving the same page for every path, and the number of
+    /// requests it answered
+    async fn page_upstream() -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let answered = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let answered = answered.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |_: Request<Incoming>| {
+                        answered.fetch_add(1, Ordering::SeqCst);
+                        async {
+                            let response = Response::builder()
+                                .header(CONTENT_TYPE, "text/html")
+                                .header("x-upstream", "1")
+                                .header(ETAG, "\"upstream-1\"")
+                                .body(Full::new(Bytes::from_static(b"<p>page</p>")))
+                                .unwrap();
+                            Ok::<_, Infallible>(response)
+                        }
+                    });
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        (addr, requests)
+    }
+
+    fn get(path: &str, headers: &[(&str, &str)]) -> Request<Full<Bytes>> {
+        let mut request = Request::builder().uri(path).header(HOST, "test.ant");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(Full::new(Bytes::new())).unwrap()
+    }
+
+    /// Wait for a background store into the content cache
+    async fn cached(cache: &ContentCache, target: &str, path: &str) -> CachedContent {
+        for _ in 0..100 {
+            if let Some(content) = cache.get(target, path) {
+                return content;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{} was not cached", path);
+    }
+
+    #[tokio::test]
+    async fn test_caches_content() {
+        let dir = TempDir::new("proxy-content");
+        let content_cache = ContentCache::new(dir.path(), u64::MAX);
+        let target = "ab".repeat(32);
+
+        let (upstream, requests) = page_upstream().await;
+        let source = ContentSource::Upstream(format!("http://{}/$ADDRESS", upstream));
+        let state = proxy_state(
+            MemoryNetwork::new(),
+            &target,
+            source,
+            Some(content_cache.clone()),
+        )
+        .await;
+
+        let response = handle_request(state.clone(), get("/page", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-antns-cache"], "MISS");
+        let miss_headers = response.headers().clone();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "<p>page</p>");
+        let etag = cached(&content_cache, &target, "/page").await.etag();
+        assert_eq!(miss_headers[ETAG], etag.as_str());
+
+        // Served locally from now on, with the validators of the miss
+        let response = handle_request(state.clone(), get("/page", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-antns-cache"], "HIT");
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+        assert_eq!(response.headers().get_all(ETAG).iter().count(), 1);
+        assert_eq!(
+            response.headers()[LAST_MODIFIED],
+            miss_headers[LAST_MODIFIED]
+        );
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/html");
+        assert!(!response.headers().contains_key("x-upstream"));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "<p>page</p>");
+
+        let response = handle_request(state.clone(), get("/page", &[("if-none-match", &etag)]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let response = handle_request(
+            state.clone(),
+            get("/page", &[("if-none-match", "\"other\"")]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Query strings may change the upstream's answer
+        let response = handle_request(state.clone(), get("/page?v=2", &[]))
+            .await
+            .unwrap();
+        assert!(!response.headers().contains_key("x-antns-cache"));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Native content gets the same ETag handling
+        let network = MemoryNetwork::new();
+        let page = network.insert_data(Bytes::from_static(b"<html>native"));
+        let state = proxy_state(
+            network,
+            &hex::encode(page),
+            ContentSource::Network,
+            Some(content_cache.clone()),
+        )
+        .await;
+        let response = handle_request(state.clone(), get("/", &[])).await.unwrap();
+        assert_eq!(response.headers()["x-antns-cache"], "MISS");
+        let etag = response.headers()[ETAG].to_str().unwrap().to_string();
+        let response = handle_request(
+            state.clone(),
+            get("/", &[("if-none-match", &format!("W/{}", etag))]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        cached(&content_cache, &hex::encode(page), "/").await;
+        let response = handle_request(state, get("/", &[])).await.unwrap();
+        assert_eq!(response.headers()["x-antns-cache"], "HIT");
+    }
+
+    #[tokio::test]
+    async fn test_serves_ranges_and_conditionals() {
+        let dir = TempDir::new("proxy-ranges");
+        let content_cache = ContentCache::new(dir.path(), u64::MAX);
+        let target = "ab".repeat(32);
+
+        // The upstream ignores ranges; the proxy cuts them out of its answer
+        let (upstream, requests) = page_upstream().await;
+        let source = ContentSource::Upstream(format!("http://{}/$ADDRESS", upstream));
+        let state = proxy_state(
+            MemoryNetwork::new(),
+            &target,
+            source,
+            Some(content_cache.clone()),
+        )
+        .await;
+        let response = handle_request(state.clone(), get("/page", &[("range", "bytes=3-6")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 3-6/11");
+        assert!(!response.headers().contains_key("x-antns-cache"));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "page");
+
+        // Cached content answers ranges and conditions locally
+        let response = handle_request(state.clone(), get("/page", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
+        response.into_body().collect().await.unwrap();
+        let content = cached(&content_cache, &target, "/page").await;
+        let last_modified = &content
+            .headers
+            .iter()
+            .find(|(name, _)| name == "last-modified")
+            .unwrap()
+            .1;
+
+        let response = handle_request(state.clone(), get("/page", &[("range", "bytes=0-2,-4")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["x-antns-cache"], "HIT");
+        let content_type = response.headers()[CONTENT_TYPE].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("Content-Range: bytes 0-2/11\r\n\r\n<p>"));
+        assert!(body.contains("Content-Range: bytes 7-10/11\r\n\r\n</p>"));
+
+        let response = handle_request(
+            state.clone(),
+            get("/page", &[("if-modified-since", last_modified)]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let response = handle_request(
+            state.clone(),
+            get(
+                "/page",
+                &[("range", "bytes=5-"), ("if-range", &content.etag())],
+            ),
+        )
+        .await
+        .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "ge</p>");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Native content, without a content cache
+        let network = MemoryNetwork::new();
+        let page = network.insert_data(Bytes::from_static(b"<html>native"));
+        let state = proxy_state(network, &hex::encode(page), ContentSource::Network, None).await;
+        let response = handle_request(state.clone(), get("/", &[("range", "bytes=-6")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_LENGTH], "6");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "native");
+
+        let response = handle_request(state.clone(), get("/", &[("range", "bytes=50-")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes */12");
+    }
+
+    #[test]
+    fn test_strip_hop_by_hop() {
+        let mut headers = HeaderMap::new();
+        headers.insert("connection", "keep-alive, X-Trace".parse().unwrap());
+        headers.insert("keep-alive", "timeout=5".parse().unwrap());
+        headers.insert("x-trace", "1".parse().unwrap());
+        headers.insert("transfer-encoding", "chunked".parse().unwrap());
+        headers.insert("upgrade", "websocket".parse().unwrap());
+        headers.insert("content-type", "text/html".parse().unwrap());
+
+        strip_hop_by_hop(&mut headers);
+
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers["content-type"], "text/html");
     }
 }
@@ -0,0 +1,378 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Local certificate authority for HTTPS on AntNS names
+//!
+//! Browsers only grant secure-context features (service workers, clipboard,
+//! secure cookies) to `https://` origins, and no public CA issues
+//! certificates for `.ant`. On first use a root CA is generated and stored
+//! in the data directory. Once the user trusts it, the proxy's HTTPS listener
+//! presents a leaf certificate for whichever AntNS name the browser asks for
+//! via SNI. Leaf certificates are issued on demand, cached in memory and on
+//! disk, and reissued after [`LEAF_RENEW_AFTER`].
+//!
+//! The CA carries name constraints, so clients that enforce them reject
+//! anything it signs outside `.ant` and `.autonomi`.
+
+use crate::{DomainName, DOMAIN_SUFFIX, DOMAIN_SUFFIX_ALT};
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, GeneralSubtree, IsCa, KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::crypto::ring::sign::any_supported_type;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::ServerConfig;
+
+/// File name of the CA certificate users import
+const CA_CERT_FILE: &str = "antns-local-ca.pem";
+
+/// File name of the CA private key
+const CA_KEY_FILE: &str = "antns-local-ca.key";
+
+/// Directory of cached leaf certificates
+const LEAF_DIR: &str = "certs";
+
+/// Common name of the CA, as shown in certificate managers
+const CA_NAME: &str = "AntNS Local CA";
+
+/// Validity of the CA certificate
+const CA_VALIDITY_DAYS: i64 = 3650;
+
+/// Validity of leaf certificates; browsers reject longer-lived ones
+const LEAF_VALIDITY_DAYS: i64 = 397;
+
+/// Age after which a cached leaf certificate is replaced
+const LEAF_RENEW_AFTER: Duration = Duration::from_secs(300 * 24 * 60 * 60);
+
+/// Root CA that issues certificates for AntNS names
+pub struct LocalCa {
+    dir: PathBuf,
+    created: bool,
+    issuer: Certificate,
+    key: KeyPair,
+    leaves: Mutex<HashMap<String, (SystemTime, Arc<CertifiedKey>)>>,
+}
+
+impl LocalCa {
+    /// Load the CA stored in `dir`, creating it on first use
+    pub fn open_or_create(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+        let created = !key_path.exists();
+
+        let key = if created {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            // Leaves signed by a previous key would no longer verify
+            let leaf_dir = dir.join(LEAF_DIR);
+            if leaf_dir.exists() {
+                fs::remove_dir_all(&leaf_dir)
+                    .with_context(|| format!("Failed to remove {}", leaf_dir.display()))?;
+            }
+            let key = KeyPair::generate().context("Failed to generate CA key")?;
+            write_private(&key_path, &key.serialize_pem())?;
+            key
+        } else {
+            let pem = fs::read_to_string(&key_path)
+                .with_context(|| format!("Failed to read {}", key_path.display()))?;
+            KeyPair::from_pem(&pem)
+                .with_context(|| format!("Invalid CA key in {}", key_path.display()))?
+        };
+
+        // Leaves only reference the CA by name and key, so the issuer is
+        // rebuilt from the stored key instead of parsing the certificate; a
+        // missing certificate file is rebuilt the same way
+        let issuer = ca_params()?
+            .self_signed(&key)
+            .context("Failed to create CA certificate")?;
+        if created || !cert_path.exists() {
+            fs::write(&cert_path, issuer.pem())
+                .with_context(|| format!("Failed to write {}", cert_path.display()))?;
+        }
+
+        Ok(Self {
+            dir,
+            created,
+            issuer,
+            key,
+            leaves: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Load or create the CA in the default directory
+    pub fn open_default() -> Result<Self> {
+        Self::open_or_create(get_local_ca_dir()?)
+    }
+
+    /// Whether the CA key was created by this process and is not trusted yet
+    pub fn is_new(&self) -> bool {
+        self.created
+    }
+
+    /// Path of the CA certificate to trust
+    pub fn cert_path(&self) -> PathBuf {
+        self.dir.join(CA_CERT_FILE)
+    }
+
+    /// Certificate and key for an AntNS name, issued if necessary
+    pub fn certificate_for(&self, name: &str) -> Result<Arc<CertifiedKey>> {
+        let name = DomainName::parse(name)?.to_string();
+        let mut leaves = self.leaves.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((issued, certified)) = leaves.get(&name) {
+            if !expired(*issued) {
+                return Ok(certified.clone());
+            }
+        }
+
+        let path = self.dir.join(LEAF_DIR).join(format!("{}.pem", name));
+        let (issued, cert, key) = match load_leaf(&path) {
+            Some(leaf) => leaf,
+            None => {
+                let (cert, key) = self.issue(&name, &path)?;
+                (SystemTime::now(), cert, key)
+            }
+        };
+
+        let signing_key = any_supported_type(&key).context("Unsupported leaf key")?;
+        let certified = Arc::new(CertifiedKey::new(vec![cert], signing_key));
+        leaves.insert(name, (issued, certified.clone()));
+        Ok(certified)
+    }
+
+    /// Issue a leaf certificate for `name` and cache it at `path`
+    fn issue(
+        &self,
+        name: &str,
+        path: &Path,
+    ) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>)> {
+        let key = KeyPair::generate().context("Failed to generate certificate key")?;
+        let mut params = CertificateParams::new(vec![name.to_string()])
+            .with_context(|| format!("Invalid certificate name '{}'", name))?;
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+        set_validity(&mut params, LEAF_VALIDITY_DAYS);
+
+        let cert = params
+            .signed_by(&key, &self.issuer, &self.key)
+            .with_context(|| format!("Failed to issue certificate for '{}'", name))?;
+        tracing::info!("Issued certificate for {}", name);
+
+        // A failed write only costs a reissue next time
+        let pem = format!("{}{}", cert.pem(), key.serialize_pem());
+        let cached = fs::create_dir_all(self.dir.join(LEAF_DIR))
+            .map_err(anyhow::Error::from)
+            .and_then(|()| write_private(path, &pem));
+        if let Err(e) = cached {
+            tracing::warn!("Failed to cache certificate for {}: {:#}", name, e);
+        }
+
+        let key = PrivateKeyDer::Pkcs8(key.serialize_der().into());
+        Ok((cert.der().clone(), key))
+    }
+
+    /// TLS configuration presenting certificates from this CA
+    pub fn server_config(self: Arc<Self>, alpn: &[&[u8]]) -> Arc<ServerConfig> {
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(self);
+        config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+        Arc::new(config)
+    }
+
+    /// How to make the operating system and browsers trust this CA
+    pub fn trust_instructions(&self) -> String {
+        let cert = self.cert_path().display().to_string();
+        let key = self.dir.join(CA_KEY_FILE).display().to_string();
+        [
+            "Trust the local CA to use https:// with .ant sites:".to_string(),
+            String::new(),
+            "  macOS:".to_string(),
+            format!(
+                "    sudo security add-trusted-cert -d -r trustRoot -k /Library/Keychains/System.keychain \"{}\"",
+                cert
+            ),
+            "  Debian/Ubuntu:".to_string(),
+            format!(
+                "    sudo cp \"{}\" /usr/local/share/ca-certificates/antns-local-ca.crt && sudo update-ca-certificates",
+                cert
+            ),
+            "  Fedora:".to_string(),
+            format!(
+                "    sudo cp \"{}\" /etc/pki/ca-trust/source/anchors/ && sudo update-ca-trust",
+                cert
+            ),
+            "  Windows (as administrator):".to_string(),
+            format!("    certutil -addstore -f ROOT \"{}\"", cert),
+            "  Firefox (own certificate store):".to_string(),
+            "    Settings → Privacy & Security → View Certificates → Authorities → Import".to_string(),
+            String::new(),
+            format!(
+                "Keep {} private: whoever holds it can impersonate .ant sites to this machine.",
+                key
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+impl std::fmt::Debug for LocalCa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalCa").field("dir", &self.dir).finish()
+    }
+}
+
+impl ResolvesServerCert for LocalCa {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let name = client_hello.server_name()?;
+        match self.certificate_for(name) {
+            Ok(certified) => Some(certified),
+            Err(e) => {
+                tracing::debug!("No certificate for '{}': {:#}", name, e);
+                None
+            }
+        }
+    }
+}
+
+/// Get the directory where the local CA and its certificates are stored
+pub fn get_local_ca_dir() -> Result<PathBuf> {
+    let home = directories::BaseDirs::new().context("Failed to determine home directory")?;
+
+    Ok(home.data_local_dir().join("antns").join("local-ca"))
+}
+
+/// Parameters of the CA certificate
+fn ca_params() -> Result<CertificateParams> {
+    let mut params = CertificateParams::new(Vec::new()).context("Invalid CA parameters")?;
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, CA_NAME);
+    name.push(DnType::OrganizationName, "AntNS");
+    params.distinguished_name = name;
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: [DOMAIN_SUFFIX, DOMAIN_SUFFIX_ALT]
+            .iter()
+            .map(|suffix| GeneralSubtree::DnsName(suffix[1..].to_string()))
+            .collect(),
+        excluded_subtrees: Vec::new(),
+    });
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    set_validity(&mut params, CA_VALIDITY_DAYS);
+    Ok(params)
+}
+
+/// Make a certificate valid from yesterday (tolerating clock skew) for `days`
+fn set_validity(params: &mut CertificateParams, days: i64) {
+    let date = |offset: i64| {
+        let date = (chrono::Utc::now() + chrono::Duration::days(offset)).date_naive();
+        rcgen::date_time_ymd(date.year(), date.month() as u8, date.day() as u8)
+    };
+    params.not_before = date(-1);
+    params.not_after = date(days);
+}
+
+/// Whether a leaf issued at `issued` should be replaced
+fn expired(issued: SystemTime) -> bool {
+    issued.elapsed().is_ok_and(|age| age > LEAF_RENEW_AFTER)
+}
+
+/// A cached leaf certificate, unless it is missing, unreadable or due for renewal
+fn load_leaf(path: &Path) -> Option<(SystemTime, CertificateDer<'static>, PrivateKeyDer<'static>)> {
+    let issued = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    if expired(issued) {
+        return None;
+    }
+
+    let pem = fs::read(path).ok()?;
+    let cert = rustls_pemfile::certs(&mut BufReader::new(&pem[..]))
+        .next()?
+        .ok()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(&pem[..])).ok()??;
+    Some((issued, cert, key))
+}
+
+/// Write a file only the current user can read
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    std::io::Write::write_all(&mut file, contents.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_issues_and_caches_certificates() {
+        let dir = TempDir::new("local-ca");
+
+        let ca = LocalCa::open_or_create(dir.path()).unwrap();
+        assert!(ca.is_new());
+        assert!(ca.cert_path().exists());
+        let ca_pem = fs::read_to_string(ca.cert_path()).unwrap();
+
+        let first = ca.certificate_for("WWW.MySite.ant.").unwrap();
+        let again = ca.certificate_for("www.mysite.ant").unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(dir
+            .path()
+            .join(LEAF_DIR)
+            .join("www.mysite.ant.pem")
+            .exists());
+
+        for rejected in ["example.com", "../../etc.ant", "localhost"] {
+            assert!(ca.certificate_for(rejected).is_err(), "{}", rejected);
+        }
+
+        // Reopening keeps the trusted CA and serves the cached leaf
+        let reopened = LocalCa::open_or_create(dir.path()).unwrap();
+        assert!(!reopened.is_new());
+        assert_eq!(fs::read_to_string(reopened.cert_path()).unwrap(), ca_pem);
+        let cached = reopened.certificate_for("www.mysite.ant").unwrap();
+        assert_eq!(cached.cert, first.cert);
+
+        // A missing certificate is rebuilt from the existing key
+        fs::remove_file(ca.cert_path()).unwrap();
+        let rebuilt = LocalCa::open_or_create(dir.path()).unwrap();
+        assert!(!rebuilt.is_new());
+        assert!(rebuilt.cert_path().exists());
+        let cached = rebuilt.certificate_for("www.mysite.ant").unwrap();
+        assert_eq!(cached.cert, first.cert);
+
+        // A new key makes every cached leaf worthless
+        fs::remove_file(dir.path().join(CA_KEY_FILE)).unwrap();
+        let replaced = LocalCa::open_or_create(dir.path()).unwrap();
+        assert!(replaced.is_new());
+        assert_ne!(fs::read_to_string(replaced.cert_path()).unwrap(), ca_pem);
+        let reissued = replaced.certificate_for("www.mysite.ant").unwrap();
+        assert_ne!(reissued.cert, first.cert);
+    }
+}
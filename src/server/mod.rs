@@ -3,10 +3,25 @@
 
 //! DNS resolver and HTTP proxy server
 
+pub mod acl;
+pub mod cache;
+pub mod conditional;
+pub mod content;
 pub mod dns;
+pub mod doh;
+pub mod dot;
+pub mod forward;
 pub mod http;
+pub mod local_ca;
 pub mod resolver_setup;
+pub mod tls;
+pub mod watcher;
 
-pub use dns::run as run_dns;
-pub use http::run as run_http;
+pub use acl::AccessList;
+pub use cache::{CacheStats, ResolutionCache, SharedResult};
+pub use dns::{run as run_dns, AntDnsHandler, TlsListeners};
+pub use forward::Forwarder;
+pub use http::{run as run_http, ContentSource, HttpsListener, UpstreamOptions};
+pub use local_ca::LocalCa;
 pub use resolver_setup::{check_resolver_config, setup_resolver_config};
+pub use watcher::RegisterWatcher;
@@ -0,0 +1,54 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! TLS configuration for the encrypted DNS listeners and the HTTPS proxy
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::ServerConfig;
+
+/// ALPN protocols offered by the DNS-over-HTTPS listener
+pub const DOH_ALPN: &[&[u8]] = &[b"h2", b"http/1.1"];
+
+/// ALPN protocols offered by the HTTPS proxy listener
+pub const PROXY_ALPN: &[&[u8]] = &[b"h2", b"http/1.1"];
+
+/// ALPN protocol of DNS-over-TLS (RFC 7858)
+pub const DOT_ALPN: &[&[u8]] = &[b"dot"];
+
+/// Server configuration from a PEM certificate chain and private key
+pub fn load_server_config(
+    cert_path: &Path,
+    key_path: &Path,
+    alpn: &[&[u8]],
+) -> Result<Arc<ServerConfig>> {
+    let mut cert_reader = BufReader::new(
+        File::open(cert_path)
+            .with_context(|| format!("Failed to open certificate {}", cert_path.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut cert_reader)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read certificate {}", cert_path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in {}", cert_path.display());
+    }
+
+    let mut key_reader = BufReader::new(
+        File::open(key_path)
+            .with_context(|| format!("Failed to open private key {}", key_path.display()))?,
+    );
+    let key = rustls_pemfile::private_key(&mut key_reader)
+        .with_context(|| format!("Failed to read private key {}", key_path.display()))?
+        .with_context(|| format!("No private key found in {}", key_path.display()))?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid certificate or private key")?;
+    config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+    Ok(Arc::new(config))
+}
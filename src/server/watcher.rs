@@ -0,0 +1,332 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Background refresh of cached names whose registers changed
+//!
+//! Without the watcher, an owner's update only reaches the servers once the
+//! cached resolution expires. [`RegisterWatcher`] polls the register heads
+//! of pinned names and of the most recently used cached names. When a head
+//! moves, the name is resolved again: the new entries are verified like any
+//! lookup, and the cached resolution is only replaced once that succeeds.
+//! Pinned names are also kept resolved when they drop out of the cache.
+
+use crate::constants::{DEFAULT_LOOKUP_CONCURRENCY, DEFAULT_WATCH_MAX_HOT};
+use crate::network::NameNetwork;
+use crate::register::name::DomainName;
+use crate::server::cache::ResolutionCache;
+use anyhow::Result;
+use autonomi::register::RegisterValue;
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Polls register heads and refreshes names whose registers changed
+pub struct RegisterWatcher<N: NameNetwork> {
+    cache: Arc<ResolutionCache<N>>,
+    interval: Duration,
+    pins: Vec<DomainName>,
+    max_hot: usize,
+    /// Register heads each watched name was last resolved at
+    heads: HashMap<String, Vec<Option<RegisterValue>>>,
+}
+
+impl<N: NameNetwork + 'static> RegisterWatcher<N> {
+    /// Watcher refreshing names in `cache`, polling every `interval`
+    pub fn new(cache: Arc<ResolutionCache<N>>, interval: Duration) -> Self {
+        Self {
+            cache,
+            interval,
+            pins: Vec::new(),
+            max_hot: DEFAULT_WATCH_MAX_HOT,
+            heads: HashMap::new(),
+        }
+    }
+
+    /// Always watch `pins` and keep them resolved
+    pub fn with_pins(mut self, pins: Vec<DomainName>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Watch at most `max_hot` of the most recently used cached names
+    pub fn with_max_hot(mut self, max_hot: usize) -> Self {
+        self.max_hot = max_hot;
+        self
+    }
+
+    /// Poll forever
+    pub async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let refreshed = self.poll().await;
+            if refreshed > 0 {
+                tracing::info!("Register watcher refreshed {} name(s)", refreshed);
+            }
+        }
+    }
+
+    /// Check every watched name once, returning how many were refreshed
+    pub async fn poll(&mut self) -> usize {
+        let mut watched: Vec<String> = self.pins.iter().map(ToString::to_string).collect();
+        let pinned: HashSet<String> = watched.iter().cloned().collect();
+        watched.extend(
+            self.cache
+                .hot_names(self.max_hot)
+                .into_iter()
+                .filter(|name| !pinned.contains(name)),
+        );
+        // Forget names that are no longer watched
+        self.heads.retain(|name, _| watched.contains(name));
+
+        let checks: Vec<(String, Option<Checked>)> = futures::stream::iter(watched)
+            .map(|name| async {
+                let changed = self.check(&name, pinned.contains(&name)).await;
+                (name, changed)
+            })
+            .buffer_unordered(DEFAULT_LOOKUP_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut refreshed = 0;
+        for (name, checked) in checks {
+            let Some(checked) = checked else { continue };
+            if checked.refreshed {
+                refreshed += 1;
+            }
+            self.heads.insert(name, checked.heads);
+        }
+        refreshed
+    }
+
+    /// Refresh a name if its registers moved since it was last seen
+    ///
+    /// Returns the heads the name's resolution now reflects, or `None` if
+    /// the name is not cached or the check failed.
+    async fn check(&self, name: &str, pinned: bool) -> Option<Checked> {
+        match self.try_check(name, pinned).await {
+            Ok(heads) => heads,
+            Err(e) => {
+                // The cached resolution stays in place; try again next poll
+                tracing::warn!("Failed to check register of '{}': {:#}", name, e);
+                None
+            }
+        }
+    }
+
+    async fn try_check(&self, name: &str, pinned: bool) -> Result<Option<Checked>> {
+        let domain = DomainName::parse(name)?;
+        let (resolved, refreshed) = match self.cache.peek(name) {
+            Some(resolved) => (resolved, false),
+            // Expired or evicted: pins are resolved again, hot names are
+            // left to the next request
+            None if pinned => (
+                self.cache
+                    .refresh(&domain)
+                    .await
+                    .map_err(anyhow::Error::new)?,
+                true,
+            ),
+            None => return Ok(None),
+        };
+
+        let mut heads = Vec::new();
+        for register in resolved.registers() {
+            // A delegated zone may not have published anything yet
+            let head = match self.cache.client().register_get(register).await {
+                Ok(head) => Some(head),
+                Err(e) if e.is_not_found() => None,
+                Err(e) => return Err(e),
+            };
+            heads.push(head);
+        }
+
+        // On first sighting, compare with the heads the cached resolution
+        // was read from: the register may have moved since
+        let previous = self
+            .heads
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or(resolved.heads());
+        let refreshed = if refreshed {
+            // Just resolved again; only a re-resolution of a known name counts
+            self.heads.contains_key(name)
+        } else if previous == heads.as_slice() {
+            false
+        } else {
+            // The new lookup reads at least up to these heads; later
+            // changes show up in the next poll
+            tracing::debug!("Register of '{}' changed, refreshing", name);
+            self.cache
+                .refresh(&domain)
+                .await
+                .map_err(anyhow::Error::new)?;
+            true
+        };
+
+        Ok(Some(Checked { heads, refreshed }))
+    }
+}
+
+/// Outcome of checking one watched name
+struct Checked {
+    /// Register heads the name's resolution now reflects
+    heads: Vec<Option<RegisterValue>>,
+    /// Whether the name was resolved again because its registers moved
+    refreshed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{MemoryNetwork, MemoryPayment};
+    use crate::register::record::RecordData;
+    use crate::register::{domain_register_key, DnsRecord, DomainRecordsDocument};
+    use crate::storage::chunks::upload_document_as_chunk;
+    use crate::testing::{register, set_records, set_target};
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn ant_record(target: &str) -> DnsRecord {
+        DnsRecord::new(".", RecordData::parse("ANT", target).unwrap())
+    }
+
+    async fn target(cache: &Arc<ResolutionCache<MemoryNetwork>>, name: &str) -> String {
+        let resolved = cache.resolve(&DomainName::parse(name).unwrap()).await;
+        resolved.unwrap().target().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_names_whose_register_changed() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+        let registration = register(&network, "test.ant", &"ab".repeat(32)).await;
+
+        let cache = Arc::new(ResolutionCache::new(
+            network,
+            Default::default(),
+            Duration::from_secs(3600),
+        ));
+        let mut watcher = RegisterWatcher::new(cache.clone(), Duration::from_secs(1));
+        assert_eq!(target(&cache, "test.ant").await, "ab".repeat(32));
+        assert_eq!(watcher.poll().await, 0);
+        assert_eq!(watcher.poll().await, 0);
+
+        let network = cache.client();
+        set_target(network, &registration, "test.ant", &"cd".repeat(32)).await;
+        assert_eq!(watcher.poll().await, 1);
+        assert_eq!(target(&cache, "test.ant").await, "cd".repeat(32));
+
+        // An entry not signed by the owner moves the head but not the target
+        let attacker = SigningKey::generate(&mut OsRng);
+        let spam = DomainRecordsDocument::sign(
+            "test.ant",
+            vec![ant_record(&"ee".repeat(32))],
+            99,
+            &attacker,
+        )
+        .unwrap();
+        let (_, spam_addr) = upload_document_as_chunk(network, &spam, payment.clone())
+            .await
+            .unwrap();
+        network
+            .register_update(
+                &domain_register_key("test.ant").unwrap(),
+                spam_addr,
+                payment,
+            )
+            .await
+            .unwrap();
+        assert_eq!(watcher.poll().await, 1);
+        assert_eq!(target(&cache, "test.ant").await, "cd".repeat(32));
+        assert_eq!(watcher.poll().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_updates_before_first_poll() {
+        let network = MemoryNetwork::new();
+        let registration = register(&network, "test.ant", &"ab".repeat(32)).await;
+
+        let cache = Arc::new(ResolutionCache::new(
+            network,
+            Default::default(),
+            Duration::from_secs(3600),
+        ));
+        let mut watcher = RegisterWatcher::new(cache.clone(), Duration::from_secs(1));
+        assert_eq!(target(&cache, "test.ant").await, "ab".repeat(32));
+
+        // Lands after the name was cached but before the watcher saw it
+        set_target(cache.client(), &registration, "test.ant", &"cd".repeat(32)).await;
+        assert_eq!(watcher.poll().await, 1);
+        assert_eq!(target(&cache, "test.ant").await, "cd".repeat(32));
+        assert_eq!(watcher.poll().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_watches_empty_delegated_zones() {
+        let network = MemoryNetwork::new();
+        let payment = MemoryPayment::unlimited();
+        let registration = register(&network, "test.ant", &"ab".repeat(32)).await;
+        let team_key = SigningKey::generate(&mut OsRng);
+        let team_hex = hex::encode(team_key.verifying_key().to_bytes());
+        let records = vec![
+            ant_record(&"ab".repeat(32)),
+            DnsRecord::new("team", RecordData::parse("DELEGATE", &team_hex).unwrap()),
+        ];
+        set_records(&network, &registration, "test.ant", records).await;
+
+        let cache = Arc::new(ResolutionCache::new(
+            network,
+            Default::default(),
+            Duration::from_secs(3600),
+        ));
+        let mut watcher = RegisterWatcher::new(cache.clone(), Duration::from_secs(1));
+        let team = DomainName::parse("team.test.ant").unwrap();
+        assert_eq!(cache.resolve(&team).await.unwrap().target(), None);
+
+        // The delegated register does not exist yet; that is not a failure
+        assert_eq!(watcher.poll().await, 0);
+        assert_eq!(watcher.heads.len(), 1);
+
+        crate::update_domain_records(
+            cache.client(),
+            "team.test.ant",
+            vec![ant_record(&"cd".repeat(32))],
+            &team_key,
+            payment,
+        )
+        .await
+        .unwrap();
+        assert_eq!(watcher.poll().await, 1);
+        assert_eq!(target(&cache, "team.test.ant").await, "cd".repeat(32));
+    }
+
+    #[tokio::test]
+    async fn test_keeps_pinned_names_resolved() {
+        let network = MemoryNetwork::new();
+        for domain in ["pinned.ant", "other.ant"] {
+            register(&network, domain, &"ab".repeat(32)).await;
+        }
+
+        let cache = Arc::new(ResolutionCache::new(
+            network,
+            Default::default(),
+            Duration::from_secs(3600),
+        ));
+        let mut watcher = RegisterWatcher::new(cache.clone(), Duration::from_secs(1))
+            .with_pins(vec![DomainName::parse("pinned.ant").unwrap()])
+            .with_max_hot(0);
+
+        // Pins are resolved by the watcher; other names only on request
+        assert_eq!(watcher.poll().await, 0);
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(target(&cache, "pinned.ant").await, "ab".repeat(32));
+        assert_eq!(cache.stats().hits, 1);
+
+        target(&cache, "other.ant").await;
+        assert_eq!(watcher.poll().await, 0);
+        assert_eq!(watcher.heads.len(), 1);
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! On-disk checkpoints of verified register histories
+//!
+//! A checkpoint remembers how far a domain's register history has been
+//! processed, the owner key and the current records document at that point.
+//! Register histories are append-only, so a later lookup only has to download
+//! and verify the entries after the checkpoint.
+//!
+//! Each file stores a SHA-256 checksum followed by the JSON checkpoint.
+//! Checkpoints that fail the checksum, belong to a different register or
+//! contain documents that no longer verify are discarded and rebuilt from
+//! the network.
+
+use crate::constants::{CHECKPOINT_CACHE_MAX_BYTES, CHECKPOINT_FORMAT_VERSION};
+use crate::register::{DomainOwnerDocument, DomainRecordsDocument};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Verified state of a register history after `processed_entries` entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub format_version: u32,
+    pub domain: String,
+    /// Register address (hex) the checkpoint was built from
+    pub register_address: String,
+    /// Chunk address (hex) of the owner document, the first register entry
+    pub owner_chunk_address: String,
+    pub owner: DomainOwnerDocument,
+    /// Number of register entries processed, including the owner entry
+    pub processed_entries: usize,
+    /// Current records document at this point of the history
+    pub current: Option<DomainRecordsDocument>,
+}
+
+impl Checkpoint {
+    /// Whether the checkpoint's documents still verify against its owner key
+    ///
+    /// Guards against edited files; checkpoints only ever hold documents that
+    /// verify in strict mode.
+    pub fn verify(&self) -> bool {
+        let Ok(owner_key) = crate::register::lookup::parse_owner_key(&self.domain, &self.owner)
+        else {
+            return false;
+        };
+
+        self.current
+            .as_ref()
+            .is_none_or(|doc| doc.verify(&self.domain, &owner_key))
+    }
+}
+
+/// Directory of checkpoint files with a total size cap
+#[derive(Debug, Clone)]
+pub struct CheckpointCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl CheckpointCache {
+    /// Cache in `dir`, evicting the oldest checkpoints above `max_bytes`
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// Cache in the default location with the default size cap
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(get_checkpoint_dir()?, CHECKPOINT_CACHE_MAX_BYTES))
+    }
+
+    /// Directory holding the checkpoint files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path_for(&self, register_address: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", register_address))
+    }
+
+    /// Load the checkpoint for a register, discarding it if it fails integrity checks
+    pub fn load(&self, domain: &str, register_address: &str) -> Option<Checkpoint> {
+        let path = self.path_for(register_address);
+        let data = std::fs::read(&path).ok()?;
+
+        match decode(&data) {
+            Some(checkpoint)
+                if checkpoint.format_version == CHECKPOINT_FORMAT_VERSION
+                    && checkpoint.domain == domain
+                    && checkpoint.register_address == register_address
+                    && checkpoint.verify() =>
+            {
+                Some(checkpoint)
+            }
+            _ => {
+                tracing::warn!(
+                    "Discarding invalid checkpoint for '{}' at {}",
+                    domain,
+                    path.display()
+                );
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Write a checkpoint, then evict old checkpoints above the size cap
+    pub fn store(&self, checkpoint: &Checkpoint) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).context("Failed to create checkpoint directory")?;
+
+        let path = self.path_for(&checkpoint.register_address);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, encode(checkpoint)?).context("Failed to write checkpoint")?;
+        std::fs::rename(&tmp, &path).context("Failed to write checkpoint")?;
+
+        self.evict(&path)
+    }
+
+    /// Remove the checkpoint for a register, if any
+    pub fn remove(&self, register_address: &str) -> Result<()> {
+        match std::fs::remove_file(self.path_for(register_address)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).context("Failed to remove checkpoint")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove all checkpoints and return how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let files = self.files()?;
+        for (path, _, _) in &files {
+            std::fs::remove_file(path).context("Failed to remove checkpoint")?;
+        }
+        Ok(files.len())
+    }
+
+    /// Number of checkpoints and their total size in bytes
+    pub fn usage(&self) -> Result<(usize, u64)> {
+        let files = self.files()?;
+        let bytes = files.iter().map(|(_, size, _)| size).sum();
+        Ok((files.len(), bytes))
+    }
+
+    /// Checkpoint files with their size and modification time
+    fn files(&self) -> Result<Vec<(PathBuf, u64, std::time::SystemTime)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.dir).context("Failed to read checkpoint directory")? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+            files.push((path, metadata.len(), modified));
+        }
+
+        Ok(files)
+    }
+
+    /// Delete least recently written checkpoints until the cache fits its cap
+    fn evict(&self, keep: &Path) -> Result<()> {
+        let mut files = self.files()?;
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            tracing::debug!("Evicting checkpoint {}", path.display());
+            std::fs::remove_file(&path).context("Failed to evict checkpoint")?;
+            total = total.saturating_sub(size);
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the directory where history checkpoints are stored
+pub fn get_checkpoint_dir() -> Result<PathBuf> {
+    let home = directories::BaseDirs::new().context("Failed to determine home directory")?;
+
+    Ok(home.cache_dir().join("antns").join("checkpoints"))
+}
+
+fn checksum(payload: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, payload))
+}
+
+/// Serialize as `<sha256 hex>\n<json>`
+fn encode(checkpoint: &Checkpoint) -> Result<Vec<u8>> {
+    let payload = serde_json::to_vec(checkpoint).context("Failed to serialize checkpoint")?;
+
+    let mut data = checksum(&payload).into_bytes();
+    data.push(b'\n');
+    data.extend_from_slice(&payload);
+    Ok(data)
+}
+
+fn decode(data: &[u8]) -> Option<Checkpoint> {
+    let split = data.iter().position(|&b| b == b'\n')?;
+    let (expected, payload) = (&data[..split], &data[split + 1..]);
+
+    if expected != checksum(payload).as_bytes() {
+        return None;
+    }
+
+    serde_json::from_slice(payload).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register::DnsRecord;
+    use crate::testing::TempDir;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+
+    fn checkpoint(register_address: &str, key: &SigningKey) -> Checkpoint {
+        let records = vec![DnsRecord {
+            record_type: "ant".to_string(),
+            name: ".".to_string(),
+            value: "abc123".to_string(),
+            ttl: None,
+        }];
+        let doc = DomainRecordsDocument::sign("test.ant", records, 1, key).unwrap();
+
+        Checkpoint {
+            format_version: CHECKPOINT_FORMAT_VERSION,
+            domain: "test.ant".to_string(),
+            register_address: register_address.to_string(),
+            owner_chunk_address: hex::encode([1u8; 32]),
+            owner: DomainOwnerDocument {
+                public_key: hex::encode(key.verifying_key().to_bytes()),
+            },
+            processed_entries: 2,
+            current: Some(doc),
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip_and_integrity() {
+        let dir = TempDir::new("checkpoints");
+        let cache = CheckpointCache::new(dir.path(), u64::MAX);
+        let key = SigningKey::generate(&mut OsRng);
+
+        cache.store(&checkpoint("aa", &key)).unwrap();
+        let loaded = cache.load("test.ant", "aa").unwrap();
+        assert_eq!(loaded.processed_entries, 2);
+
+        // Wrong domain for this register
+        assert!(cache.load("other.ant", "aa").is_none());
+        cache.store(&checkpoint("aa", &key)).unwrap();
+
+        // Tampered file is discarded
+        cache.store(&checkpoint("bb", &key)).unwrap();
+        let path = dir.path().join("bb.json");
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"processed_entries\":2", "\"processed_entries\":9");
+        std::fs::write(&path, tampered).unwrap();
+        assert!(cache.load("test.ant", "bb").is_none());
+        assert!(!path.exists());
+
+        // Document signed by someone else than the cached owner
+        let mut forged = checkpoint("cc", &key);
+        forged.owner.public_key =
+            hex::encode(SigningKey::generate(&mut OsRng).verifying_key().to_bytes());
+        cache.store(&forged).unwrap();
+        assert!(cache.load("test.ant", "cc").is_none());
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.usage().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_checkpoint_size_cap() {
+        let dir = TempDir::new("checkpoints-cap");
+        let key = SigningKey::generate(&mut OsRng);
+        let size = encode(&checkpoint("aa", &key)).unwrap().len() as u64;
+
+        // Room for two checkpoints
+        let cache = CheckpointCache::new(dir.path(), size * 2 + size / 2);
+        for address in ["aa", "bb", "cc"] {
+            cache.store(&checkpoint(address, &key)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        let (count, bytes) = cache.usage().unwrap();
+        assert_eq!(count, 2);
+        assert!(bytes <= size * 2 + size / 2);
+        assert!(cache.load("test.ant", "aa").is_none());
+        assert!(cache.load("test.ant", "cc").is_some());
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! On-disk cache of content served by the HTTP proxy
+//!
+//! Entries are keyed by target address and path. A target is a content
+//! address, so the bytes at a path of it never change: cached entries are
+//! served without asking the network or the upstream again, and only the
+//! size cap removes them. Reading an entry refreshes its modification time,
+//! so the least recently used entries are evicted first.
+//!
+//! Each file stores a JSON header line followed by the body. The header
+//! holds the body's SHA-256, which doubles as its strong ETag unless the
+//! upstream sent its own; files whose body no longer matches are discarded.
+
+use crate::constants::{CONTENT_CACHE_MAX_BYTES, CONTENT_CACHE_MAX_ENTRY_BYTES};
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File extension of cache entries
+const ENTRY_EXTENSION: &str = "content";
+
+/// Response headers kept with cached content
+pub const CACHED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-encoding",
+    "content-language",
+    "content-disposition",
+    "last-modified",
+    "etag",
+];
+
+/// Content at a path of a target, with the headers describing it
+#[derive(Debug, Clone)]
+pub struct CachedContent {
+    /// Headers from [`CACHED_HEADERS`]
+    pub headers: Vec<(String, String)>,
+    pub data: Bytes,
+    etag: String,
+}
+
+impl CachedContent {
+    pub fn new(headers: Vec<(String, String)>, data: Bytes) -> Self {
+        Self {
+            etag: checksum(&data),
+            headers,
+            data,
+        }
+    }
+
+    /// Entity tag of the body, quoted as in the `ETag` header
+    ///
+    /// The upstream's own tag if it sent one, so clients revalidating with
+    /// the tag they got before the content was cached still match. A weak
+    /// upstream tag cannot validate range requests, so the body's checksum
+    /// is used instead.
+    pub fn etag(&self) -> String {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("etag"))
+            .map(|(_, value)| value.clone())
+            .filter(|value| !value.starts_with("W/"))
+            .unwrap_or_else(|| format!("\"{}\"", self.etag))
+    }
+}
+
+/// First line of a cache file
+#[derive(Serialize, Deserialize)]
+struct EntryHeader {
+    target: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    /// SHA-256 (hex) of the body
+    sha256: String,
+}
+
+/// Directory of cached content with a total size cap
+#[derive(Debug, Clone)]
+pub struct ContentCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ContentCache {
+    /// Cache in `dir`, evicting the least recently used entries above `max_bytes`
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// Cache in the default location with the default size cap
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(get_content_cache_dir()?, CONTENT_CACHE_MAX_BYTES))
+    }
+
+    /// Directory holding the cache files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Largest body worth caching: bigger ones would evict too much
+    pub fn max_entry_bytes(&self) -> u64 {
+        CONTENT_CACHE_MAX_ENTRY_BYTES.min(self.max_bytes / 4)
+    }
+
+    fn path_for(&self, target: &str, path: &str) -> PathBuf {
+        let key = checksum(format!("{}\n{}", target, path).as_bytes());
+        self.dir.join(format!("{}.{}", key, ENTRY_EXTENSION))
+    }
+
+    /// Load the content at `path` of `target`, discarding it if it is corrupt
+    pub fn get(&self, target: &str, path: &str) -> Option<CachedContent> {
+        let file = self.path_for(target, path);
+        let data = std::fs::read(&file).ok()?;
+
+        match decode(&data) {
+            Some((header, body)) if header.target == target && header.path == path => {
+                // Mark as recently used for eviction
+                let _ = std::fs::File::options()
+                    .append(true)
+                    .open(&file)
+                    .and_then(|f| f.set_modified(std::time::SystemTime::now()));
+                Some(CachedContent {
+                    headers: header.headers,
+                    data: Bytes::copy_from_slice(body),
+                    etag: header.sha256,
+                })
+            }
+            _ => {
+                tracing::warn!("Discarding invalid cached content at {}", file.display());
+                let _ = std::fs::remove_file(&file);
+                None
+            }
+        }
+    }
+
+    /// Write the content at `path` of `target`, then evict entries above the size cap
+    ///
+    /// Bodies larger than [`Self::max_entry_bytes`] are not cached.
+    pub fn store(&self, target: &str, path: &str, content: &CachedContent) -> Result<()> {
+        if content.data.len() as u64 > self.max_entry_bytes() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.dir).context("Failed to create content cache directory")?;
+
+        let header = EntryHeader {
+            target: target.to_string(),
+            path: path.to_string(),
+            headers: content.headers.clone(),
+            sha256: content.etag.clone(),
+        };
+        let mut data = serde_json::to_vec(&header).context("Failed to serialize cache entry")?;
+        data.push(b'\n');
+        data.extend_from_slice(&content.data);
+
+        let file = self.path_for(target, path);
+        let tmp = file.with_extension("tmp");
+        std::fs::write(&tmp, data).context("Failed to write cached content")?;
+        std::fs::rename(&tmp, &file).context("Failed to write cached content")?;
+
+        self.evict(&file)
+    }
+
+    /// Remove all cached content and return how many entries were removed
+    pub fn clear(&self) -> Result<usize> {
+        let files = self.files()?;
+        for (path, _, _) in &files {
+            std::fs::remove_file(path).context("Failed to remove cached content")?;
+        }
+        Ok(files.len())
+    }
+
+    /// Number of entries and their total size in bytes
+    pub fn usage(&self) -> Result<(usize, u64)> {
+        let files = self.files()?;
+        let bytes = files.iter().map(|(_, size, _)| size).sum();
+        Ok((files.len(), bytes))
+    }
+
+    /// Cache files with their size and modification time
+    fn files(&self) -> Result<Vec<(PathBuf, u64, std::time::SystemTime)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in
+            std::fs::read_dir(&self.dir).context("Failed to read content cache directory")?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+            files.push((path, metadata.len(), modified));
+        }
+
+        Ok(files)
+    }
+
+    /// Delete least recently used entries until the cache fits its cap
+    fn evict(&self, keep: &Path) -> Result<()> {
+        let mut files = self.files()?;
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            tracing::debug!("Evicting cached content {}", path.display());
+            // Another request may have evicted it already
+            let _ = std::fs::remove_file(&path);
+            total = total.saturating_sub(size);
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the directory where proxied content is cached
+pub fn get_content_cache_dir() -> Result<PathBuf> {
+    let home = directories::BaseDirs::new().context("Failed to determine home directory")?;
+
+    Ok(home.cache_dir().join("antns").join("content"))
+}
+
+fn checksum(data: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, data))
+}
+
+/// Split a cache file into its header and body, checking the body's checksum
+fn decode(data: &[u8]) -> Option<(EntryHeader, &[u8])> {
+    let split = data.iter().position(|&b| b == b'\n')?;
+    let header: EntryHeader = serde_json::from_slice(&data[..split]).ok()?;
+    let body = &data[split + 1..];
+
+    (checksum(body) == header.sha256).then_some((header, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn content(body: &'static [u8]) -> CachedContent {
+        let headers = vec![("content-type".to_string(), "text/plain".to_string())];
+        CachedContent::new(headers, Bytes::from_static(body))
+    }
+
+    #[test]
+    fn test_content_roundtrip_and_integrity() {
+        let dir = TempDir::new("content");
+        let cache = ContentCache::new(dir.path(), u64::MAX);
+        let target = "ab".repeat(32);
+
+        cache.store(&target, "/", &content(b"home")).unwrap();
+        let cached = cache.get(&target, "/").unwrap();
+        assert_eq!(cached.data, "home");
+        assert_eq!(cached.headers, content(b"home").headers);
+        assert_eq!(cached.etag(), content(b"home").etag());
+        assert_ne!(cached.etag(), content(b"other").etag());
+        assert!(cache.get(&target, "/other").is_none());
+        assert!(cache.get(&"cd".repeat(32), "/").is_none());
+
+        // Tampered body is discarded
+        let file = cache.path_for(&target, "/");
+        let mut data = std::fs::read(&file).unwrap();
+        *data.last_mut().unwrap() = b'!';
+        std::fs::write(&file, data).unwrap();
+        assert!(cache.get(&target, "/").is_none());
+        assert!(!file.exists());
+
+        cache.store(&target, "/", &content(b"home")).unwrap();
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.usage().unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn test_weak_upstream_etag_is_replaced() {
+        let tagged = |etag: &str| {
+            let headers = vec![("ETag".to_string(), etag.to_string())];
+            CachedContent::new(headers, Bytes::from_static(b"home"))
+        };
+
+        assert_eq!(tagged("\"v1\"").etag(), "\"v1\"");
+        assert_eq!(tagged("W/\"v1\"").etag(), content(b"home").etag());
+        assert!(!content(b"home").etag().starts_with("W/"));
+    }
+
+    #[test]
+    fn test_content_size_cap_evicts_least_recently_used() {
+        let dir = TempDir::new("content-cap");
+        let target = "ab".repeat(32);
+        let probe = ContentCache::new(dir.path(), u64::MAX);
+        probe.store(&target, "/a", &content(b"0123456789")).unwrap();
+        let (_, size) = probe.usage().unwrap();
+        probe.clear().unwrap();
+
+        // Room for two entries
+        let cache = ContentCache::new(dir.path(), size * 2 + size / 2);
+        for path in ["/a", "/b"] {
+            cache.store(&target, path, &content(b"0123456789")).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        // Reading "/a" makes "/b" the least recently used
+        assert!(cache.get(&target, "/a").is_some());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store(&target, "/c", &content(b"0123456789")).unwrap();
+
+        assert_eq!(cache.usage().unwrap().0, 2);
+        assert!(cache.get(&target, "/a").is_some());
+        assert!(cache.get(&target, "/b").is_none());
+        assert!(cache.get(&target, "/c").is_some());
+
+        // Too large for the cap: not cached at all
+        let tiny = ContentCache::new(dir.path(), 8);
+        tiny.store(&target, "/d", &content(b"0123456789")).unwrap();
+        assert!(cache.get(&target, "/d").is_none());
+    }
+}
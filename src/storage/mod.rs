@@ -3,8 +3,12 @@
 
 //! Storage operations for chunks and local data
 
+pub mod checkpoint;
 pub mod chunks;
+pub mod content;
 pub mod local;
 
+pub use checkpoint::{Checkpoint, CheckpointCache};
 pub use chunks::{download_document_from_chunk, upload_document_as_chunk};
+pub use content::{CachedContent, ContentCache};
 pub use local::{get_domain_keys_dir, list_local_domains};
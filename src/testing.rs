@@ -0,0 +1,100 @@
+// Copyright 2025 AntNS Contributors
+// Licensed under GPL-3.0
+
+//! Helpers shared by unit tests
+
+use crate::network::{MemoryNetwork, MemoryPayment};
+use crate::register::record::RecordData;
+use crate::register::{DnsRecord, DomainRegistration};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary directories handed out by this process so far
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary directory that is removed again when dropped
+///
+/// The directory itself is not created, so code under test starts from a
+/// missing directory. Dropping also happens while a failing test unwinds,
+/// so nothing is left behind in the system's temporary directory.
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Fresh path named after `name`, unique to this process and call
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "antns-{}-{}-{}",
+            name,
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        Self { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Register `domain` with an `ANT` record pointing at `target`
+pub(crate) async fn register(
+    network: &MemoryNetwork,
+    domain: &str,
+    target: &str,
+) -> DomainRegistration {
+    register_with_records(network, domain, vec![root_target(target)]).await
+}
+
+/// Register `domain` and publish `records` as its first document
+pub(crate) async fn register_with_records(
+    network: &MemoryNetwork,
+    domain: &str,
+    records: Vec<DnsRecord>,
+) -> DomainRegistration {
+    let registration = crate::register_domain(network, domain, MemoryPayment::unlimited())
+        .await
+        .unwrap();
+    set_records(network, &registration, domain, records).await;
+    registration
+}
+
+/// Publish a document whose root `ANT` record points at `target`
+pub(crate) async fn set_target(
+    network: &MemoryNetwork,
+    registration: &DomainRegistration,
+    domain: &str,
+    target: &str,
+) {
+    set_records(network, registration, domain, vec![root_target(target)]).await;
+}
+
+/// Publish a document holding `records`, signed by the owner key
+pub(crate) async fn set_records(
+    network: &MemoryNetwork,
+    registration: &DomainRegistration,
+    domain: &str,
+    records: Vec<DnsRecord>,
+) {
+    crate::update_domain_records(
+        network,
+        domain,
+        records,
+        &registration.owner_key,
+        MemoryPayment::unlimited(),
+    )
+    .await
+    .unwrap();
+}
+
+fn root_target(target: &str) -> DnsRecord {
+    DnsRecord::new(".", RecordData::parse("ANT", target).unwrap())
+}
@@ -3,13 +3,14 @@
 
 //! Vault backup and restore operations for domain keypairs
 
-use anyhow::{Context, Result};
-use autonomi::client::payment::PaymentOption;
+use crate::error::Result;
+use crate::network::NameNetwork;
+use anyhow::Context;
 use autonomi::client::vault::{vault_content_type_from_app_name, vault_derive_key};
-use autonomi::Client;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 const ANTNS_VAULT_CONTENT_TYPE: &str = "antns_keys";
 
@@ -25,24 +26,35 @@ struct KeysBackup {
 }
 
 /// Backup all domain keypairs to the vault
-pub async fn backup_keys(
-    client: &Client,
+pub async fn backup_keys<N: NameNetwork>(
+    client: &N,
     wallet_private_key: &str,
-    payment: PaymentOption,
+    payment: N::Payment,
 ) -> Result<()> {
-    println!("Collecting domain keypairs...");
-
-    // Get all domain keys
     let keys_dir = crate::storage::local::get_domain_keys_dir()?;
+    backup_keys_from_dir(client, &keys_dir, wallet_private_key, payment).await
+}
+
+/// Backup all domain keypairs found in `keys_dir` to the vault
+pub async fn backup_keys_from_dir<N: NameNetwork>(
+    client: &N,
+    keys_dir: &Path,
+    wallet_private_key: &str,
+    payment: N::Payment,
+) -> Result<()> {
+    println!("Collecting domain keypairs...");
 
     if !keys_dir.exists() {
-        anyhow::bail!("No domain keys directory found. Have you registered any domains?");
+        return Err(anyhow::anyhow!(
+            "No domain keys directory found. Have you registered any domains?"
+        )
+        .into());
     }
 
     let mut keys_map = HashMap::new();
 
     // Read all domain-key-*.txt files
-    for entry in std::fs::read_dir(&keys_dir).context("Failed to read keys directory")? {
+    for entry in std::fs::read_dir(keys_dir).context("Failed to read keys directory")? {
         let entry = entry.context("Failed to read directory entry")?;
         let path = entry.path();
 
@@ -66,7 +78,7 @@ pub async fn backup_keys(
     }
 
     if keys_map.is_empty() {
-        anyhow::bail!("No domain keys found to backup");
+        return Err(anyhow::anyhow!("No domain keys found to backup").into());
     }
 
     println!("\nBacking up {} domain key(s)...", keys_map.len());
@@ -93,8 +105,7 @@ pub async fn backup_keys(
     // Store in vault
     let cost = client
         .vault_put(backup_bytes, payment, &vault_key, content_type)
-        .await
-        .context("Failed to store backup in vault")?;
+        .await?;
 
     println!("\n✓ Backup stored in vault successfully!");
     println!("Cost: {} AttoTokens", cost);
@@ -105,7 +116,17 @@ pub async fn backup_keys(
 }
 
 /// Restore domain keypairs from the vault
-pub async fn restore_keys(client: &Client, wallet_private_key: &str) -> Result<()> {
+pub async fn restore_keys<N: NameNetwork>(client: &N, wallet_private_key: &str) -> Result<()> {
+    let keys_dir = crate::storage::local::get_domain_keys_dir()?;
+    restore_keys_to_dir(client, &keys_dir, wallet_private_key).await
+}
+
+/// Restore domain keypairs from the vault into `keys_dir`
+pub async fn restore_keys_to_dir<N: NameNetwork>(
+    client: &N,
+    keys_dir: &Path,
+    wallet_private_key: &str,
+) -> Result<()> {
     println!("Fetching backup from vault...");
 
     // Derive vault key from wallet private key
@@ -113,10 +134,7 @@ pub async fn restore_keys(client: &Client, wallet_private_key: &str) -> Result<(
         vault_derive_key(wallet_private_key).context("Failed to derive vault key from wallet")?;
 
     // Get from vault
-    let (backup_bytes, _content_type) = client
-        .vault_get(&vault_key)
-        .await
-        .context("Failed to retrieve backup from vault. Have you created a backup yet?")?;
+    let (backup_bytes, _content_type) = client.vault_get(&vault_key).await?;
 
     // Deserialize backup
     let backup_json =
@@ -129,8 +147,7 @@ pub async fn restore_keys(client: &Client, wallet_private_key: &str) -> Result<(
     println!("Restoring {} domain key(s)...", backup.keys.len());
 
     // Ensure keys directory exists
-    let keys_dir = crate::storage::local::get_domain_keys_dir()?;
-    std::fs::create_dir_all(&keys_dir).context("Failed to create keys directory")?;
+    std::fs::create_dir_all(keys_dir).context("Failed to create keys directory")?;
 
     // Restore each keypair
     for (domain, key_hex) in backup.keys.iter() {
@@ -143,7 +160,7 @@ pub async fn restore_keys(client: &Client, wallet_private_key: &str) -> Result<(
             .with_context(|| format!("Failed to create keypair for domain: {}", domain))?;
 
         // Save to local storage
-        crate::crypto::save_keypair(domain, &keypair)
+        crate::crypto::save_keypair_in(keys_dir, domain, &keypair)
             .with_context(|| format!("Failed to save keypair for domain: {}", domain))?;
 
         println!("  Restored: {}", domain);